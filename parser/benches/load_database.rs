@@ -0,0 +1,48 @@
+use std::path::{Path, PathBuf};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use parser::compiled_license_database::{self, CompiledLicenseDatabase};
+use parser::license_database;
+use parser::license_expression_parser::LicenseExpressionParser;
+
+fn index_path() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("..").join(license_database::DEFAULT_INDEX_PATH)
+}
+
+fn bench_load_database(c: &mut Criterion) {
+    let index_path = index_path();
+    let compiled_path = std::env::temp_dir().join("license-expression-copyleft-bench.lxcdb");
+    compiled_license_database::compile_license_index_file(&index_path, &compiled_path)
+        .expect("failed to compile license index");
+
+    let mut group = c.benchmark_group("load_database");
+    group.bench_function("json_index", |b| {
+        b.iter(|| {
+            let index = license_database::load_license_index(black_box(&index_path)).unwrap();
+            license_database::build_license_db(&index.licenses)
+        })
+    });
+    group.bench_function("compiled_mmap", |b| {
+        b.iter(|| CompiledLicenseDatabase::open(black_box(&compiled_path)).unwrap())
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("parser_new");
+    group.bench_function("json_index", |b| {
+        b.iter(|| LicenseExpressionParser::with_database::<&Path>(black_box(&index_path), &[]).unwrap())
+    });
+    group.bench_function("compiled_mmap", |b| {
+        b.iter(|| LicenseExpressionParser::with_database::<&Path>(black_box(&compiled_path), &[]).unwrap())
+    });
+    group.finish();
+
+    let json_parser = LicenseExpressionParser::with_database::<&Path>(&index_path, &[]).unwrap();
+    let compiled_parser = LicenseExpressionParser::with_database::<&Path>(&compiled_path, &[]).unwrap();
+    let expression = "(GPL-2.0-only OR Apache-2.0) AND MIT";
+    let mut group = c.benchmark_group("analyze");
+    group.bench_function("json_index", |b| b.iter(|| json_parser.analyze(black_box(expression))));
+    group.bench_function("compiled_mmap", |b| b.iter(|| compiled_parser.analyze(black_box(expression))));
+    group.finish();
+}
+
+criterion_group!(benches, bench_load_database);
+criterion_main!(benches);
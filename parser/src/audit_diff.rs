@@ -0,0 +1,358 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::cargo_lock::{self, DEFAULT_VENDOR_DIR};
+use crate::cargo_metadata;
+use crate::cyclonedx_bom;
+use crate::inventory::{DependencyKind, InventoryError};
+use crate::license_audit::{audit_inventory, AuditReport, ComponentAudit};
+use crate::license_expression_parser::LicenseExpressionParser;
+use crate::license_policy::LicensePolicy;
+use crate::models::{new_copyleft_strength_order, risk_level_order, NewCopyleftStrength, RiskLevel};
+use crate::npm_packages;
+use crate::scancode_results::{self, ScanCodeOptions};
+use crate::spdx_document;
+
+/// License state of a component on one side of a diff
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComponentLicense {
+    pub name: String,
+    pub version: Option<String>,
+    pub kind: DependencyKind,
+    pub expression: Option<String>,
+    pub risk_level: RiskLevel,
+    /// None if the component declares no license
+    pub strongest_copyleft: Option<NewCopyleftStrength>,
+}
+
+/// A component whose license expression differs between the two audits
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LicenseChange {
+    pub old: ComponentLicense,
+    pub new: ComponentLicense,
+    /// The risk level or the strongest copyleft category went up
+    pub risk_increased: bool,
+}
+
+/// Differences between two audits of the same project
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditDiff {
+    pub old_source: String,
+    pub new_source: String,
+    pub added: Vec<ComponentLicense>,
+    pub removed: Vec<ComponentLicense>,
+    pub license_changes: Vec<LicenseChange>,
+    pub old_overall_risk: RiskLevel,
+    pub new_overall_risk: RiskLevel,
+}
+
+impl ComponentLicense {
+    fn from_audit(audit: &ComponentAudit) -> Self {
+        ComponentLicense {
+            name: audit.component.name.clone(),
+            version: audit.component.version.clone(),
+            kind: audit.component.kind,
+            expression: audit.component.expression.clone(),
+            risk_level: audit.risk_level(),
+            strongest_copyleft: audit.analysis.as_ref().map(|a| a.strongest_copyleft.clone()),
+        }
+    }
+
+    fn display_name(&self) -> String {
+        match self.version {
+            Some(ref version) => format!("{}@{}", self.name, version),
+            None => self.name.clone(),
+        }
+    }
+
+    fn describe(&self) -> String {
+        let copyleft = self.strongest_copyleft.as_ref().map(|s| s.to_string()).unwrap_or_else(|| "No License".to_string());
+        format!("{} [{}, {}]", self.expression.as_deref().unwrap_or("NO LICENSE"), self.risk_level, copyleft)
+    }
+}
+
+/// Expressions compared ignoring whitespace
+fn same_expression(a: Option<&str>, b: Option<&str>) -> bool {
+    let normalize = |e: Option<&str>| e.map(|e| e.split_whitespace().collect::<Vec<_>>().join(" "));
+    normalize(a) == normalize(b)
+}
+
+fn copyleft_order(strength: Option<&NewCopyleftStrength>) -> u8 {
+    strength.map(new_copyleft_strength_order).unwrap_or(new_copyleft_strength_order(&NewCopyleftStrength::UnstatedLicense))
+}
+
+/// Compares two audits component by component
+///
+/// Components are matched by name, so a version bump is a change rather than a removal and an
+/// addition. When several versions of a name exist, equal versions are paired first.
+pub fn diff_audits(old: &AuditReport, new: &AuditReport) -> AuditDiff {
+    let by_name = |report: &AuditReport| {
+        let mut map: BTreeMap<String, Vec<ComponentLicense>> = BTreeMap::new();
+        for audit in &report.components {
+            map.entry(audit.component.name.clone()).or_default().push(ComponentLicense::from_audit(audit));
+        }
+        map
+    };
+    let old_by_name = by_name(old);
+    let mut new_by_name = by_name(new);
+
+    let mut diff = AuditDiff {
+        old_source: old.source.clone(),
+        new_source: new.source.clone(),
+        added: Vec::new(),
+        removed: Vec::new(),
+        license_changes: Vec::new(),
+        old_overall_risk: old.overall_risk.clone(),
+        new_overall_risk: new.overall_risk.clone(),
+    };
+
+    for (name, mut old_versions) in old_by_name {
+        let mut new_versions = new_by_name.remove(&name).unwrap_or_default();
+        let mut pairs = Vec::new();
+        old_versions.retain(|old_component| {
+            match new_versions.iter().position(|c| c.version == old_component.version) {
+                Some(index) => {
+                    pairs.push((old_component.clone(), new_versions.remove(index)));
+                    false
+                }
+                None => true,
+            }
+        });
+        let unpaired = old_versions.len().min(new_versions.len());
+        pairs.extend(old_versions.drain(..unpaired).zip(new_versions.drain(..unpaired)));
+        diff.removed.extend(old_versions);
+        diff.added.extend(new_versions);
+
+        for (old_component, new_component) in pairs {
+            if same_expression(old_component.expression.as_deref(), new_component.expression.as_deref()) {
+                continue;
+            }
+            let risk_increased = risk_level_order(&new_component.risk_level) > risk_level_order(&old_component.risk_level)
+                || copyleft_order(new_component.strongest_copyleft.as_ref()) > copyleft_order(old_component.strongest_copyleft.as_ref());
+            diff.license_changes.push(LicenseChange {
+                old: old_component,
+                new: new_component,
+                risk_increased,
+            });
+        }
+    }
+    diff.added.extend(new_by_name.into_values().flatten());
+    diff.added.sort_by(|a, b| a.name.cmp(&b.name));
+    diff
+}
+
+impl AuditDiff {
+    /// Whether a license change raised a component's risk or the overall risk went up
+    pub fn has_risk_increase(&self) -> bool {
+        self.license_changes.iter().any(|c| c.risk_increased)
+            || risk_level_order(&self.new_overall_risk) > risk_level_order(&self.old_overall_risk)
+    }
+}
+
+fn is_file_named(path: &Path, name: &str) -> bool {
+    path.file_name().is_some_and(|n| n == name)
+}
+
+/// Audits an inventory file, or loads a saved audit, for diffing
+///
+/// Accepts JSON audit reports written with `--format json` (also wrapped in an `audit` field),
+/// CycloneDX and SPDX SBOMs, `cargo metadata` output, ScanCode results, Cargo.lock and
+/// package-lock.json. Inventories are audited without a policy.
+pub fn load_audit(parser: &LicenseExpressionParser, path: &Path) -> Result<AuditReport, InventoryError> {
+    let policy = LicensePolicy::default();
+    let parse_error = |e: String| InventoryError::ParseError(format!("{}: {}", path.display(), e));
+    if is_file_named(path, "Cargo.lock") {
+        let vendor = path.parent().unwrap_or(Path::new("")).join(DEFAULT_VENDOR_DIR);
+        return Ok(audit_inventory(parser, &cargo_lock::inventory_from_cargo_lock(path, &vendor)?, &policy));
+    }
+    if is_file_named(path, "package-lock.json") {
+        return Ok(audit_inventory(parser, &npm_packages::inventory_from_package_lock(path)?, &policy));
+    }
+
+    let content = fs::read_to_string(path)
+        .map_err(|e| InventoryError::FileReadError(format!("{}: {}", path.display(), e)))?;
+    let trimmed = content.trim_start();
+    if trimmed.starts_with('<') {
+        return Ok(audit_inventory(parser, &cyclonedx_bom::inventory_from_cyclonedx(&parser.database(), path)?, &policy));
+    }
+    if content.contains("SPDXVersion:") {
+        let document = spdx_document::read_spdx_document(path)?;
+        return Ok(spdx_document::analyze_spdx_document(parser, &document, &policy, None, &path.display().to_string()).audit);
+    }
+
+    let unsupported = || parse_error("not an audit report or a supported SBOM".to_string());
+    let value: Value = serde_json::from_str(&content).map_err(|_| unsupported())?;
+    let saved = value.get("audit").filter(|audit| audit.is_object()).unwrap_or(&value);
+    if saved.get("verdict").is_some() && saved.get("components").is_some() {
+        return serde_json::from_value(saved.clone()).map_err(|e| parse_error(e.to_string()));
+    }
+    let inventory = if value.get("bomFormat").is_some() {
+        cyclonedx_bom::inventory_from_cyclonedx(&parser.database(), path)?
+    } else if value.get("spdxVersion").is_some() {
+        let document = spdx_document::read_spdx_document(path)?;
+        return Ok(spdx_document::analyze_spdx_document(parser, &document, &policy, None, &path.display().to_string()).audit);
+    } else if value.get("workspace_members").is_some() {
+        cargo_metadata::inventory_from_cargo_metadata(&content, &path.display().to_string())?
+    } else if value.get("files").is_some() {
+        scancode_results::inventory_from_scancode(path, &ScanCodeOptions::default())?
+    } else {
+        return Err(unsupported());
+    };
+    Ok(audit_inventory(parser, &inventory, &policy))
+}
+
+impl fmt::Display for AuditDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "License Diff: {} -> {}", self.old_source, self.new_source)?;
+        if !self.added.is_empty() {
+            writeln!(f, "ADDED ({}):", self.added.len())?;
+            for component in &self.added {
+                writeln!(f, "  + {}: {} [{}]", component.display_name(), component.describe(), component.kind)?;
+            }
+        }
+        if !self.removed.is_empty() {
+            writeln!(f, "REMOVED ({}):", self.removed.len())?;
+            for component in &self.removed {
+                writeln!(f, "  - {}: {} [{}]", component.display_name(), component.describe(), component.kind)?;
+            }
+        }
+        if !self.license_changes.is_empty() {
+            writeln!(f, "LICENSE CHANGES ({}):", self.license_changes.len())?;
+            for change in &self.license_changes {
+                let name = if change.old.version == change.new.version {
+                    change.new.display_name()
+                } else {
+                    format!("{} -> {}", change.old.display_name(), change.new.version.as_deref().unwrap_or("?"))
+                };
+                let marker = if change.risk_increased { " RISK INCREASE" } else { "" };
+                writeln!(f, "  {}:{}", name, marker)?;
+                writeln!(f, "    old: {}", change.old.describe())?;
+                writeln!(f, "    new: {}", change.new.describe())?;
+            }
+        }
+        if self.added.is_empty() && self.removed.is_empty() && self.license_changes.is_empty() {
+            writeln!(f, "No component or license changes")?;
+        }
+        if self.old_overall_risk == self.new_overall_risk {
+            writeln!(f, "Overall Risk: {}", self.new_overall_risk)
+        } else {
+            let marker = if risk_level_order(&self.new_overall_risk) > risk_level_order(&self.old_overall_risk) { " RISK INCREASE" } else { "" };
+            writeln!(f, "Overall Risk: {} -> {}{}", self.old_overall_risk, self.new_overall_risk, marker)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inventory::{Component, Inventory};
+    use crate::test_support::{parser, temp_dir, write_file};
+
+    fn component(name: &str, version: &str, expression: &str) -> Component {
+        let mut component = Component::new(&format!("{}@{}", name, version), name, Some(version), DependencyKind::Normal);
+        component.expression = Some(expression.to_string());
+        component
+    }
+
+    fn audit(source: &str, components: Vec<Component>) -> AuditReport {
+        let inventory = Inventory { source: source.to_string(), roots: Vec::new(), components };
+        audit_inventory(&parser(), &inventory, &LicensePolicy::default())
+    }
+
+    fn old_audit() -> AuditReport {
+        audit("old", vec![
+            component("serde", "1.0", "Apache-2.0 OR MIT"),
+            component("rand", "0.7", "MIT"),
+            component("rand", "0.8", "MIT"),
+            component("relicensed", "2.0", "GPL-3.0-only"),
+            component("left-pad", "1.0", "MIT"),
+        ])
+    }
+
+    fn new_audit() -> AuditReport {
+        audit("new", vec![
+            component("serde", "1.0", "Apache-2.0  OR  MIT"),
+            component("rand", "0.8", "MIT"),
+            component("rand", "0.9", "GPL-3.0-only"),
+            component("relicensed", "2.1", "MIT"),
+            component("gpl-lib", "1.0", "GPL-2.0-only"),
+        ])
+    }
+
+    #[test]
+    fn pairs_components_by_name_and_version() {
+        let diff = diff_audits(&old_audit(), &new_audit());
+        assert_eq!(diff.old_source, "old");
+        assert_eq!(diff.new_source, "new");
+        assert_eq!(diff.added.iter().map(|c| c.display_name()).collect::<Vec<_>>(), vec!["gpl-lib@1.0"]);
+        assert_eq!(diff.removed.iter().map(|c| c.display_name()).collect::<Vec<_>>(), vec!["left-pad@1.0"]);
+
+        // rand 0.8 pairs with itself and is unchanged; 0.7 -> 0.9 is a version bump with a new license
+        assert_eq!(diff.license_changes.len(), 2);
+        let rand = diff.license_changes.iter().find(|c| c.new.name == "rand").unwrap();
+        assert_eq!(rand.old.version.as_deref(), Some("0.7"));
+        assert_eq!(rand.new.version.as_deref(), Some("0.9"));
+        assert!(rand.risk_increased);
+        let relicensed = diff.license_changes.iter().find(|c| c.new.name == "relicensed").unwrap();
+        assert!(!relicensed.risk_increased);
+        assert!(diff.has_risk_increase());
+    }
+
+    #[test]
+    fn identical_audits_have_no_changes() {
+        let diff = diff_audits(&old_audit(), &old_audit());
+        assert!(diff.added.is_empty() && diff.removed.is_empty() && diff.license_changes.is_empty());
+        assert!(!diff.has_risk_increase());
+        assert!(diff.to_string().contains("No component or license changes\n"));
+    }
+
+    #[test]
+    fn displays_changes_and_risk_increases() {
+        let text = diff_audits(&old_audit(), &new_audit()).to_string();
+        assert!(text.starts_with("License Diff: old -> new\n"));
+        assert!(text.contains("  + gpl-lib@1.0: GPL-2.0-only"));
+        assert!(text.contains("  - left-pad@1.0: MIT"));
+        assert!(text.contains("  rand@0.7 -> 0.9: RISK INCREASE\n"));
+        assert!(text.contains("  relicensed@2.0 -> 2.1:\n"));
+    }
+
+    #[test]
+    fn losing_a_license_is_a_risk_increase() {
+        let mut unlicensed = component("serde", "1.0", "MIT");
+        unlicensed.expression = None;
+        let diff = diff_audits(&audit("old", vec![component("serde", "1.0", "MIT")]), &audit("new", vec![unlicensed]));
+        assert_eq!(diff.license_changes.len(), 1);
+        assert!(diff.license_changes[0].risk_increased);
+        assert!(diff.to_string().contains("new: NO LICENSE"));
+    }
+
+    #[test]
+    fn loads_saved_audit_reports() {
+        let parser = parser();
+        let dir = temp_dir("audit_diff_load");
+        let report = serde_json::to_string(&old_audit()).unwrap();
+        let saved = write_file(&dir, "audit.json", &report);
+        let wrapped = write_file(&dir, "wrapped.json", &format!(r#"{{"audit": {}}}"#, report));
+        for path in [saved, wrapped] {
+            let loaded = load_audit(&parser, &path).unwrap();
+            assert_eq!(loaded.source, "old");
+            assert_eq!(loaded.components.len(), 5);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_unsupported_input() {
+        let parser = parser();
+        let dir = temp_dir("audit_diff_unsupported");
+        for (name, content) in [("other.json", r#"{"name": "app"}"#), ("notes.txt", "just text")] {
+            let result = load_audit(&parser, &write_file(&dir, name, content));
+            assert!(matches!(result, Err(InventoryError::ParseError(_))), "{}", name);
+        }
+        assert!(matches!(load_audit(&parser, &dir.join("missing.json")), Err(InventoryError::FileReadError(_))));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,357 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+use toml::Value;
+use crate::cargo_metadata::set_cargo_license;
+use crate::inventory::{kind_rank, resolve_kinds, Component, DependencyKind, Inventory, InventoryError};
+
+/// Default directory `cargo vendor` writes to, relative to the workspace root
+pub const DEFAULT_VENDOR_DIR: &str = "vendor";
+
+#[derive(Debug, Deserialize)]
+struct CargoLock {
+    #[serde(default, rename = "package")]
+    packages: Vec<LockPackage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LockPackage {
+    name: String,
+    version: String,
+    /// Absent for workspace members and path dependencies
+    #[serde(default)]
+    source: Option<String>,
+    /// Entries are `name`, `name version` or `name version (source)`
+    #[serde(default)]
+    dependencies: Vec<String>,
+}
+
+/// The parts of a Cargo.toml the audit needs
+#[derive(Debug, Default)]
+struct Manifest {
+    name: Option<String>,
+    version: Option<String>,
+    license: Option<String>,
+    license_file: Option<String>,
+    dir: PathBuf,
+    /// Kind of each direct dependency, keyed by package name
+    dependency_kinds: HashMap<String, DependencyKind>,
+    /// Workspace members and path dependencies to read next
+    local_manifests: Vec<PathBuf>,
+}
+
+fn read_toml(path: &Path) -> Result<Value, InventoryError> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| InventoryError::FileReadError(format!("{}: {}", path.display(), e)))?;
+    toml::from_str(&content).map_err(|e| InventoryError::ParseError(format!("{}: {}", path.display(), e)))
+}
+
+/// Reads a string manifest field, resolving `field.workspace = true` against `[workspace.package]`
+fn package_field(package: &Value, workspace: Option<&Value>, field: &str) -> Option<String> {
+    match package.get(field)? {
+        Value::String(value) => Some(value.clone()),
+        Value::Table(table) if table.get("workspace").and_then(Value::as_bool) == Some(true) => {
+            workspace?.get("package")?.get(field)?.as_str().map(String::from)
+        }
+        _ => None,
+    }
+}
+
+/// Expands workspace member patterns; only a trailing `*` component is supported
+fn expand_members(root: &Path, pattern: &str) -> Vec<PathBuf> {
+    let Some(parent) = pattern.strip_suffix("/*") else {
+        return vec![root.join(pattern)];
+    };
+    let mut dirs: Vec<PathBuf> = fs::read_dir(root.join(parent))
+        .map(|entries| entries.flatten().map(|e| e.path()).filter(|p| p.is_dir()).collect())
+        .unwrap_or_default();
+    dirs.sort();
+    dirs
+}
+
+fn read_manifest(path: &Path, workspace: Option<&Value>) -> Result<Manifest, InventoryError> {
+    let value = read_toml(path)?;
+    let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+    let workspace = value.get("workspace").or(workspace);
+    let mut manifest = Manifest {
+        dir: dir.clone(),
+        ..Manifest::default()
+    };
+
+    if let Some(package) = value.get("package") {
+        manifest.name = package_field(package, workspace, "name");
+        manifest.version = package_field(package, workspace, "version");
+        manifest.license = package_field(package, workspace, "license");
+        manifest.license_file = package_field(package, workspace, "license-file");
+    }
+
+    let mut tables: Vec<(&Value, DependencyKind)> = Vec::new();
+    for section in [&value].into_iter().chain(value.get("target").and_then(Value::as_table).into_iter().flat_map(|t| t.values())) {
+        for (name, kind) in [
+            ("dependencies", DependencyKind::Normal),
+            ("build-dependencies", DependencyKind::Build),
+            ("dev-dependencies", DependencyKind::Dev),
+        ] {
+            if let Some(table) = section.get(name) {
+                tables.push((table, kind));
+            }
+        }
+    }
+    for (table, kind) in tables {
+        for (key, spec) in table.as_table().into_iter().flatten() {
+            let package = spec.get("package").and_then(Value::as_str).unwrap_or(key).to_string();
+            let current = manifest.dependency_kinds.entry(package).or_insert(kind);
+            if kind_rank(kind) < kind_rank(*current) {
+                *current = kind;
+            }
+            if let Some(local) = spec.get("path").and_then(Value::as_str) {
+                manifest.local_manifests.push(dir.join(local).join("Cargo.toml"));
+            }
+        }
+    }
+
+    if let Some(members) = value.get("workspace").and_then(|w| w.get("members")).and_then(Value::as_array) {
+        for member in members.iter().filter_map(Value::as_str) {
+            manifest.local_manifests.extend(expand_members(&dir, member).into_iter().map(|d| d.join("Cargo.toml")));
+        }
+    }
+
+    Ok(manifest)
+}
+
+/// Reads the workspace manifest next to Cargo.lock and every local manifest it leads to
+fn read_local_manifests(root: &Path) -> Vec<Manifest> {
+    let root_manifest = root.join("Cargo.toml");
+    let workspace = read_toml(&root_manifest).ok().and_then(|v| v.get("workspace").cloned());
+
+    let mut manifests: Vec<Manifest> = Vec::new();
+    let mut pending = vec![root_manifest];
+    let mut seen: Vec<PathBuf> = Vec::new();
+    while let Some(path) = pending.pop() {
+        let canonical = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+        if seen.contains(&canonical) {
+            continue;
+        }
+        seen.push(canonical);
+        match read_manifest(&path, workspace.as_ref()) {
+            Ok(manifest) => {
+                pending.extend(manifest.local_manifests.iter().cloned());
+                manifests.push(manifest);
+            }
+            Err(e) => log::warn!("Skipping local manifest: {}", e),
+        }
+    }
+    manifests
+}
+
+/// Reads `<vendor_dir>/*/Cargo.toml`, keyed by package name and version
+fn read_vendored_manifests(vendor_dir: &Path) -> HashMap<(String, String), Manifest> {
+    let Ok(entries) = fs::read_dir(vendor_dir) else {
+        log::warn!("Cannot read vendor directory {}", vendor_dir.display());
+        return HashMap::new();
+    };
+
+    let mut manifests = HashMap::new();
+    for entry in entries.flatten() {
+        let path = entry.path().join("Cargo.toml");
+        if !path.is_file() {
+            continue;
+        }
+        match read_manifest(&path, None) {
+            Ok(manifest) => {
+                if let (Some(name), Some(version)) = (manifest.name.clone(), manifest.version.clone()) {
+                    manifests.insert((name, version), manifest);
+                }
+            }
+            Err(e) => log::warn!("Skipping vendored manifest: {}", e),
+        }
+    }
+    manifests
+}
+
+fn package_id(name: &str, version: &str) -> String {
+    format!("{} {}", name, version)
+}
+
+/// Resolves a Cargo.lock dependency entry to the id of the package it names
+fn resolve_dependency(entry: &str, packages: &[LockPackage]) -> Option<String> {
+    let mut parts = entry.split_whitespace();
+    let name = parts.next()?;
+    let version = parts.next();
+    packages.iter()
+        .find(|p| p.name == name && version.is_none_or(|v| p.version == v))
+        .map(|p| package_id(&p.name, &p.version))
+}
+
+/// Builds an inventory from a Cargo.lock and the manifests of `cargo vendor` output, without running cargo
+///
+/// Packages without a source in the lock file are the project itself; their manifests are found
+/// from the Cargo.toml next to the lock file through workspace members and path dependencies.
+pub fn inventory_from_cargo_lock(lock_path: &Path, vendor_dir: &Path) -> Result<Inventory, InventoryError> {
+    let content = fs::read_to_string(lock_path)
+        .map_err(|e| InventoryError::FileReadError(format!("{}: {}", lock_path.display(), e)))?;
+    let lock: CargoLock = toml::from_str(&content)
+        .map_err(|e| InventoryError::ParseError(format!("{}: {}", lock_path.display(), e)))?;
+
+    let root_dir = lock_path.parent().unwrap_or(Path::new(""));
+    let local: HashMap<String, Manifest> = read_local_manifests(root_dir).into_iter()
+        .filter_map(|m| m.name.clone().map(|name| (name, m)))
+        .collect();
+    let vendored = read_vendored_manifests(vendor_dir);
+
+    let roots: Vec<String> = lock.packages.iter()
+        .filter(|p| p.source.is_none())
+        .map(|p| package_id(&p.name, &p.version))
+        .collect();
+
+    let mut edges: HashMap<String, Vec<(String, DependencyKind)>> = HashMap::new();
+    for package in &lock.packages {
+        let kinds = package.source.is_none().then(|| local.get(&package.name)).flatten().map(|m| &m.dependency_kinds);
+        let package_edges = edges.entry(package_id(&package.name, &package.version)).or_default();
+        for entry in &package.dependencies {
+            let Some(dependency) = resolve_dependency(entry, &lock.packages) else {
+                log::warn!("Unresolved dependency '{}' of {}", entry, package.name);
+                continue;
+            };
+            let dependency_name = entry.split_whitespace().next().unwrap_or_default();
+            let kind = kinds.and_then(|k| k.get(dependency_name)).copied().unwrap_or(DependencyKind::Normal);
+            package_edges.push((dependency, kind));
+        }
+    }
+    let kinds = resolve_kinds(&roots, &edges);
+
+    let components = lock.packages.iter()
+        .map(|package| {
+            let id = package_id(&package.name, &package.version);
+            let kind = kinds.get(&id).copied().unwrap_or(DependencyKind::Normal);
+            let mut component = Component::new(&id, &package.name, Some(&package.version), kind);
+            component.dependencies = edges.get(&id)
+                .map(|deps| deps.iter().map(|(dependency, _)| dependency.clone()).collect())
+                .unwrap_or_default();
+
+            let manifest = if package.source.is_none() {
+                local.get(&package.name)
+            } else {
+                vendored.get(&(package.name.clone(), package.version.clone()))
+            };
+            match manifest {
+                Some(manifest) => {
+                    component.source_dir = Some(manifest.dir.clone());
+                    set_cargo_license(&mut component, manifest.license.as_deref(), manifest.license_file.as_deref(), &manifest.dir);
+                }
+                None => component.notes.push("No manifest found in the workspace or vendor directory".to_string()),
+            }
+            component
+        })
+        .collect();
+
+    Ok(Inventory {
+        source: lock_path.display().to_string(),
+        roots,
+        components,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{temp_dir, write_file};
+
+    const LOCK: &str = r#"
+version = 3
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = ["util", "serde 1.0.1", "cc", "proptest"]
+
+[[package]]
+name = "util"
+version = "0.1.0"
+dependencies = ["serde 1.0.0"]
+
+[[package]]
+name = "serde"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "serde"
+version = "1.0.1"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "cc"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "proptest"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+    fn workspace() -> PathBuf {
+        let dir = temp_dir("cargo-lock");
+        write_file(&dir, "Cargo.lock", LOCK);
+        write_file(&dir, "Cargo.toml", "[workspace]\nmembers = [\"crates/*\"]\n\n[workspace.package]\nlicense = \"MIT/Apache-2.0\"\n");
+        write_file(&dir, "crates/app/Cargo.toml", r#"
+[package]
+name = "app"
+version = "0.1.0"
+license.workspace = true
+
+[dependencies]
+util = { path = "../../util" }
+serde-new = { package = "serde", version = "1.0.1" }
+
+[build-dependencies]
+cc = "1"
+
+[target.'cfg(unix)'.dev-dependencies]
+proptest = "1"
+"#);
+        write_file(&dir, "util/Cargo.toml", "[package]\nname = \"util\"\nversion = \"0.1.0\"\nlicense-file = \"COPYING\"\n");
+        write_file(&dir, "vendor/serde/Cargo.toml", "[package]\nname = \"serde\"\nversion = \"1.0.1\"\nlicense = \"MIT OR Apache-2.0\"\n");
+        write_file(&dir, "vendor/serde-1.0.0/Cargo.toml", "[package]\nname = \"serde\"\nversion = \"1.0.0\"\nlicense = \"MIT\"\n");
+        write_file(&dir, "vendor/cc/Cargo.toml", "[package]\nname = \"cc\"\nversion = \"1.0.0\"\nlicense = \"Apache-2.0\"\n");
+        dir
+    }
+
+    #[test]
+    fn reads_local_and_vendored_manifests() {
+        let dir = workspace();
+        let inventory = inventory_from_cargo_lock(&dir.join("Cargo.lock"), &dir.join(DEFAULT_VENDOR_DIR)).unwrap();
+        let component = |id: &str| inventory.component(id).unwrap();
+        assert_eq!(inventory.roots, vec!["app 0.1.0", "util 0.1.0"]);
+        assert_eq!(component("app 0.1.0").expression.as_deref(), Some("MIT OR Apache-2.0"));
+        assert!(component("util 0.1.0").license_files[0].ends_with("util/COPYING"));
+        assert_eq!(component("serde 1.0.0").expression.as_deref(), Some("MIT"));
+        assert_eq!(component("serde 1.0.1").expression.as_deref(), Some("MIT OR Apache-2.0"));
+        assert_eq!(component("proptest 1.0.0").notes, vec!["No manifest found in the workspace or vendor directory"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn dependency_kinds_come_from_local_manifests() {
+        let dir = workspace();
+        let inventory = inventory_from_cargo_lock(&dir.join("Cargo.lock"), &dir.join(DEFAULT_VENDOR_DIR)).unwrap();
+        let kind = |id: &str| inventory.component(id).unwrap().kind;
+        assert_eq!(kind("app 0.1.0"), DependencyKind::Root);
+        assert_eq!(kind("serde 1.0.1"), DependencyKind::Normal);
+        assert_eq!(kind("serde 1.0.0"), DependencyKind::Normal);
+        assert_eq!(kind("cc 1.0.0"), DependencyKind::Build);
+        assert_eq!(kind("proptest 1.0.0"), DependencyKind::Dev);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_or_malformed_lock_is_an_error() {
+        let dir = temp_dir("cargo-lock-bad");
+        assert!(matches!(inventory_from_cargo_lock(&dir.join("Cargo.lock"), &dir), Err(InventoryError::FileReadError(_))));
+        let lock = write_file(&dir, "Cargo.lock", "[[package]]\nname = 1\n");
+        assert!(matches!(inventory_from_cargo_lock(&lock, &dir), Err(InventoryError::ParseError(_))));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
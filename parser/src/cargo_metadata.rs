@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+use crate::inventory::{any_of_licenses, check_declared_expression, kind_rank, resolve_kinds, Component, DependencyKind, Inventory, InventoryError};
+
+#[derive(Debug, Deserialize)]
+struct CargoMetadata {
+    packages: Vec<CargoPackage>,
+    #[serde(default)]
+    workspace_members: Vec<String>,
+    #[serde(default)]
+    resolve: Option<CargoResolve>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CargoPackage {
+    id: String,
+    name: String,
+    version: String,
+    #[serde(default)]
+    license: Option<String>,
+    #[serde(default)]
+    license_file: Option<String>,
+    #[serde(default)]
+    manifest_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CargoResolve {
+    nodes: Vec<CargoNode>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CargoNode {
+    id: String,
+    /// Plain dependency ids, all that cargo before 1.41 reports
+    #[serde(default)]
+    dependencies: Vec<String>,
+    #[serde(default)]
+    deps: Vec<CargoNodeDep>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CargoNodeDep {
+    pkg: String,
+    #[serde(default)]
+    dep_kinds: Vec<CargoDepKind>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CargoDepKind {
+    kind: Option<String>,
+}
+
+/// Converts a Cargo `license` field to an SPDX expression
+/// Cargo used to accept `/` as a separator between alternatives, e.g. `MIT/Apache-2.0`. SPDX ids
+/// can't contain `/`, so a field with one is only rewritten when every alternative is a valid
+/// expression; anything else is kept as declared.
+pub fn normalize_cargo_license(license: &str) -> String {
+    let license = license.trim();
+    if !license.contains('/') {
+        return license.to_string();
+    }
+    let alternatives: Vec<&str> = license.split('/').map(str::trim).filter(|part| !part.is_empty()).collect();
+    if !alternatives.iter().all(|alternative| check_declared_expression(alternative).is_ok()) {
+        return license.to_string();
+    }
+    any_of_licenses(&alternatives).unwrap_or_default()
+}
+
+/// Records a package's `license` and `license-file` manifest fields on its component
+pub(crate) fn set_cargo_license(component: &mut Component, license: Option<&str>, license_file: Option<&str>, manifest_dir: &Path) {
+    if let Some(license) = license {
+        let expression = normalize_cargo_license(license);
+        if expression != license.trim() {
+            component.notes.push("Legacy '/' license separator read as OR".to_string());
+        }
+        component.declared_license = Some(license.to_string());
+        component.expression = Some(expression);
+    }
+    if let Some(license_file) = license_file {
+        component.license_files.push(manifest_dir.join(license_file));
+        if license.is_none() {
+            component.notes.push(format!("Only a license file is declared: {}", license_file));
+        }
+    }
+}
+
+/// Builds an inventory from the output of `cargo metadata --format-version 1`
+/// `source` names the input in reports
+pub fn inventory_from_cargo_metadata(content: &str, source: &str) -> Result<Inventory, InventoryError> {
+    let metadata: CargoMetadata = serde_json::from_str(content)
+        .map_err(|e| InventoryError::ParseError(format!("{}: {}", source, e)))?;
+
+    let mut edges: HashMap<String, Vec<(String, DependencyKind)>> = HashMap::new();
+    for node in metadata.resolve.iter().flat_map(|r| &r.nodes) {
+        let node_edges = edges.entry(node.id.clone()).or_default();
+        if node.deps.is_empty() {
+            node_edges.extend(node.dependencies.iter().map(|id| (id.clone(), DependencyKind::Normal)));
+            continue;
+        }
+        for dep in &node.deps {
+            // A dependency declared under several sections counts as its most shipped one
+            let kind = dep.dep_kinds.iter()
+                .map(|k| match k.kind.as_deref() {
+                    Some("dev") => DependencyKind::Dev,
+                    Some("build") => DependencyKind::Build,
+                    _ => DependencyKind::Normal,
+                })
+                .min_by_key(|k| kind_rank(*k))
+                .unwrap_or(DependencyKind::Normal);
+            node_edges.push((dep.pkg.clone(), kind));
+        }
+    }
+    let kinds = resolve_kinds(&metadata.workspace_members, &edges);
+
+    let components = metadata.packages.iter()
+        .map(|package| {
+            let kind = kinds.get(&package.id).copied().unwrap_or(DependencyKind::Normal);
+            let mut component = Component::new(&package.id, &package.name, Some(&package.version), kind);
+            component.dependencies = edges.get(&package.id)
+                .map(|deps| deps.iter().map(|(id, _)| id.clone()).collect())
+                .unwrap_or_default();
+
+            component.source_dir = package.manifest_path.as_deref().and_then(Path::parent).map(Path::to_path_buf);
+            let manifest_dir = component.source_dir.clone().unwrap_or_default();
+            set_cargo_license(&mut component, package.license.as_deref(), package.license_file.as_deref(), &manifest_dir);
+            component
+        })
+        .collect();
+
+    Ok(Inventory {
+        source: source.to_string(),
+        roots: metadata.workspace_members,
+        components,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const METADATA: &str = r#"{
+        "packages": [
+            {"id": "app 0.1.0", "name": "app", "version": "0.1.0", "license": "MIT", "manifest_path": "/src/app/Cargo.toml"},
+            {"id": "serde 1.0.0", "name": "serde", "version": "1.0.0", "license": "MIT/Apache-2.0"},
+            {"id": "cc 1.0.0", "name": "cc", "version": "1.0.0", "license": "MIT OR Apache-2.0"},
+            {"id": "proptest 1.0.0", "name": "proptest", "version": "1.0.0", "license": "GPL-3.0-only"},
+            {"id": "rand 0.8.0", "name": "rand", "version": "0.8.0", "license_file": "LICENSE.txt", "manifest_path": "/registry/rand/Cargo.toml"}
+        ],
+        "workspace_members": ["app 0.1.0"],
+        "resolve": {"nodes": [
+            {"id": "app 0.1.0", "deps": [
+                {"pkg": "serde 1.0.0", "dep_kinds": [{"kind": null}]},
+                {"pkg": "cc 1.0.0", "dep_kinds": [{"kind": "build"}]},
+                {"pkg": "proptest 1.0.0", "dep_kinds": [{"kind": "dev"}]},
+                {"pkg": "rand 0.8.0", "dep_kinds": [{"kind": "dev"}, {"kind": null}]}
+            ]},
+            {"id": "proptest 1.0.0", "deps": [{"pkg": "rand 0.8.0", "dep_kinds": [{"kind": null}]}]}
+        ]}
+    }"#;
+
+    #[test]
+    fn normalizes_legacy_separator() {
+        assert_eq!(normalize_cargo_license("MIT/Apache-2.0"), "MIT OR Apache-2.0");
+        assert_eq!(normalize_cargo_license(" MIT OR Apache-2.0 "), "MIT OR Apache-2.0");
+        assert_eq!(normalize_cargo_license("(MIT OR Apache-2.0) AND BSD-3-Clause"), "(MIT OR Apache-2.0) AND BSD-3-Clause");
+        assert_eq!(normalize_cargo_license("Apache-2.0 WITH LLVM-exception/MIT"), "(Apache-2.0 WITH LLVM-exception) OR MIT");
+        assert_eq!(normalize_cargo_license("MIT/(Apache-2.0"), "MIT/(Apache-2.0");
+    }
+
+    #[test]
+    fn reads_packages_licenses_and_dependency_kinds() {
+        let inventory = inventory_from_cargo_metadata(METADATA, "metadata.json").unwrap();
+        assert_eq!(inventory.roots, vec!["app 0.1.0"]);
+        let component = |id: &str| inventory.component(id).unwrap();
+        assert_eq!(component("app 0.1.0").kind, DependencyKind::Root);
+        assert_eq!(component("app 0.1.0").source_dir.as_deref(), Some(Path::new("/src/app")));
+        assert_eq!(component("serde 1.0.0").kind, DependencyKind::Normal);
+        assert_eq!(component("serde 1.0.0").expression.as_deref(), Some("MIT OR Apache-2.0"));
+        assert_eq!(component("serde 1.0.0").notes, vec!["Legacy '/' license separator read as OR"]);
+        assert_eq!(component("cc 1.0.0").kind, DependencyKind::Build);
+        assert_eq!(component("proptest 1.0.0").kind, DependencyKind::Dev);
+
+        // Declared both as a normal and a dev dependency, so it is shipped
+        let rand = component("rand 0.8.0");
+        assert_eq!(rand.kind, DependencyKind::Normal);
+        assert_eq!(rand.expression, None);
+        assert_eq!(rand.license_files, vec![PathBuf::from("/registry/rand/LICENSE.txt")]);
+    }
+
+    #[test]
+    fn reads_plain_dependency_lists_of_old_cargo() {
+        let metadata = r#"{
+            "packages": [{"id": "a", "name": "a", "version": "1"}, {"id": "b", "name": "b", "version": "1"}],
+            "workspace_members": ["a"],
+            "resolve": {"nodes": [{"id": "a", "dependencies": ["b"]}]}
+        }"#;
+        let inventory = inventory_from_cargo_metadata(metadata, "old.json").unwrap();
+        assert_eq!(inventory.component("a").unwrap().dependencies, vec!["b"]);
+        assert_eq!(inventory.component("b").unwrap().kind, DependencyKind::Normal);
+        assert!(matches!(inventory_from_cargo_metadata("[]", "bad.json"), Err(InventoryError::ParseError(_))));
+    }
+}
@@ -0,0 +1,367 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::ops::Deref;
+use std::path::Path;
+use memmap2::Mmap;
+use crate::license_database::{self, DatabaseVersion, LicenseDatabaseError, LicenseIndex, LicenseSource, NewLicense};
+use crate::models::NewCopyleftStrength;
+
+/// Magic bytes at the start of every compiled license database
+pub const COMPILED_DATABASE_MAGIC: &[u8; 8] = b"LXCDB002";
+
+// Layout (all integers little-endian):
+//   header   magic[8] entry_count:u32 license_count:u32 version:str released:str checksum:str  (str = offset:u32 len:u32)
+//   entries  entry_count * (key:str name:str spdx_id:str strength:u8 flags:u8 reserved:u16), sorted by key bytes
+//   strings  UTF-8 string table referenced by offset/len pairs; an empty spdx_id means none
+const HEADER_LEN: usize = 40;
+const ENTRY_LEN: usize = 28;
+
+const FLAG_EXCEPTION: u8 = 0b01;
+const FLAG_DEPRECATED: u8 = 0b10;
+
+/// Bytes backing a compiled database, either memory-mapped or read into memory
+#[derive(Debug)]
+enum DatabaseBytes {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+
+impl Deref for DatabaseBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            DatabaseBytes::Mapped(map) => map,
+            DatabaseBytes::Owned(bytes) => bytes,
+        }
+    }
+}
+
+/// Precompiled license database with a sorted id index
+///
+/// Opening validates the layout once; lookups binary-search the entry table and
+/// borrow strings directly from the underlying bytes.
+#[derive(Debug)]
+pub struct CompiledLicenseDatabase {
+    bytes: DatabaseBytes,
+    entry_count: usize,
+}
+
+/// A single entry borrowed from a compiled database
+#[derive(Debug, Clone)]
+pub struct CompiledLicenseEntry<'a> {
+    pub key: &'a str,
+    pub name: &'a str,
+    pub copyleft_strength: NewCopyleftStrength,
+    pub is_exception: bool,
+    pub is_deprecated: bool,
+    pub spdx_id: Option<&'a str>,
+}
+
+impl CompiledLicenseEntry<'_> {
+    pub fn to_new_license(&self) -> NewLicense {
+        NewLicense {
+            id: self.key.to_string(),
+            name: self.name.to_string(),
+            copyleft_strength: self.copyleft_strength.clone(),
+            source: LicenseSource::ScanCode,
+            obligations: Vec::new(),
+            is_exception: self.is_exception,
+            is_deprecated: self.is_deprecated,
+            spdx_id: self.spdx_id.map(String::from),
+        }
+    }
+}
+
+fn strength_to_byte(strength: &NewCopyleftStrength) -> u8 {
+    match strength {
+        NewCopyleftStrength::CLA => 0,
+        NewCopyleftStrength::Commercial => 1,
+        NewCopyleftStrength::Copyleft => 2,
+        NewCopyleftStrength::CopyleftLimited => 3,
+        NewCopyleftStrength::FreeRestricted => 4,
+        NewCopyleftStrength::PatentLicense => 5,
+        NewCopyleftStrength::Permissive => 6,
+        NewCopyleftStrength::ProprietaryFree => 7,
+        NewCopyleftStrength::PublicDomain => 8,
+        NewCopyleftStrength::SourceAvailable => 9,
+        NewCopyleftStrength::UnstatedLicense => 10,
+    }
+}
+
+fn strength_from_byte(byte: u8) -> Option<NewCopyleftStrength> {
+    match byte {
+        0 => Some(NewCopyleftStrength::CLA),
+        1 => Some(NewCopyleftStrength::Commercial),
+        2 => Some(NewCopyleftStrength::Copyleft),
+        3 => Some(NewCopyleftStrength::CopyleftLimited),
+        4 => Some(NewCopyleftStrength::FreeRestricted),
+        5 => Some(NewCopyleftStrength::PatentLicense),
+        6 => Some(NewCopyleftStrength::Permissive),
+        7 => Some(NewCopyleftStrength::ProprietaryFree),
+        8 => Some(NewCopyleftStrength::PublicDomain),
+        9 => Some(NewCopyleftStrength::SourceAvailable),
+        10 => Some(NewCopyleftStrength::UnstatedLicense),
+        _ => None,
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> usize {
+    let mut buf = [0u8; 4];
+    buf.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(buf) as usize
+}
+
+/// Serializes a license index into the compiled database format
+pub fn compile_license_index(index: &LicenseIndex) -> Vec<u8> {
+    // Same key resolution as build_license_db: later keys overwrite earlier ones
+    let mut entries: BTreeMap<String, (String, String, NewCopyleftStrength, u8)> = BTreeMap::new();
+    for license in &index.licenses {
+        let strength = license_database::category_to_copyleft_strength(&license.category)
+            .unwrap_or(NewCopyleftStrength::UnstatedLicense);
+        let mut flags = 0;
+        if license.is_exception {
+            flags |= FLAG_EXCEPTION;
+        }
+        if license.is_deprecated {
+            flags |= FLAG_DEPRECATED;
+        }
+        let spdx_id = license.spdx_license_key.clone().unwrap_or_default();
+        for (key, name) in license_database::license_db_keys(license) {
+            entries.insert(key, (name, spdx_id.clone(), strength.clone(), flags));
+        }
+    }
+
+    let mut strings: Vec<u8> = Vec::new();
+    let mut push_str = |s: &str| -> (u32, u32) {
+        let offset = strings.len() as u32;
+        strings.extend_from_slice(s.as_bytes());
+        (offset, s.len() as u32)
+    };
+
+    let version = push_str(index.version.version.as_deref().unwrap_or(""));
+    let released = push_str(index.version.released.as_deref().unwrap_or(""));
+    let checksum = push_str(&index.version.checksum);
+
+    let mut table = Vec::with_capacity(entries.len() * ENTRY_LEN);
+    for (key, (name, spdx_id, strength, flags)) in &entries {
+        for (offset, len) in [push_str(key), push_str(name), push_str(spdx_id)] {
+            table.extend_from_slice(&offset.to_le_bytes());
+            table.extend_from_slice(&len.to_le_bytes());
+        }
+        table.push(strength_to_byte(strength));
+        table.push(*flags);
+        table.extend_from_slice(&[0, 0]);
+    }
+
+    let mut out = Vec::with_capacity(HEADER_LEN + table.len() + strings.len());
+    out.extend_from_slice(COMPILED_DATABASE_MAGIC);
+    out.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+    out.extend_from_slice(&(index.licenses.len() as u32).to_le_bytes());
+    for (offset, len) in [version, released, checksum] {
+        out.extend_from_slice(&offset.to_le_bytes());
+        out.extend_from_slice(&len.to_le_bytes());
+    }
+    out.extend_from_slice(&table);
+    out.extend_from_slice(&strings);
+    out
+}
+
+/// Compiles a JSON license index file into the compiled database format on disk
+pub fn compile_license_index_file(index_path: &Path, output_path: &Path) -> Result<DatabaseVersion, LicenseDatabaseError> {
+    let index = license_database::load_license_index(index_path)?;
+    fs::write(output_path, compile_license_index(&index))
+        .map_err(|e| LicenseDatabaseError::FileReadError(format!("{}: {}", output_path.display(), e)))?;
+    Ok(index.version)
+}
+
+/// Returns true if the file at `path` starts with the compiled database magic bytes
+pub fn is_compiled_database(path: &Path) -> bool {
+    use std::io::Read;
+
+    let mut magic = [0u8; 8];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .map(|_| &magic == COMPILED_DATABASE_MAGIC)
+        .unwrap_or(false)
+}
+
+impl CompiledLicenseDatabase {
+    /// Memory-maps a compiled database file
+    pub fn open(path: &Path) -> Result<Self, LicenseDatabaseError> {
+        let file = File::open(path)
+            .map_err(|e| LicenseDatabaseError::FileReadError(format!("{}: {}", path.display(), e)))?;
+        // SAFETY: the mapping is read-only and every access is bounds-checked against the
+        // mapped length; the database file is not expected to be modified while mapped.
+        let map = unsafe { Mmap::map(&file) }
+            .map_err(|e| LicenseDatabaseError::FileReadError(format!("{}: {}", path.display(), e)))?;
+        Self::from_database_bytes(DatabaseBytes::Mapped(map))
+            .map_err(|e| LicenseDatabaseError::InvalidDatabase(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Loads a compiled database from an in-memory buffer
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, LicenseDatabaseError> {
+        Self::from_database_bytes(DatabaseBytes::Owned(bytes))
+            .map_err(LicenseDatabaseError::InvalidDatabase)
+    }
+
+    fn from_database_bytes(bytes: DatabaseBytes) -> Result<Self, String> {
+        if bytes.len() < HEADER_LEN || &bytes[..8] != COMPILED_DATABASE_MAGIC {
+            return Err("not a compiled license database".to_string());
+        }
+
+        let entry_count = read_u32(&bytes, 8);
+        let strings_start = HEADER_LEN + entry_count * ENTRY_LEN;
+        if bytes.len() < strings_start {
+            return Err("truncated entry table".to_string());
+        }
+
+        let database = CompiledLicenseDatabase { bytes, entry_count };
+
+        // Validate every string reference and strength once, so lookups can't go out of bounds
+        for offset in [16, 24, 32] {
+            database.string_at(offset).ok_or("invalid header string")?;
+        }
+        let checksum = database.string_at(32).unwrap_or_default();
+        if checksum.len() != 16 || !checksum.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(format!("invalid index checksum '{}'", checksum));
+        }
+        for index in 0..entry_count {
+            let base = HEADER_LEN + index * ENTRY_LEN;
+            database.string_at(base).ok_or("invalid entry key")?;
+            database.string_at(base + 8).ok_or("invalid entry name")?;
+            database.string_at(base + 16).ok_or("invalid entry SPDX id")?;
+            strength_from_byte(database.bytes[base + 24]).ok_or("invalid copyleft strength")?;
+        }
+
+        Ok(database)
+    }
+
+    /// Reads the offset/len string reference stored at `position`
+    fn string_at(&self, position: usize) -> Option<&str> {
+        let strings_start = HEADER_LEN + self.entry_count * ENTRY_LEN;
+        let offset = read_u32(&self.bytes, position);
+        let len = read_u32(&self.bytes, position + 4);
+        let start = strings_start.checked_add(offset)?;
+        let end = start.checked_add(len)?;
+        std::str::from_utf8(self.bytes.get(start..end)?).ok()
+    }
+
+    fn entry(&self, index: usize) -> CompiledLicenseEntry<'_> {
+        let base = HEADER_LEN + index * ENTRY_LEN;
+        let flags = self.bytes[base + 25];
+        CompiledLicenseEntry {
+            key: self.string_at(base).unwrap_or_default(),
+            name: self.string_at(base + 8).unwrap_or_default(),
+            copyleft_strength: strength_from_byte(self.bytes[base + 24]).unwrap_or(NewCopyleftStrength::UnstatedLicense),
+            is_exception: flags & FLAG_EXCEPTION != 0,
+            is_deprecated: flags & FLAG_DEPRECATED != 0,
+            spdx_id: self.string_at(base + 16).filter(|id| !id.is_empty()),
+        }
+    }
+
+    /// Looks up a license id (case-insensitive) by binary search over the sorted key table
+    pub fn get(&self, id: &str) -> Option<CompiledLicenseEntry<'_>> {
+        let key = id.to_lowercase();
+        let (mut low, mut high) = (0, self.entry_count);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let entry = self.entry(mid);
+            match entry.key.as_bytes().cmp(key.as_bytes()) {
+                std::cmp::Ordering::Equal => return Some(entry),
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+            }
+        }
+        None
+    }
+
+    /// Iterates over all entries in key order
+    pub fn entries(&self) -> impl Iterator<Item = CompiledLicenseEntry<'_>> {
+        (0..self.entry_count).map(move |index| self.entry(index))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entry_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entry_count == 0
+    }
+
+    /// Version metadata recorded when the database was compiled
+    pub fn version(&self) -> DatabaseVersion {
+        let optional = |s: &str| if s.is_empty() { None } else { Some(s.to_string()) };
+        DatabaseVersion {
+            version: optional(self.string_at(16).unwrap_or_default()),
+            released: optional(self.string_at(24).unwrap_or_default()),
+            checksum: self.string_at(32).unwrap_or_default().to_string(),
+            license_count: read_u32(&self.bytes, 12),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{index_path, temp_dir};
+
+    fn compiled_index() -> (LicenseIndex, Vec<u8>) {
+        let index = license_database::load_license_index(&index_path()).unwrap();
+        let bytes = compile_license_index(&index);
+        (index, bytes)
+    }
+
+    #[test]
+    fn compiled_database_matches_json_index() {
+        let (index, bytes) = compiled_index();
+        let compiled = CompiledLicenseDatabase::from_bytes(bytes).unwrap();
+        let expected = license_database::build_license_db(&index.licenses);
+        assert_eq!(compiled.len(), expected.len());
+        assert_eq!(compiled.version(), index.version);
+        for (key, license) in &expected {
+            let entry = compiled.get(key).unwrap();
+            assert_eq!(entry.name, license.name, "{}", key);
+            assert_eq!(entry.copyleft_strength, license.copyleft_strength, "{}", key);
+            assert_eq!(entry.is_exception, license.is_exception, "{}", key);
+            assert_eq!(entry.spdx_id, license.spdx_id.as_deref(), "{}", key);
+        }
+        assert!(compiled.entries().zip(compiled.entries().skip(1)).all(|(a, b)| a.key < b.key));
+    }
+
+    #[test]
+    fn lookups_ignore_case() {
+        let compiled = CompiledLicenseDatabase::from_bytes(compiled_index().1).unwrap();
+        assert_eq!(compiled.get("gpl-3.0-only").unwrap().name, compiled.get("GPL-3.0-ONLY").unwrap().name);
+        assert!(compiled.get("not-a-license").is_none());
+    }
+
+    #[test]
+    fn rejects_corrupt_databases() {
+        let bytes = compiled_index().1;
+        assert!(CompiledLicenseDatabase::from_bytes(b"[{\"license_key\": \"mit\"}]".to_vec()).is_err());
+        assert!(CompiledLicenseDatabase::from_bytes(bytes[..HEADER_LEN + ENTRY_LEN].to_vec()).is_err());
+        let mut bad_string = bytes.clone();
+        bad_string[HEADER_LEN + 4..HEADER_LEN + 8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(CompiledLicenseDatabase::from_bytes(bad_string).is_err());
+        let mut bad_strength = bytes.clone();
+        bad_strength[HEADER_LEN + 24] = 200;
+        assert!(CompiledLicenseDatabase::from_bytes(bad_strength).is_err());
+        let mut short_checksum = bytes;
+        short_checksum[36..40].copy_from_slice(&4u32.to_le_bytes());
+        assert!(CompiledLicenseDatabase::from_bytes(short_checksum).is_err());
+    }
+
+    #[test]
+    fn compiles_and_maps_files() {
+        let dir = temp_dir("compiled");
+        let output = dir.join("index.lxcdb");
+        let version = compile_license_index_file(&index_path(), &output).unwrap();
+        assert!(is_compiled_database(&output));
+        assert!(!is_compiled_database(&index_path()));
+        let compiled = CompiledLicenseDatabase::open(&output).unwrap();
+        assert_eq!(compiled.version(), version);
+        assert!(compiled.get("MIT").is_some());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
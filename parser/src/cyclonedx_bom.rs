@@ -0,0 +1,859 @@
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+use roxmltree::{Document, Node};
+use serde_json::Value;
+use crate::inventory::{any_of_licenses, resolve_kinds, Component, DependencyKind, Inventory, InventoryError};
+use crate::license_audit::{audit_inventory, AuditReport, AuditVerdict, ComponentAudit};
+use crate::license_database::LicenseDatabase;
+use crate::license_expression_parser::LicenseExpressionParser;
+use crate::license_names::NameMatchMethod;
+use crate::license_policy::LicensePolicy;
+
+/// CycloneDX versions this reader was written against; others are read on a best-effort basis
+const SUPPORTED_SPEC_VERSIONS: [&str; 3] = ["1.4", "1.5", "1.6"];
+
+/// What a `licenses[]` entry holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CdxLicenseKind {
+    Id,
+    Name,
+    Expression,
+}
+
+#[derive(Debug, Clone)]
+struct CdxLicense {
+    kind: CdxLicenseKind,
+    value: String,
+    url: Option<String>,
+    /// `declared` or `concluded` (CycloneDX 1.6)
+    acknowledgement: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct CdxComponent {
+    bom_ref: Option<String>,
+    group: Option<String>,
+    name: String,
+    version: Option<String>,
+    scope: Option<String>,
+    licenses: Vec<CdxLicense>,
+    components: Vec<CdxComponent>,
+}
+
+#[derive(Debug, Default)]
+struct CdxBom {
+    spec_version: String,
+    metadata_component: Option<CdxComponent>,
+    components: Vec<CdxComponent>,
+    dependencies: Vec<(String, Vec<String>)>,
+}
+
+impl CdxComponent {
+    fn id(&self) -> String {
+        self.bom_ref.clone().unwrap_or_else(|| match self.version {
+            Some(ref version) => format!("{}@{}", self.display_name(), version),
+            None => self.display_name(),
+        })
+    }
+
+    fn display_name(&self) -> String {
+        match self.group {
+            Some(ref group) if !group.is_empty() => format!("{}/{}", group, self.name),
+            _ => self.name.clone(),
+        }
+    }
+}
+
+fn json_component(value: &Value) -> CdxComponent {
+    let text = |field: &str| value.get(field).and_then(Value::as_str).map(String::from);
+    let mut licenses = Vec::new();
+    for entry in value.get("licenses").and_then(Value::as_array).into_iter().flatten() {
+        let acknowledgement = entry.get("acknowledgement").and_then(Value::as_str).map(String::from);
+        if let Some(expression) = entry.get("expression").and_then(Value::as_str) {
+            licenses.push(CdxLicense { kind: CdxLicenseKind::Expression, value: expression.to_string(), url: None, acknowledgement });
+        } else if let Some(license) = entry.get("license") {
+            let field = |name: &str| license.get(name).and_then(Value::as_str).map(String::from);
+            let acknowledgement = field("acknowledgement").or(acknowledgement);
+            let (kind, value) = match (field("id"), field("name")) {
+                (Some(id), _) => (CdxLicenseKind::Id, id),
+                (None, Some(name)) => (CdxLicenseKind::Name, name),
+                (None, None) => (CdxLicenseKind::Name, String::new()),
+            };
+            licenses.push(CdxLicense { kind, value, url: field("url"), acknowledgement });
+        }
+    }
+    CdxComponent {
+        bom_ref: text("bom-ref"),
+        group: text("group"),
+        name: text("name").unwrap_or_default(),
+        version: text("version"),
+        scope: text("scope"),
+        licenses,
+        components: value.get("components").and_then(Value::as_array).into_iter().flatten().map(json_component).collect(),
+    }
+}
+
+fn parse_json_bom(content: &str) -> Result<CdxBom, String> {
+    let value: Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    if value.get("bomFormat").and_then(Value::as_str) != Some("CycloneDX") {
+        return Err("not a CycloneDX BOM (bomFormat is not \"CycloneDX\")".to_string());
+    }
+    Ok(CdxBom {
+        spec_version: value.get("specVersion").and_then(Value::as_str).unwrap_or_default().to_string(),
+        metadata_component: value.get("metadata").and_then(|m| m.get("component")).map(json_component),
+        components: value.get("components").and_then(Value::as_array).into_iter().flatten().map(json_component).collect(),
+        dependencies: value.get("dependencies").and_then(Value::as_array).into_iter().flatten()
+            .filter_map(|dependency| {
+                let dependency_ref = dependency.get("ref")?.as_str()?.to_string();
+                let depends_on = dependency.get("dependsOn").and_then(Value::as_array).into_iter().flatten()
+                    .filter_map(|d| d.as_str().map(String::from))
+                    .collect();
+                Some((dependency_ref, depends_on))
+            })
+            .collect(),
+    })
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.is_element() && n.tag_name().name() == name)
+}
+
+fn child_text(node: Node, name: &str) -> Option<String> {
+    child(node, name).and_then(|n| n.text()).map(str::trim).filter(|t| !t.is_empty()).map(String::from)
+}
+
+fn children<'a, 'input>(node: Option<Node<'a, 'input>>, name: &'a str) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.into_iter().flat_map(|n| n.children()).filter(move |n| n.is_element() && n.tag_name().name() == name)
+}
+
+fn xml_component(node: Node) -> CdxComponent {
+    let mut licenses = Vec::new();
+    for entry in child(node, "licenses").into_iter().flat_map(|l| l.children()).filter(Node::is_element) {
+        let acknowledgement = entry.attribute("acknowledgement").map(String::from);
+        match entry.tag_name().name() {
+            "expression" => licenses.push(CdxLicense {
+                kind: CdxLicenseKind::Expression,
+                value: entry.text().unwrap_or_default().trim().to_string(),
+                url: None,
+                acknowledgement,
+            }),
+            "license" => {
+                let (kind, value) = match (child_text(entry, "id"), child_text(entry, "name")) {
+                    (Some(id), _) => (CdxLicenseKind::Id, id),
+                    (None, Some(name)) => (CdxLicenseKind::Name, name),
+                    (None, None) => (CdxLicenseKind::Name, String::new()),
+                };
+                licenses.push(CdxLicense { kind, value, url: child_text(entry, "url"), acknowledgement });
+            }
+            _ => {}
+        }
+    }
+    CdxComponent {
+        bom_ref: node.attribute("bom-ref").map(String::from),
+        group: child_text(node, "group"),
+        name: child_text(node, "name").unwrap_or_default(),
+        version: child_text(node, "version"),
+        scope: child_text(node, "scope"),
+        licenses,
+        components: children(child(node, "components"), "component").map(xml_component).collect(),
+    }
+}
+
+fn parse_xml_bom(content: &str) -> Result<CdxBom, String> {
+    let document = Document::parse(content).map_err(|e| e.to_string())?;
+    let bom = document.root_element();
+    if bom.tag_name().name() != "bom" {
+        return Err("not a CycloneDX BOM (root element is not <bom>)".to_string());
+    }
+    // The spec version is only in the namespace, e.g. http://cyclonedx.org/schema/bom/1.5
+    let spec_version = bom.tag_name().namespace()
+        .and_then(|ns| ns.rsplit('/').next())
+        .unwrap_or_default()
+        .to_string();
+    Ok(CdxBom {
+        spec_version,
+        metadata_component: child(bom, "metadata").and_then(|m| child(m, "component")).map(xml_component),
+        components: children(child(bom, "components"), "component").map(xml_component).collect(),
+        dependencies: children(child(bom, "dependencies"), "dependency")
+            .filter_map(|dependency| {
+                let dependency_ref = dependency.attribute("ref")?.to_string();
+                let depends_on = children(Some(dependency), "dependency")
+                    .filter_map(|d| d.attribute("ref").map(String::from))
+                    .collect();
+                Some((dependency_ref, depends_on))
+            })
+            .collect(),
+    })
+}
+
+/// Merges the license entries of a component into one expression, read as OR like other package metadata
+///
+/// Tools such as cyclonedx-maven-plugin copy a POM's `<licenses>` into `licenses[]` unchanged, so
+/// OR keeps the result the same as auditing the POM itself (see `any_of_licenses`).
+///
+/// Concluded entries (CycloneDX 1.6 acknowledgement) win over declared ones. Names are mapped
+/// to SPDX ids through the database; names that cannot be mapped are kept as `LicenseRef-<name>`
+/// so they are analyzed as unknown licenses instead of disappearing from the expression.
+fn set_cdx_license(component: &mut Component, database: &LicenseDatabase, licenses: &[CdxLicense]) {
+    if licenses.is_empty() {
+        return;
+    }
+    let concluded: Vec<&CdxLicense> = licenses.iter().filter(|l| l.acknowledgement.as_deref() == Some("concluded")).collect();
+    let entries: Vec<&CdxLicense> = if concluded.is_empty() { licenses.iter().collect() } else { concluded };
+    component.declared_license = Some(licenses.iter()
+        .filter(|l| l.acknowledgement.as_deref() != Some("concluded"))
+        .map(|l| l.value.clone())
+        .collect::<Vec<_>>()
+        .join("; "))
+        .filter(|declared| !declared.is_empty());
+
+    let mut parts: Vec<String> = Vec::new();
+    for license in &entries {
+        let mapped = match license.kind {
+            CdxLicenseKind::Expression => Some(license.value.clone()),
+            CdxLicenseKind::Id => Some(database.get(&license.value).map(|l| l.name).unwrap_or_else(|| license.value.clone())),
+            CdxLicenseKind::Name => {
+                let matched = database.map_license_name(&license.value)
+                    .filter(|m| m.method != NameMatchMethod::Suggested)
+                    .or_else(|| license.url.as_deref().and_then(|url| database.map_license_url(url)))
+                    .or_else(|| database.map_license_name(&license.value));
+                match matched {
+                    Some(matched) => {
+                        component.notes.push(format!("'{}' read as {} ({})", license.value, matched.id, matched.method));
+                        Some(matched.id)
+                    }
+                    None if license.value.trim().is_empty() => None,
+                    None => {
+                        let reference = license_ref(&license.value);
+                        component.notes.push(format!("License name '{}' not recognized; kept as {}", license.value, reference));
+                        Some(reference)
+                    }
+                }
+            }
+        };
+        if let Some(part) = mapped.filter(|p| !p.is_empty()) {
+            if !parts.contains(&part) {
+                parts.push(part);
+            }
+        }
+    }
+
+    if entries.len() > 1 {
+        component.notes.push("Several license entries read as OR".to_string());
+    }
+    component.expression = any_of_licenses(&parts);
+}
+
+/// A `LicenseRef-` id for a license name, with the characters SPDX does not allow in ids replaced
+fn license_ref(name: &str) -> String {
+    let reference: String = name.trim().chars().map(|c| if c.is_ascii_alphanumeric() || c == '.' { c } else { '-' }).collect();
+    format!("LicenseRef-{}", reference)
+}
+
+/// Flattens nested components, recording each parent-to-child nesting as a dependency edge
+fn flatten<'a>(components: &'a [CdxComponent], parent: Option<&str>, flat: &mut Vec<&'a CdxComponent>, nesting: &mut Vec<(String, String)>) {
+    for component in components {
+        if let Some(parent) = parent {
+            nesting.push((parent.to_string(), component.id()));
+        }
+        flat.push(component);
+        flatten(&component.components, Some(&component.id()), flat, nesting);
+    }
+}
+
+/// Builds an inventory from a CycloneDX BOM in JSON or XML form
+///
+/// Nested components and the `dependencies` graph are kept as dependency edges. The BOM's
+/// metadata component is the supplier's product and is audited like any dependency.
+pub fn inventory_from_cyclonedx(database: &LicenseDatabase, path: &Path) -> Result<Inventory, InventoryError> {
+    let content = read_bom_file(path)?;
+    let bom = parse_bom(&content).map_err(|e| InventoryError::ParseError(format!("{}: {}", path.display(), e)))?;
+    Ok(inventory_from_bom(database, &bom, path))
+}
+
+fn read_bom_file(path: &Path) -> Result<String, InventoryError> {
+    fs::read_to_string(path).map_err(|e| InventoryError::FileReadError(format!("{}: {}", path.display(), e)))
+}
+
+fn is_xml(content: &str) -> bool {
+    content.trim_start().starts_with('<')
+}
+
+fn parse_bom(content: &str) -> Result<CdxBom, String> {
+    let bom = if is_xml(content) { parse_xml_bom(content) } else { parse_json_bom(content) }?;
+    if !SUPPORTED_SPEC_VERSIONS.contains(&bom.spec_version.as_str()) {
+        log::warn!("CycloneDX spec version '{}' is not 1.4-1.6; reading it anyway", bom.spec_version);
+    }
+    Ok(bom)
+}
+
+fn inventory_from_bom(database: &LicenseDatabase, bom: &CdxBom, path: &Path) -> Inventory {
+    let mut flat: Vec<&CdxComponent> = Vec::new();
+    let mut nesting: Vec<(String, String)> = Vec::new();
+    let roots: Vec<String> = bom.metadata_component.iter().map(CdxComponent::id).collect();
+    flatten(bom.metadata_component.as_slice(), None, &mut flat, &mut nesting);
+    flatten(&bom.components, None, &mut flat, &mut nesting);
+
+    let mut edges: HashMap<String, Vec<(String, DependencyKind)>> = HashMap::new();
+    let excluded = |id: &str| flat.iter().any(|c| c.id() == id && c.scope.as_deref() == Some("excluded"));
+    let kind_of = |id: &str| if excluded(id) { DependencyKind::Dev } else { DependencyKind::Normal };
+    for (parent, child) in &nesting {
+        edges.entry(parent.clone()).or_default().push((child.clone(), kind_of(child)));
+    }
+    for (dependency_ref, depends_on) in &bom.dependencies {
+        let dependency_edges = edges.entry(dependency_ref.clone()).or_default();
+        for target in depends_on {
+            if !dependency_edges.iter().any(|(id, _)| id == target) {
+                dependency_edges.push((target.clone(), kind_of(target)));
+            }
+        }
+    }
+    // Without a graph for the product, every top-level component is taken to be its dependency
+    if let Some(root) = roots.first() {
+        if edges.get(root).is_none_or(Vec::is_empty) {
+            edges.insert(root.clone(), bom.components.iter().map(|c| (c.id(), kind_of(&c.id()))).collect());
+        }
+    }
+    let kinds = resolve_kinds(&roots, &edges);
+
+    let components = flat.iter()
+        .map(|cdx| {
+            let id = cdx.id();
+            let kind = match kinds.get(&id).copied() {
+                Some(DependencyKind::Root) | None => kind_of(&id),
+                Some(kind) => kind,
+            };
+            let mut component = Component::new(&id, &cdx.display_name(), cdx.version.as_deref(), kind);
+            component.dependencies = edges.get(&id).into_iter().flatten().map(|(d, _)| d.clone()).collect();
+            set_cdx_license(&mut component, database, &cdx.licenses);
+            component
+        })
+        .collect();
+
+    Inventory {
+        source: path.display().to_string(),
+        roots,
+        components,
+    }
+}
+
+/// Namespace of the properties added by [`enrich_cyclonedx`], following the CycloneDX property taxonomy convention
+pub const CDX_PROPERTY_NAMESPACE: &str = "license-expression-copyleft";
+
+/// Child elements that follow `<properties>` in a CycloneDX component
+const XML_ELEMENTS_AFTER_PROPERTIES: [&str; 7] = ["components", "evidence", "releaseNotes", "modelCard", "data", "cryptoProperties", "signature"];
+
+/// A CycloneDX BOM written back with the analysis of each component as properties
+#[derive(Debug, Clone)]
+pub struct CdxEnrichment {
+    /// The BOM in its original format (JSON or XML)
+    pub content: String,
+    pub report: AuditReport,
+    /// Number of components that received properties
+    pub annotated: usize,
+}
+
+/// Properties describing the analysis of one component, names prefixed with the namespace
+fn component_properties(audit: &ComponentAudit) -> Vec<(String, String)> {
+    let name = |property: &str| format!("{}:{}", CDX_PROPERTY_NAMESPACE, property);
+    let mut properties = Vec::new();
+    if let Some(ref analysis) = audit.analysis {
+        if let Some(ref expression) = analysis.parsed_expression {
+            properties.push((name("expression"), expression.to_string()));
+        }
+    }
+    if let Some(category) = audit.effective_copyleft() {
+        properties.push((name("copyleft-category"), category.to_string()));
+    }
+    properties.push((name("risk-level"), audit.risk_level().to_string()));
+    if let Some(choice) = audit.analysis.as_ref().and_then(|a| a.recommended_choice.as_ref()) {
+        properties.push((name("recommended-choice"), choice.name.clone()));
+    }
+    let verdict = if audit.violations.is_empty() { AuditVerdict::Pass } else { AuditVerdict::Fail };
+    properties.push((name("policy-verdict"), verdict.to_string()));
+    for violation in &audit.violations {
+        properties.push((name("policy-violation"), violation.message.clone()));
+    }
+    for conflict in audit.analysis.iter().flat_map(|a| a.conflicts.iter()) {
+        properties.push((name("conflict"), conflict.clone()));
+    }
+    properties
+}
+
+fn is_own_property(name: &str) -> bool {
+    name.strip_prefix(CDX_PROPERTY_NAMESPACE).is_some_and(|rest| rest.starts_with(':'))
+}
+
+/// Position of the first non-whitespace byte at or after `position`
+fn skip_json_whitespace(content: &str, position: usize) -> usize {
+    content[position..].find(|c: char| !c.is_ascii_whitespace()).map_or(content.len(), |i| position + i)
+}
+
+/// End of the JSON value that starts at `position`
+fn json_value_end(content: &str, position: usize) -> Option<usize> {
+    let bytes = content.as_bytes();
+    match bytes.get(position)? {
+        b'"' => {
+            let mut i = position + 1;
+            while i < bytes.len() {
+                match bytes[i] {
+                    b'\\' => i += 2,
+                    b'"' => return Some(i + 1),
+                    _ => i += 1,
+                }
+            }
+            None
+        }
+        b'{' | b'[' => {
+            let mut i = skip_json_whitespace(content, position + 1);
+            if matches!(bytes.get(i), Some(b'}' | b']')) {
+                return Some(i + 1);
+            }
+            loop {
+                i = json_value_end(content, i)?;
+                i = skip_json_whitespace(content, i);
+                if bytes.get(i) == Some(&b':') {
+                    i = json_value_end(content, skip_json_whitespace(content, i + 1))?;
+                    i = skip_json_whitespace(content, i);
+                }
+                match bytes.get(i)? {
+                    b',' => i = skip_json_whitespace(content, i + 1),
+                    b'}' | b']' => return Some(i + 1),
+                    _ => return None,
+                }
+            }
+        }
+        _ => content[position..].find(|c: char| matches!(c, ',' | '}' | ']') || c.is_ascii_whitespace()).map(|i| position + i),
+    }
+}
+
+/// A member of a JSON object: its key, the range of the key and the range of its value
+struct JsonMember {
+    key: String,
+    key_range: Range<usize>,
+    value: Range<usize>,
+}
+
+/// Members of the JSON object spanning `object`
+fn json_members(content: &str, object: &Range<usize>) -> Option<Vec<JsonMember>> {
+    let mut members = Vec::new();
+    let mut i = skip_json_whitespace(content, object.start + 1);
+    while content.as_bytes().get(i) == Some(&b'"') {
+        let key_end = json_value_end(content, i)?;
+        let key = serde_json::from_str(&content[i..key_end]).ok()?;
+        let value_start = skip_json_whitespace(content, skip_json_whitespace(content, key_end) + 1);
+        let value_end = json_value_end(content, value_start)?;
+        members.push(JsonMember { key, key_range: i..key_end, value: value_start..value_end });
+        i = skip_json_whitespace(content, value_end);
+        if content.as_bytes().get(i) == Some(&b',') {
+            i = skip_json_whitespace(content, i + 1);
+        }
+    }
+    Some(members)
+}
+
+/// Elements of the JSON array spanning `array`
+fn json_elements(content: &str, array: &Range<usize>) -> Option<Vec<Range<usize>>> {
+    let mut elements = Vec::new();
+    let mut i = skip_json_whitespace(content, array.start + 1);
+    while i < array.end - 1 {
+        let end = json_value_end(content, i)?;
+        elements.push(i..end);
+        i = skip_json_whitespace(content, end);
+        if content.as_bytes().get(i) == Some(&b',') {
+            i = skip_json_whitespace(content, i + 1);
+        }
+    }
+    Some(elements)
+}
+
+/// Components of the JSON BOM: the metadata component and every (nested) entry of `components`
+fn json_component_ranges(content: &str) -> Option<Vec<Range<usize>>> {
+    let root_start = skip_json_whitespace(content, 0);
+    let root = root_start..json_value_end(content, root_start)?;
+    let members = json_members(content, &root)?;
+    let mut pending = Vec::new();
+    for member in &members {
+        match member.key.as_str() {
+            "metadata" if content[member.value.clone()].starts_with('{') => {
+                let metadata = json_members(content, &member.value)?;
+                pending.extend(metadata.into_iter().filter(|m| m.key == "component").map(|m| m.value));
+            }
+            "components" if content[member.value.clone()].starts_with('[') => pending.extend(json_elements(content, &member.value)?),
+            _ => {}
+        }
+    }
+    let mut components = Vec::new();
+    while let Some(component) = pending.pop() {
+        if !content[component.clone()].starts_with('{') {
+            continue;
+        }
+        for member in json_members(content, &component)? {
+            if member.key == "components" && content[member.value.clone()].starts_with('[') {
+                pending.extend(json_elements(content, &member.value)?);
+            }
+        }
+        components.push(component);
+    }
+    Some(components)
+}
+
+fn json_string(value: &str) -> String {
+    serde_json::to_string(value).expect("strings serialize")
+}
+
+/// Rewrites the namespace's properties of each JSON component in place
+///
+/// Only the `properties` array of an annotated component is replaced (or added after its last
+/// member); the rest of the text, including formatting, number spelling and escapes, is kept.
+fn enrich_json(content: &str, properties: &HashMap<String, Vec<(String, String)>>) -> Result<(String, usize), String> {
+    let malformed = || "malformed JSON".to_string();
+    let mut edits: Vec<(usize, usize, String)> = Vec::new();
+    let mut annotated = 0;
+    for component in json_component_ranges(content).ok_or_else(malformed)? {
+        let value: Value = serde_json::from_str(&content[component.clone()]).map_err(|e| e.to_string())?;
+        let Some(own) = properties.get(&json_component(&value).id()) else {
+            continue;
+        };
+        let members = json_members(content, &component).ok_or_else(malformed)?;
+        // Formatting follows the component's own members: indented on their own lines, or inline
+        let member_indent = members.first().and_then(|m| line_indent(content, m.key_range.start));
+        let separator = members.first()
+            .map(|m| &content[m.key_range.end..m.value.start])
+            .and_then(|between| between.find(':').map(|colon| &between[colon..]))
+            .unwrap_or(":");
+        let comma = members.get(..2)
+            .and_then(|pair| (pair.len() == 2).then(|| &content[pair[0].value.end..pair[1].key_range.start]))
+            .unwrap_or(",");
+        let line_start = content[..component.start].rfind('\n').map_or(0, |i| i + 1);
+        let component_indent = &content[line_start..skip_json_whitespace(content, line_start)];
+        let unit = member_indent
+            .and_then(|indent| indent.strip_prefix(component_indent))
+            .filter(|unit| !unit.is_empty())
+            .unwrap_or("  ");
+        let property_text = |name: &str, value: &str| match member_indent {
+            Some(indent) => format!("{{\n{i}{u}{u}\"name\"{s}{n},\n{i}{u}{u}\"value\"{s}{v}\n{i}{u}}}",
+                i = indent, u = unit, s = separator, n = json_string(name), v = json_string(value)),
+            None => format!("{{\"name\"{s}{n}{c}\"value\"{s}{v}}}", s = separator, c = comma, n = json_string(name), v = json_string(value)),
+        };
+
+        let existing = members.iter().find(|m| m.key == "properties" && content[m.value.clone()].starts_with('['));
+        let mut elements: Vec<String> = Vec::new();
+        if let Some(existing) = existing {
+            for element in json_elements(content, &existing.value).ok_or_else(malformed)? {
+                let property: Value = serde_json::from_str(&content[element.clone()]).map_err(|e| e.to_string())?;
+                if !property.get("name").and_then(Value::as_str).is_some_and(is_own_property) {
+                    elements.push(content[element].to_string());
+                }
+            }
+        }
+        elements.extend(own.iter().map(|(name, value)| property_text(name, value)));
+        let array = match member_indent {
+            Some(indent) => format!("[\n{}\n{}]", elements.iter().map(|e| format!("{}{}{}", indent, unit, e)).collect::<Vec<_>>().join(",\n"), indent),
+            None => format!("[{}]", elements.join(comma)),
+        };
+
+        match (existing, members.last()) {
+            (Some(existing), _) => edits.push((existing.value.start, existing.value.end, array)),
+            (None, Some(last)) => {
+                let member = match member_indent {
+                    Some(indent) => format!(",\n{}\"properties\"{}{}", indent, separator, array),
+                    None => format!("{}\"properties\"{}{}", comma, separator, array),
+                };
+                edits.push((last.value.end, last.value.end, member));
+            }
+            (None, None) => edits.push((component.start + 1, component.start + 1, format!("\"properties\"{}{}", separator, array))),
+        }
+        annotated += 1;
+    }
+
+    let mut output = content.to_string();
+    edits.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.cmp(&a.1)));
+    for (start, end, replacement) in edits {
+        output.replace_range(start..end, &replacement);
+    }
+    Ok((output, annotated))
+}
+
+fn escape_xml(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Whitespace that starts the line holding `position`, if only whitespace precedes it on that line
+fn line_indent(content: &str, position: usize) -> Option<&str> {
+    let line_start = content[..position].rfind('\n').map_or(0, |i| i + 1);
+    let indent = &content[line_start..position];
+    indent.chars().all(char::is_whitespace).then_some(indent)
+}
+
+/// Text that inserts `lines` before the markup at `position`, on their own lines when the markup starts a line
+fn insertion(content: &str, position: usize, lines: &[String], indent: &str) -> (usize, String) {
+    match line_indent(content, position) {
+        Some(own_indent) => {
+            let text = lines.iter().map(|line| format!("{}{}\n", indent, line)).collect::<String>();
+            (position - own_indent.len(), text)
+        }
+        None => (position, lines.concat()),
+    }
+}
+
+/// Start of the closing tag of an element
+fn closing_tag_start(content: &str, node: Node) -> Option<usize> {
+    let range = node.range();
+    content[range.clone()].rfind("</").map(|i| range.start + i)
+}
+
+fn enrich_xml(content: &str, properties: &HashMap<String, Vec<(String, String)>>) -> Result<(String, usize), String> {
+    let document = Document::parse(content).map_err(|e| e.to_string())?;
+    let bom = document.root_element();
+    let mut components = Vec::new();
+    let mut pending: Vec<Node> = child(bom, "metadata").and_then(|m| child(m, "component")).into_iter()
+        .chain(children(child(bom, "components"), "component"))
+        .collect();
+    while let Some(node) = pending.pop() {
+        pending.extend(children(child(node, "components"), "component"));
+        components.push(node);
+    }
+
+    // (start, end, replacement) edits, applied back to front
+    let mut edits: Vec<(usize, usize, String)> = Vec::new();
+    let mut annotated = 0;
+    for node in components {
+        let Some(own) = properties.get(&xml_component(node).id()) else {
+            continue;
+        };
+        let indent = line_indent(content, node.range().start).unwrap_or_default();
+        let child_indent = node.children().filter(Node::is_element)
+            .find_map(|c| line_indent(content, c.range().start))
+            .map(String::from)
+            .unwrap_or_else(|| format!("{}  ", indent));
+        let unit = child_indent.strip_prefix(indent).filter(|u| !u.is_empty()).unwrap_or("  ");
+        let property_lines: Vec<String> = own.iter()
+            .map(|(name, value)| format!("<property name=\"{}\">{}</property>", escape_xml(name), escape_xml(value)))
+            .collect();
+
+        match child(node, "properties") {
+            Some(existing) => {
+                for property in children(Some(existing), "property").filter(|p| p.attribute("name").is_some_and(is_own_property)) {
+                    let range = property.range();
+                    let (start, end) = match line_indent(content, range.start) {
+                        Some(own_indent) if content[range.end..].starts_with('\n') => (range.start - own_indent.len(), range.end + 1),
+                        _ => (range.start, range.end),
+                    };
+                    edits.push((start, end, String::new()));
+                }
+                let properties_indent = line_indent(content, existing.range().start).unwrap_or(&child_indent);
+                let property_indent = format!("{}{}", properties_indent, unit);
+                match closing_tag_start(content, existing) {
+                    Some(position) => {
+                        let (at, text) = insertion(content, position, &property_lines, &property_indent);
+                        edits.push((at, at, text));
+                    }
+                    // <properties/>
+                    None => {
+                        let range = existing.range();
+                        edits.push((range.start, range.end, format!("<properties>{}</properties>", property_lines.concat())));
+                    }
+                }
+            }
+            None => {
+                let before = node.children()
+                    .find(|c| c.is_element() && XML_ELEMENTS_AFTER_PROPERTIES.contains(&c.tag_name().name()))
+                    .map(|c| c.range().start)
+                    .or_else(|| closing_tag_start(content, node));
+                let Some(position) = before else {
+                    continue;
+                };
+                let mut lines = vec!["<properties>".to_string()];
+                lines.extend(property_lines.iter().map(|line| format!("{}{}", unit, line)));
+                lines.push("</properties>".to_string());
+                let (at, text) = match line_indent(content, position) {
+                    Some(_) => insertion(content, position, &lines, &child_indent),
+                    None => (position, format!("<properties>{}</properties>", property_lines.concat())),
+                };
+                edits.push((at, at, text));
+            }
+        }
+        annotated += 1;
+    }
+
+    let mut output = content.to_string();
+    edits.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.cmp(&a.1)));
+    for (start, end, replacement) in edits {
+        output.replace_range(start..end, &replacement);
+    }
+    Ok((output, annotated))
+}
+
+/// Audits a CycloneDX BOM and writes each component's analysis back as `properties`
+///
+/// Properties in [`CDX_PROPERTY_NAMESPACE`] are replaced, so enriching twice gives the same BOM.
+/// Nothing else is changed: both JSON and XML are edited in place.
+pub fn enrich_cyclonedx(parser: &LicenseExpressionParser, path: &Path, policy: &LicensePolicy) -> Result<CdxEnrichment, InventoryError> {
+    let content = read_bom_file(path)?;
+    let parse_error = |e: String| InventoryError::ParseError(format!("{}: {}", path.display(), e));
+    let bom = parse_bom(&content).map_err(parse_error)?;
+    let inventory = inventory_from_bom(&parser.database(), &bom, path);
+    let report = audit_inventory(parser, &inventory, policy);
+
+    let mut properties: HashMap<String, Vec<(String, String)>> = HashMap::new();
+    for audit in &report.components {
+        properties.entry(audit.component.id.clone()).or_insert_with(|| component_properties(audit));
+    }
+    let (content, annotated) = if is_xml(&content) {
+        enrich_xml(&content, &properties)
+    } else {
+        enrich_json(&content, &properties)
+    }
+    .map_err(parse_error)?;
+
+    Ok(CdxEnrichment { content, report, annotated })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::RiskLevel;
+    use crate::test_support::{parser, temp_dir, write_file};
+
+    const BOM: &str = r#"{
+  "bomFormat": "CycloneDX",
+  "specVersion": "1.5",
+  "metadata": {"component": {"bom-ref": "app", "name": "app", "licenses": [{"license": {"id": "MIT"}}]}},
+  "components": [
+    {"bom-ref": "acme", "name": "acme", "licenses": [{"license": {"id": "MIT"}}, {"license": {"name": "ACME Proprietary EULA"}}]},
+    {"bom-ref": "eula", "name": "eula", "licenses": [{"license": {"name": "ACME Proprietary EULA"}}]},
+    {"bom-ref": "apache", "name": "apache", "licenses": [{"license": {"name": "Apache License, Version 2.0"}}]}
+  ]
+}
+"#;
+
+    fn component<'a>(inventory: &'a Inventory, id: &str) -> &'a Component {
+        inventory.components.iter().find(|c| c.id == id).unwrap()
+    }
+
+    #[test]
+    fn reads_json_bom() {
+        let dir = temp_dir("cdx-json");
+        let path = write_file(&dir, "bom.cdx.json", BOM);
+        let inventory = inventory_from_cyclonedx(&parser().database(), &path).unwrap();
+        assert_eq!(inventory.roots, vec!["app"]);
+        assert_eq!(component(&inventory, "app").dependencies, vec!["acme", "eula", "apache"]);
+        assert_eq!(component(&inventory, "apache").expression.as_deref(), Some("Apache-2.0"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_unmapped_license_names() {
+        let dir = temp_dir("cdx-names");
+        let path = write_file(&dir, "bom.cdx.json", BOM);
+        let parser = parser();
+        let inventory = inventory_from_cyclonedx(&parser.database(), &path).unwrap();
+        let acme = component(&inventory, "acme");
+        assert_eq!(acme.expression.as_deref(), Some("MIT OR LicenseRef-ACME-Proprietary-EULA"));
+        assert!(acme.notes.contains(&"Several license entries read as OR".to_string()));
+        assert!(acme.notes.iter().any(|n| n.contains("kept as LicenseRef-ACME-Proprietary-EULA")));
+        assert_eq!(component(&inventory, "eula").expression.as_deref(), Some("LicenseRef-ACME-Proprietary-EULA"));
+
+        let report = audit_inventory(&parser, &inventory, &LicensePolicy::default());
+        let audit = report.components.iter().find(|a| a.component.id == "acme").unwrap();
+        assert_eq!(audit.risk_level(), RiskLevel::Unknown);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn enrich(content: &str) -> CdxEnrichment {
+        let dir = temp_dir("cdx-enrich");
+        let name = if is_xml(content) { "bom.cdx.xml" } else { "bom.cdx.json" };
+        let path = write_file(&dir, name, content);
+        let enrichment = enrich_cyclonedx(&parser(), &path, &LicensePolicy::default()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        enrichment
+    }
+
+    #[test]
+    fn enrich_json_splices_into_compact_text() {
+        let bom = concat!(
+            r#"{"bomFormat":"CycloneDX","specVersion":"1.5","version":1,"#,
+            r#""components":[{"bom-ref":"a","name":"café","description":"caf\u00e9","x-score":1.50,"licenses":[{"license":{"id":"MIT"}}],"#,
+            r#""properties":[{"name":"other:keep","value":"1"},{"name":"license-expression-copyleft:risk-level","value":"stale"}]}]}"#,
+        );
+        let enrichment = enrich(bom);
+        assert_eq!(enrichment.annotated, 1);
+        let content = &enrichment.content;
+        assert!(!content.contains('\n'));
+        assert!(content.starts_with(r#"{"bomFormat":"CycloneDX","specVersion":"1.5","version":1,"components":[{"bom-ref":"a","name":"café","description":"caf\u00e9","x-score":1.50,"#));
+        assert!(content.contains(r#""properties":[{"name":"other:keep","value":"1"},{"name":"license-expression-copyleft:expression","value":"MIT"},"#));
+        assert!(!content.contains("stale"));
+        assert_eq!(enrich(content).content, *content);
+    }
+
+    #[test]
+    fn enrich_json_adds_indented_properties() {
+        let bom = "{\n  \"bomFormat\": \"CycloneDX\",\n  \"specVersion\": \"1.5\",\n  \"components\": [\n    {\n      \"name\": \"a\",\n      \"licenses\": [{\"license\": {\"id\": \"MIT\"}}]\n    }\n  ]\n}\n";
+        let content = enrich(bom).content;
+        let expected = "      \"licenses\": [{\"license\": {\"id\": \"MIT\"}}],\n      \"properties\": [\n        {\n          \"name\": \"license-expression-copyleft:expression\",\n          \"value\": \"MIT\"\n        },\n";
+        assert!(content.contains(expected), "{}", content);
+        assert!(content.ends_with("    }\n  ]\n}\n"));
+        assert_eq!(serde_json::from_str::<Value>(&content).unwrap()["components"][0]["properties"].as_array().unwrap().len(), 5);
+    }
+
+    #[test]
+    fn enrich_xml_splices_into_compact_text() {
+        let bom = concat!(
+            r#"<?xml version="1.0" encoding="UTF-8"?><!-- keep --><bom xmlns="http://cyclonedx.org/schema/bom/1.5" version="1"><components>"#,
+            r#"<component type="library" bom-ref="a"><name>caf&#233;</name><licenses><license><id>MIT</id></license></licenses><properties/></component>"#,
+            r#"<component type="library" bom-ref="b"><name>b</name><licenses><license><id>Apache-2.0</id></license></licenses>"#,
+            r#"<properties><property name="other:keep">1</property><property name="license-expression-copyleft:risk-level">stale</property></properties>"#,
+            r#"<components><component type="library" bom-ref="c"><name>c</name><licenses><license><id>GPL-3.0-only</id></license></licenses></component></components>"#,
+            r#"</component></components></bom>"#,
+        );
+        let enrichment = enrich(bom);
+        assert_eq!(enrichment.annotated, 3);
+        let content = &enrichment.content;
+        assert!(!content.contains('\n'));
+        assert!(content.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?><!-- keep --><bom xmlns="http://cyclonedx.org/schema/bom/1.5" version="1"><components><component type="library" bom-ref="a"><name>caf&#233;</name>"#));
+        assert!(content.contains(r#"</licenses><properties><property name="license-expression-copyleft:expression">MIT</property>"#));
+        assert!(content.contains(r#"<properties><property name="other:keep">1</property><property name="license-expression-copyleft:expression">Apache-2.0</property>"#));
+        assert!(content.contains(r#"</property></properties><components><component type="library" bom-ref="c">"#));
+        assert!(content.contains(r#"<id>GPL-3.0-only</id></license></licenses><properties><property name="license-expression-copyleft:expression">GPL-3.0-only</property>"#));
+        assert!(!content.contains("stale"));
+        assert_eq!(enrich(content).content, *content);
+    }
+
+    #[test]
+    fn enrich_xml_adds_indented_properties() {
+        let bom = concat!(
+            "<?xml version=\"1.0\"?>\n",
+            "<bom xmlns=\"http://cyclonedx.org/schema/bom/1.5\" version=\"1\">\n",
+            "    <components>\n",
+            "        <component type=\"library\" bom-ref=\"a\">\n",
+            "            <name>a</name>\n",
+            "            <licenses><license><id>MIT</id></license></licenses>\n",
+            "            <components>\n",
+            "                <component type=\"library\" bom-ref=\"b\">\n",
+            "                    <name>b</name>\n",
+            "                    <properties>\n",
+            "                        <property name=\"license-expression-copyleft:risk-level\">stale</property>\n",
+            "                    </properties>\n",
+            "                </component>\n",
+            "            </components>\n",
+            "        </component>\n",
+            "    </components>\n",
+            "</bom>\n",
+        );
+        let content = enrich(bom).content;
+        let expected = concat!(
+            "            <licenses><license><id>MIT</id></license></licenses>\n",
+            "            <properties>\n",
+            "                <property name=\"license-expression-copyleft:expression\">MIT</property>\n",
+        );
+        assert!(content.contains(expected), "{}", content);
+        assert!(content.contains("            </properties>\n            <components>\n"), "{}", content);
+        assert!(content.contains("                    <name>b</name>\n                    <properties>\n                        <property name=\"license-expression-copyleft:risk-level\">Unknown</property>\n"), "{}", content);
+        assert!(!content.contains("stale"));
+        assert!(content.ends_with("        </component>\n    </components>\n</bom>\n"));
+        assert_eq!(enrich(&content).content, content);
+    }
+}
@@ -0,0 +1,482 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use regex::Regex;
+use serde::Serialize;
+use crate::license_expression_parser::LicenseExpressionParser;
+use crate::models::{LicenseAnalysis, SpdxExpr};
+use crate::source_scanner::aggregate;
+
+/// Where Debian packages install their copyright files, relative to the root filesystem
+pub const DEBIAN_DOC_DIR: &str = "usr/share/doc";
+
+/// Debian short license names that differ from their SPDX id
+/// Names are compared case-insensitively; anything else is looked up in the database.
+const DEBIAN_LICENSE_NAMES: &[(&str, &str)] = &[
+    ("gpl", "GPL-1.0-or-later"),
+    ("gpl-1", "GPL-1.0-only"),
+    ("gpl-1+", "GPL-1.0-or-later"),
+    ("gpl-2", "GPL-2.0-only"),
+    ("gpl-2+", "GPL-2.0-or-later"),
+    ("gpl-3", "GPL-3.0-only"),
+    ("gpl-3+", "GPL-3.0-or-later"),
+    ("lgpl", "LGPL-2.0-or-later"),
+    ("lgpl-2", "LGPL-2.0-only"),
+    ("lgpl-2+", "LGPL-2.0-or-later"),
+    ("lgpl-2.1", "LGPL-2.1-only"),
+    ("lgpl-2.1+", "LGPL-2.1-or-later"),
+    ("lgpl-3", "LGPL-3.0-only"),
+    ("lgpl-3+", "LGPL-3.0-or-later"),
+    ("agpl-3", "AGPL-3.0-only"),
+    ("agpl-3+", "AGPL-3.0-or-later"),
+    ("gfdl-1.2", "GFDL-1.2-only"),
+    ("gfdl-1.2+", "GFDL-1.2-or-later"),
+    ("gfdl-1.3", "GFDL-1.3-only"),
+    ("gfdl-1.3+", "GFDL-1.3-or-later"),
+    ("expat", "MIT"),
+    ("apache-2", "Apache-2.0"),
+    ("artistic", "Artistic-1.0-Perl"),
+    ("artistic-2", "Artistic-2.0"),
+    ("perl", "(Artistic-1.0-Perl OR GPL-1.0-or-later)"),
+    ("mpl-2", "MPL-2.0"),
+    ("cc0", "CC0-1.0"),
+    ("zlib", "Zlib"),
+    ("boost-1.0", "BSL-1.0"),
+    ("psf-2", "PSF-2.0"),
+    ("python", "Python-2.0"),
+    ("python-2", "Python-2.0"),
+    ("public-domain", "LicenseRef-scancode-public-domain"),
+];
+
+/// Names used in `<license> with <name> exception` and their SPDX exception id
+const DEBIAN_EXCEPTION_NAMES: &[(&str, &str)] = &[
+    ("classpath", "Classpath-exception-2.0"),
+    ("font", "Font-exception-2.0"),
+    ("gcc", "GCC-exception-3.1"),
+    ("bison", "Bison-exception-2.2"),
+    ("libtool", "Libtool-exception"),
+    ("llvm", "LLVM-exception"),
+];
+
+/// A comma followed by `and`/`or`, the loosest-binding operator of a License field
+static LOOSE_OPERATOR: OnceLock<Regex> = OnceLock::new();
+
+/// One `Files:` stanza of a DEP-5 file
+#[derive(Debug, Clone, Serialize)]
+pub struct Dep5FilesStanza {
+    pub files: Vec<String>,
+    pub copyright: Vec<String>,
+    /// First line of the License field, in Debian syntax
+    pub license: String,
+}
+
+/// A machine-readable debian/copyright file
+#[derive(Debug, Clone, Serialize)]
+pub struct DebianCopyright {
+    pub upstream_name: Option<String>,
+    pub stanzas: Vec<Dep5FilesStanza>,
+}
+
+/// Analysis of one `Files:` stanza
+#[derive(Debug, Clone, Serialize)]
+pub struct Dep5FilesAnalysis {
+    pub files: Vec<String>,
+    pub license: String,
+    pub expression: Option<String>,
+    pub analysis: Option<LicenseAnalysis>,
+    /// Debian names that needed a heuristic translation
+    pub notes: Vec<String>,
+    pub error: Option<String>,
+}
+
+/// Licenses of one installed package
+#[derive(Debug, Clone, Serialize)]
+pub struct DebianPackageLicenses {
+    pub package: String,
+    pub copyright_file: PathBuf,
+    pub stanzas: Vec<Dep5FilesAnalysis>,
+    /// AND of the distinct stanza expressions
+    pub expression: Option<String>,
+    pub analysis: Option<LicenseAnalysis>,
+    /// Set when the copyright file could not be read or is not in DEP-5 format
+    pub error: Option<String>,
+}
+
+/// Licenses of every package in a root filesystem
+#[derive(Debug, Clone, Serialize)]
+pub struct DebianCopyrightReport {
+    pub root: PathBuf,
+    pub packages: Vec<DebianPackageLicenses>,
+}
+
+/// Splits a Debian control-style file into paragraphs of field name (lowercase) to lines
+/// Continuation lines are kept as separate entries, with the ` .` blank-line marker left in.
+pub(crate) fn dep5_paragraphs(content: &str) -> Vec<BTreeMap<String, Vec<String>>> {
+    let mut paragraphs = Vec::new();
+    let mut fields: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut current: Option<String> = None;
+    for line in content.lines() {
+        if line.trim().is_empty() {
+            if !fields.is_empty() {
+                paragraphs.push(std::mem::take(&mut fields));
+            }
+            current = None;
+        } else if line.starts_with(' ') || line.starts_with('\t') {
+            if let Some(ref field) = current {
+                fields.entry(field.clone()).or_default().push(line.trim().to_string());
+            }
+        } else if line.starts_with('#') {
+            continue;
+        } else if let Some((name, value)) = line.split_once(':') {
+            let name = name.trim().to_lowercase();
+            fields.entry(name.clone()).or_default().push(value.trim().to_string());
+            current = Some(name);
+        }
+    }
+    if !fields.is_empty() {
+        paragraphs.push(fields);
+    }
+    paragraphs
+}
+
+/// Parses a machine-readable debian/copyright file
+/// Returns an error for free-form copyright files, which have no `Format:` header.
+pub fn parse_debian_copyright(content: &str) -> Result<DebianCopyright, String> {
+    if content.trim().is_empty() {
+        return Err("Empty copyright file".to_string());
+    }
+    let paragraphs = dep5_paragraphs(content);
+    let header = paragraphs.first()
+        .filter(|header| header.contains_key("format") || header.contains_key("format-specification"))
+        .ok_or_else(|| "Not a machine-readable (DEP-5) copyright file".to_string())?;
+
+    let stanzas = paragraphs.iter()
+        .filter_map(|fields| {
+            let files = fields.get("files")?;
+            Some(Dep5FilesStanza {
+                files: files.join(" ").split_whitespace().map(String::from).collect(),
+                copyright: fields.get("copyright").cloned().unwrap_or_default()
+                    .into_iter()
+                    .filter(|line| !line.is_empty() && line != ".")
+                    .collect(),
+                license: fields.get("license").and_then(|lines| lines.first().cloned()).unwrap_or_default(),
+            })
+        })
+        .collect();
+
+    Ok(DebianCopyright {
+        upstream_name: header.get("upstream-name").and_then(|lines| lines.first().cloned()),
+        stanzas,
+    })
+}
+
+/// Translates one Debian short name to an SPDX id, noting names that needed a guess
+fn translate_name(parser: &LicenseExpressionParser, name: &str, notes: &mut Vec<String>) -> String {
+    let lowercase = name.to_lowercase();
+    if let Some((_, id)) = DEBIAN_LICENSE_NAMES.iter().find(|(debian, _)| *debian == lowercase) {
+        return id.to_string();
+    }
+    let database = parser.database();
+    if let Some(license) = database.get(name) {
+        return license.name;
+    }
+    // `<name>+` is the Debian spelling of "or any later version"
+    if let Some(base) = name.strip_suffix('+') {
+        for candidate in [format!("{}-or-later", base), format!("{}.0-or-later", base), format!("{}+", base)] {
+            if let Some(license) = database.get(&candidate) {
+                return license.name;
+            }
+        }
+    }
+    if let Some(matched) = database.map_license_name(name) {
+        notes.push(format!("'{}' read as {} ({})", name, matched.id, matched.method));
+        return matched.id;
+    }
+    let reference: String = name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '.' { c } else { '-' }).collect();
+    notes.push(format!("'{}' is not a known license; kept as LicenseRef-Debian-{}", name, reference));
+    format!("LicenseRef-Debian-{}", reference)
+}
+
+/// Translates a group without top-level commas: names joined by `or`/`and`, with optional exceptions
+/// Words between operators form one name, so a stray "Apache 2.0" is still read as a single license.
+fn translate_group(parser: &LicenseExpressionParser, group: &str, notes: &mut Vec<String>) -> String {
+    let mut expression = String::new();
+    let mut name: Vec<&str> = Vec::new();
+    let flush = |name: &mut Vec<&str>, expression: &mut String, notes: &mut Vec<String>| {
+        if !name.is_empty() {
+            expression.push_str(&translate_name(parser, &name.join(" "), notes));
+            name.clear();
+        }
+    };
+
+    let mut words = group.split_whitespace().peekable();
+    while let Some(word) = words.next() {
+        match word.to_lowercase().as_str() {
+            "or" | "and" => {
+                flush(&mut name, &mut expression, notes);
+                expression.push_str(if word.eq_ignore_ascii_case("or") { " OR " } else { " AND " });
+            }
+            "with" => {
+                flush(&mut name, &mut expression, notes);
+                // "with Font exception" or "with OpenSSL-exception"; the exception ends before the next operator
+                let mut exception: Vec<&str> = Vec::new();
+                while let Some(next) = words.next_if(|w| !w.eq_ignore_ascii_case("or") && !w.eq_ignore_ascii_case("and")) {
+                    if next.eq_ignore_ascii_case("exception") {
+                        break;
+                    }
+                    let stem = next.len().saturating_sub("-exception".len());
+                    if next.get(stem..).is_some_and(|suffix| suffix.eq_ignore_ascii_case("-exception")) {
+                        exception.push(&next[..stem]);
+                        break;
+                    }
+                    exception.push(next);
+                }
+                let exception = exception.join(" ");
+                match DEBIAN_EXCEPTION_NAMES.iter().find(|(debian, _)| debian.eq_ignore_ascii_case(&exception)) {
+                    Some((_, id)) => {
+                        expression.push_str(" WITH ");
+                        expression.push_str(id);
+                    }
+                    None if exception.is_empty() => notes.push("Exception without a name is left out".to_string()),
+                    None => {
+                        let reference: String = exception.chars().map(|c| if c.is_ascii_alphanumeric() || c == '.' { c } else { '-' }).collect();
+                        let reference = format!("LicenseRef-Debian-{}-exception", reference);
+                        notes.push(format!("Exception '{}' has no SPDX id; kept as {}", exception, reference));
+                        expression.push_str(" WITH ");
+                        expression.push_str(&reference);
+                    }
+                }
+            }
+            _ => name.push(word),
+        }
+    }
+    flush(&mut name, &mut expression, notes);
+    expression
+}
+
+/// Converts a Debian License field ("GPL-2+ or Artistic, and BSD-3-clause") to an SPDX expression
+///
+/// Debian names are translated to SPDX ids; a comma before `and`/`or` binds loosest, as DEP-5 specifies.
+pub fn debian_license_to_spdx(parser: &LicenseExpressionParser, field: &str) -> Result<(SpdxExpr, Vec<String>), String> {
+    let field = field.trim();
+    if field.is_empty() {
+        return Err("Empty License field".to_string());
+    }
+    let separator = LOOSE_OPERATOR.get_or_init(|| Regex::new(r"(?i),\s*(and|or)\s+").expect("valid regex"));
+
+    let mut notes = Vec::new();
+    let mut expression = String::new();
+    let mut start = 0;
+    let mut operator: Option<&str> = None;
+    for captures in separator.captures_iter(field) {
+        let whole = captures.get(0).expect("match");
+        let group = translate_group(parser, &field[start..whole.start()], &mut notes);
+        expression = match operator {
+            Some(op) => format!("({}) {} ({})", expression, op, group),
+            None => group,
+        };
+        operator = Some(if captures[1].eq_ignore_ascii_case("and") { "AND" } else { "OR" });
+        start = whole.end();
+    }
+    let last = translate_group(parser, &field[start..], &mut notes);
+    expression = match operator {
+        Some(op) => format!("({}) {} ({})", expression, op, last),
+        None => last,
+    };
+
+    parser.parse(&expression).map(|expr| (expr, notes))
+}
+
+/// Analyzes every `Files:` stanza of a copyright file and the package as a whole
+pub fn analyze_debian_copyright(parser: &LicenseExpressionParser, package: &str, path: &Path) -> DebianPackageLicenses {
+    let mut result = DebianPackageLicenses {
+        package: package.to_string(),
+        copyright_file: path.to_path_buf(),
+        stanzas: Vec::new(),
+        expression: None,
+        analysis: None,
+        error: None,
+    };
+    let copyright = match fs::read_to_string(path).map_err(|e| e.to_string()).and_then(|c| parse_debian_copyright(&c)) {
+        Ok(copyright) => copyright,
+        Err(e) => {
+            result.error = Some(e);
+            return result;
+        }
+    };
+
+    let mut expressions: Vec<SpdxExpr> = Vec::new();
+    for stanza in copyright.stanzas {
+        let mut analysis = Dep5FilesAnalysis {
+            files: stanza.files,
+            license: stanza.license.clone(),
+            expression: None,
+            analysis: None,
+            notes: Vec::new(),
+            error: None,
+        };
+        match debian_license_to_spdx(parser, &stanza.license) {
+            Ok((expr, notes)) => {
+                analysis.expression = Some(expr.to_string());
+                analysis.analysis = Some(parser.analyze(&expr.to_string()));
+                analysis.notes = notes;
+                expressions.push(expr);
+            }
+            Err(e) => analysis.error = Some(e),
+        }
+        result.stanzas.push(analysis);
+    }
+    (result.expression, result.analysis) = aggregate(parser, &expressions);
+    result
+}
+
+/// Analyzes `usr/share/doc/*/copyright` below an unpacked root filesystem, or a single copyright file
+pub fn scan_debian_copyright(parser: &LicenseExpressionParser, path: &Path) -> DebianCopyrightReport {
+    let mut packages = Vec::new();
+    if path.is_file() {
+        let package = path.parent().and_then(Path::file_name).map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        packages.push(analyze_debian_copyright(parser, &package, path));
+    } else {
+        let doc_dir = path.join(DEBIAN_DOC_DIR);
+        let mut dirs: Vec<PathBuf> = fs::read_dir(&doc_dir)
+            .map(|entries| entries.flatten().map(|e| e.path()).collect())
+            .unwrap_or_else(|e| {
+                log::warn!("Cannot read {}: {}", doc_dir.display(), e);
+                Vec::new()
+            });
+        dirs.sort();
+        for dir in dirs {
+            let copyright = dir.join("copyright");
+            if copyright.is_file() {
+                let package = dir.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+                packages.push(analyze_debian_copyright(parser, &package, &copyright));
+            }
+        }
+    }
+
+    DebianCopyrightReport {
+        root: path.to_path_buf(),
+        packages,
+    }
+}
+
+fn write_analysis(f: &mut fmt::Formatter<'_>, label: &str, expression: &Option<String>, analysis: &Option<LicenseAnalysis>) -> fmt::Result {
+    match (expression, analysis) {
+        (Some(expression), Some(analysis)) => {
+            writeln!(f, "{}: {} [{}, {}]", label, expression, analysis.risk_level, analysis.strongest_copyleft)
+        }
+        _ => writeln!(f, "{}: NO LICENSE", label),
+    }
+}
+
+impl fmt::Display for DebianCopyrightReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Debian Copyright for: {}", self.root.display())?;
+        let (readable, unreadable): (Vec<&DebianPackageLicenses>, Vec<&DebianPackageLicenses>) =
+            self.packages.iter().partition(|p| p.error.is_none());
+
+        writeln!(f, "Packages ({}):", readable.len())?;
+        for package in &readable {
+            write_analysis(f, &format!("  {}", package.package), &package.expression, &package.analysis)?;
+            for stanza in &package.stanzas {
+                let label = format!("    {} ({})", stanza.files.join(" "), stanza.license);
+                match stanza.error {
+                    Some(ref error) => writeln!(f, "{}: INVALID ({})", label, error)?,
+                    None => write_analysis(f, &label, &stanza.expression, &stanza.analysis)?,
+                }
+                for note in &stanza.notes {
+                    writeln!(f, "      Note: {}", note)?;
+                }
+            }
+        }
+
+        if !unreadable.is_empty() {
+            writeln!(f, "NOT MACHINE-READABLE ({}):", unreadable.len())?;
+            for package in unreadable {
+                writeln!(f, "  {}: {}", package.package, package.error.as_deref().unwrap_or_default())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{parser, temp_dir, write_file};
+
+    const COPYRIGHT: &str = "Format: https://www.debian.org/doc/packaging-manuals/copyright-format/1.0/
+Upstream-Name: foo
+
+Files: *
+Copyright: 2020 Jane Doe
+License: GPL-2+
+
+Files: lib/*
+Copyright: 2021 John Roe
+ .
+ 2022 Acme Inc.
+License: Expat or Apache-2.0, and BSD-3-clause
+
+License: GPL-2+
+ This program is free software.
+";
+
+    fn to_spdx(field: &str) -> String {
+        debian_license_to_spdx(&parser(), field).unwrap().0.to_string()
+    }
+
+    #[test]
+    fn parses_files_stanzas() {
+        let copyright = parse_debian_copyright(COPYRIGHT).unwrap();
+        assert_eq!(copyright.upstream_name.as_deref(), Some("foo"));
+        assert_eq!(copyright.stanzas.len(), 2);
+        assert_eq!(copyright.stanzas[1].files, vec!["lib/*"]);
+        assert_eq!(copyright.stanzas[1].copyright, vec!["2021 John Roe", "2022 Acme Inc."]);
+        assert_eq!(copyright.stanzas[1].license, "Expat or Apache-2.0, and BSD-3-clause");
+    }
+
+    #[test]
+    fn free_form_file_is_not_machine_readable() {
+        let free_form = "This package was debianized by Jane Doe.\n\nIt is licensed under the GPL.\n";
+        assert_eq!(parse_debian_copyright(free_form).unwrap_err(), "Not a machine-readable (DEP-5) copyright file");
+        let colon = "Upstream Author: Jane Doe\n";
+        assert_eq!(parse_debian_copyright(colon).unwrap_err(), "Not a machine-readable (DEP-5) copyright file");
+        assert_eq!(parse_debian_copyright(" \n\n").unwrap_err(), "Empty copyright file");
+    }
+
+    #[test]
+    fn translates_debian_license_names() {
+        assert_eq!(to_spdx("GPL-2+"), "GPL-2.0-or-later");
+        assert_eq!(to_spdx("Expat or Apache-2.0, and BSD-3-clause"), "(MIT OR Apache-2.0) AND BSD-3-Clause");
+        assert_eq!(to_spdx("GPL-2 with Classpath exception"), "GPL-2.0-only WITH Classpath-exception-2.0");
+        assert_eq!(to_spdx("Frobnicate Public License"), "LicenseRef-Debian-Frobnicate-Public-License");
+        assert!(debian_license_to_spdx(&parser(), " ").is_err());
+    }
+
+    #[test]
+    fn exceptions_end_at_the_next_operator() {
+        assert_eq!(to_spdx("GPL-3+ with GCC-exception and MIT"), "GPL-3.0-or-later WITH GCC-exception-3.1 AND MIT");
+        let (expr, notes) = debian_license_to_spdx(&parser(), "GPL-2+ with OpenSSL-exception or MIT").unwrap();
+        assert_eq!(expr.to_string(), "GPL-2.0-or-later WITH LicenseRef-Debian-OpenSSL-exception OR MIT");
+        assert_eq!(notes, vec!["Exception 'OpenSSL' has no SPDX id; kept as LicenseRef-Debian-OpenSSL-exception"]);
+        assert_eq!(to_spdx("GPL-2+ with OpenSSL exception"), "GPL-2.0-or-later WITH LicenseRef-Debian-OpenSSL-exception");
+    }
+
+    #[test]
+    fn scans_root_filesystem() {
+        let dir = temp_dir("debian");
+        write_file(&dir, "usr/share/doc/foo/copyright", COPYRIGHT);
+        write_file(&dir, "usr/share/doc/bar/copyright", "Copyright 1999 someone\n");
+        let report = scan_debian_copyright(&parser(), &dir);
+        assert_eq!(report.packages.len(), 2);
+        let bar = &report.packages[0];
+        assert_eq!(bar.package, "bar");
+        assert!(bar.error.is_some());
+        let foo = &report.packages[1];
+        assert_eq!(foo.expression.as_deref(), Some("(MIT OR Apache-2.0) AND BSD-3-Clause AND GPL-2.0-or-later"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,284 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use crate::license_database::LicenseDatabase;
+use crate::license_expression_parser::LicenseExpressionParser;
+
+/// Error type for reading dependency inventories
+#[derive(Debug)]
+pub enum InventoryError {
+    FileReadError(String),
+    ParseError(String),
+}
+
+impl fmt::Display for InventoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InventoryError::FileReadError(msg) => write!(f, "Failed to read inventory: {}", msg),
+            InventoryError::ParseError(msg) => write!(f, "Failed to parse inventory: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for InventoryError {}
+
+/// How a component is pulled into the project
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DependencyKind {
+    /// Part of the project itself, e.g. a workspace member
+    Root,
+    /// Shipped with the project
+    Normal,
+    /// Only needed to build the project
+    Build,
+    /// Only needed to develop or test the project
+    Dev,
+}
+
+impl fmt::Display for DependencyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DependencyKind::Root => write!(f, "root"),
+            DependencyKind::Normal => write!(f, "normal"),
+            DependencyKind::Build => write!(f, "build"),
+            DependencyKind::Dev => write!(f, "dev"),
+        }
+    }
+}
+
+/// A package with the license information its ecosystem declares for it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Component {
+    /// Unique key within the inventory
+    pub id: String,
+    pub name: String,
+    pub version: Option<String>,
+    pub kind: DependencyKind,
+    /// License exactly as declared by the package metadata
+    pub declared_license: Option<String>,
+    /// Declared license normalized to an SPDX expression
+    pub expression: Option<String>,
+    pub license_files: Vec<PathBuf>,
+    /// Directory of the unpacked or installed package, searched for copyright lines and NOTICE files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_dir: Option<PathBuf>,
+    /// Ids of the components this one depends on
+    pub dependencies: Vec<String>,
+    /// Remarks made while normalizing the license
+    pub notes: Vec<String>,
+}
+
+/// The components of a project and the dependency edges between them
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Inventory {
+    /// Where the inventory was read from
+    pub source: String,
+    /// Ids of the components the project consists of
+    pub roots: Vec<String>,
+    pub components: Vec<Component>,
+}
+
+/// Order in which a component is considered shipped: normal before build before dev
+pub(crate) fn kind_rank(kind: DependencyKind) -> u8 {
+    match kind {
+        DependencyKind::Root => 0,
+        DependencyKind::Normal => 1,
+        DependencyKind::Build => 2,
+        DependencyKind::Dev => 3,
+    }
+}
+
+/// Kind of a dependency reached from a component of kind `parent` over an edge of kind `edge`
+fn child_kind(parent: DependencyKind, edge: DependencyKind) -> DependencyKind {
+    match (parent, edge) {
+        (DependencyKind::Dev, _) | (_, DependencyKind::Dev) => DependencyKind::Dev,
+        (DependencyKind::Build, _) | (_, DependencyKind::Build) => DependencyKind::Build,
+        _ => DependencyKind::Normal,
+    }
+}
+
+/// Propagates dependency kinds from the roots, keeping the most shipped kind per component
+pub(crate) fn resolve_kinds(roots: &[String], edges: &HashMap<String, Vec<(String, DependencyKind)>>) -> HashMap<String, DependencyKind> {
+    let mut kinds: HashMap<String, DependencyKind> = roots.iter().map(|id| (id.clone(), DependencyKind::Root)).collect();
+    let mut queue: VecDeque<String> = roots.iter().cloned().collect();
+    while let Some(id) = queue.pop_front() {
+        let parent = kinds[&id];
+        for (dependency, edge) in edges.get(&id).into_iter().flatten() {
+            let kind = child_kind(parent, *edge);
+            let improves = kinds.get(dependency).is_none_or(|current| kind_rank(kind) < kind_rank(*current));
+            if improves {
+                kinds.insert(dependency.clone(), kind);
+                queue.push_back(dependency.clone());
+            }
+        }
+    }
+    kinds
+}
+
+/// Combines the license entries one package declares into a single expression
+///
+/// A package that lists several licenses (npm `licenses`, Maven `<licenses>`, Python classifiers,
+/// CycloneDX `licenses[]`) is offered under any of them, so the entries are joined with OR.
+/// Licenses detected in separate files, as in ScanCode results, apply together and are ANDed instead.
+pub(crate) fn any_of_licenses<S: AsRef<str>>(entries: &[S]) -> Option<String> {
+    let mut distinct: Vec<&str> = Vec::new();
+    for entry in entries.iter().map(|e| e.as_ref().trim()).filter(|e| !e.is_empty()) {
+        if !distinct.contains(&entry) {
+            distinct.push(entry);
+        }
+    }
+    match distinct.len() {
+        0 => None,
+        1 => Some(distinct[0].to_string()),
+        _ => Some(distinct.iter()
+            .map(|e| if e.contains(char::is_whitespace) { format!("({})", e) } else { e.to_string() })
+            .collect::<Vec<_>>()
+            .join(" OR ")),
+    }
+}
+
+/// Checks that a field meant to hold an SPDX expression, such as PEP 639 `License-Expression`, holds one
+/// Only the syntax is checked, which doesn't depend on the license database
+pub(crate) fn check_declared_expression(expression: &str) -> Result<(), String> {
+    LicenseExpressionParser::from_shared(Arc::new(LicenseDatabase::empty()))
+        .parse_strict(expression)
+        .map(|_| ())
+}
+
+impl Component {
+    pub fn new(id: &str, name: &str, version: Option<&str>, kind: DependencyKind) -> Self {
+        Component {
+            id: id.to_string(),
+            name: name.to_string(),
+            version: version.map(String::from),
+            kind,
+            declared_license: None,
+            expression: None,
+            license_files: Vec::new(),
+            source_dir: None,
+            dependencies: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    /// `name@version`, or the name alone for unversioned components
+    pub fn display_name(&self) -> String {
+        match self.version {
+            Some(ref version) => format!("{}@{}", self.name, version),
+            None => self.name.clone(),
+        }
+    }
+}
+
+impl Inventory {
+    pub fn component(&self, id: &str) -> Option<&Component> {
+        self.components.iter().find(|c| c.id == id)
+    }
+
+    /// Shortest chain of component ids from a root to `id`, both included
+    /// Returns None if the component is not reachable from any root
+    pub fn dependency_path(&self, id: &str) -> Option<Vec<String>> {
+        let by_id: HashMap<&str, &Component> = self.components.iter().map(|c| (c.id.as_str(), c)).collect();
+        let mut previous: HashMap<&str, Option<&str>> = HashMap::new();
+        let mut queue = VecDeque::new();
+        for root in &self.roots {
+            previous.insert(root.as_str(), None);
+            queue.push_back(root.as_str());
+        }
+
+        while let Some(current) = queue.pop_front() {
+            if current == id {
+                let mut path = vec![current.to_string()];
+                let mut step = previous[current];
+                while let Some(parent) = step {
+                    path.push(parent.to_string());
+                    step = previous[parent];
+                }
+                path.reverse();
+                return Some(path);
+            }
+            let Some(component) = by_id.get(current) else {
+                continue;
+            };
+            for dependency in &component.dependencies {
+                if !previous.contains_key(dependency.as_str()) {
+                    previous.insert(dependency.as_str(), Some(current));
+                    queue.push_back(dependency.as_str());
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inventory(edges: &[(&str, &[&str])]) -> Inventory {
+        Inventory {
+            source: "test".to_string(),
+            roots: vec!["root".to_string()],
+            components: edges.iter()
+                .map(|(id, dependencies)| {
+                    let mut component = Component::new(id, id, Some("1.0"), DependencyKind::Normal);
+                    component.dependencies = dependencies.iter().map(|d| d.to_string()).collect();
+                    component
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn kinds_keep_the_most_shipped_path() {
+        let edges: HashMap<String, Vec<(String, DependencyKind)>> = [
+            ("root", vec![("test-lib", DependencyKind::Dev), ("codegen", DependencyKind::Build), ("lib", DependencyKind::Normal)]),
+            ("test-lib", vec![("shared", DependencyKind::Normal)]),
+            ("codegen", vec![("shared", DependencyKind::Normal), ("helper", DependencyKind::Normal)]),
+            ("lib", vec![("helper", DependencyKind::Dev)]),
+        ]
+        .into_iter()
+        .map(|(id, deps)| (id.to_string(), deps.into_iter().map(|(d, k)| (d.to_string(), k)).collect()))
+        .collect();
+        let kinds = resolve_kinds(&["root".to_string()], &edges);
+        assert_eq!(kinds["root"], DependencyKind::Root);
+        assert_eq!(kinds["lib"], DependencyKind::Normal);
+        assert_eq!(kinds["test-lib"], DependencyKind::Dev);
+        assert_eq!(kinds["shared"], DependencyKind::Build);
+        assert_eq!(kinds["helper"], DependencyKind::Build);
+    }
+
+    #[test]
+    fn dependency_path_is_shortest_chain_from_a_root() {
+        let inventory = inventory(&[("root", &["a", "b"]), ("a", &["c"]), ("b", &["d"]), ("d", &["c"]), ("orphan", &[])]);
+        assert_eq!(inventory.dependency_path("c"), Some(vec!["root".to_string(), "a".to_string(), "c".to_string()]));
+        assert_eq!(inventory.dependency_path("root"), Some(vec!["root".to_string()]));
+        assert_eq!(inventory.dependency_path("orphan"), None);
+    }
+
+    #[test]
+    fn dependency_path_survives_cycles() {
+        let inventory = inventory(&[("root", &["a"]), ("a", &["b"]), ("b", &["a", "c"])]);
+        assert_eq!(inventory.dependency_path("c").unwrap().len(), 4);
+        assert_eq!(inventory.component("b").unwrap().display_name(), "b@1.0");
+    }
+
+    #[test]
+    fn declared_license_entries_are_alternatives() {
+        assert_eq!(any_of_licenses(&["MIT", " Apache-2.0 ", "MIT"]).as_deref(), Some("MIT OR Apache-2.0"));
+        assert_eq!(any_of_licenses(&["GPL-2.0-only WITH Classpath-exception-2.0", "MIT"]).as_deref(),
+            Some("(GPL-2.0-only WITH Classpath-exception-2.0) OR MIT"));
+        assert_eq!(any_of_licenses(&["BSD-3-Clause"]).as_deref(), Some("BSD-3-Clause"));
+        assert_eq!(any_of_licenses::<&str>(&["", " "]), None);
+    }
+
+    #[test]
+    fn declared_expressions_must_be_valid_spdx() {
+        assert!(check_declared_expression("MIT OR (Apache-2.0 WITH LLVM-exception)").is_ok());
+        assert!(check_declared_expression("MIT Apache-2.0").is_err());
+        assert!(check_declared_expression("GPL-2.0 AND").is_err());
+        assert!(check_declared_expression("").is_err());
+    }
+}
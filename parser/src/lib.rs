@@ -1,10 +1,66 @@
+pub mod audit_diff;
+pub mod cargo_lock;
+pub mod cargo_metadata;
+pub mod compiled_license_database;
+pub mod cyclonedx_bom;
+pub mod debian_copyright;
+pub mod inventory;
 pub mod license;
+pub mod license_audit;
 pub mod license_database;
+pub mod license_database_diff;
+pub mod license_database_watcher;
 pub mod license_expression_parser;
+pub mod license_graph;
+pub mod license_names;
+pub mod license_overlay;
+pub mod license_policy;
+pub mod license_query;
+pub mod license_text_matcher;
+pub mod license_waivers;
+pub mod maven_poms;
 pub mod models;
+pub mod notice_file;
+pub mod npm_packages;
+pub mod outbound_license;
+pub mod python_packages;
+pub mod reuse_lint;
+pub mod scancode_results;
+pub mod source_scanner;
+pub mod spdx_document;
+pub mod spdx_export;
+#[cfg(test)]
+mod test_support;
 
 // Re-export commonly used items
+pub use audit_diff::*;
+pub use cargo_lock::*;
+pub use cargo_metadata::*;
+pub use compiled_license_database::*;
+pub use cyclonedx_bom::*;
+pub use debian_copyright::*;
+pub use inventory::*;
 pub use license::*;
+pub use license_audit::*;
 pub use license_database::*;
+pub use license_database_diff::*;
+pub use license_database_watcher::*;
 pub use license_expression_parser::*;
-pub use models::*;
\ No newline at end of file
+pub use license_graph::*;
+pub use license_names::*;
+pub use license_overlay::*;
+pub use license_policy::*;
+pub use license_query::*;
+pub use license_text_matcher::*;
+pub use license_waivers::*;
+pub use maven_poms::*;
+pub use models::*;
+pub use notice_file::*;
+pub use npm_packages::*;
+pub use outbound_license::*;
+pub use python_packages::*;
+pub use reuse_lint::*;
+pub use scancode_results::*;
+pub use source_scanner::*;
+pub use spdx_document::*;
+pub use spdx_export::*;
\ No newline at end of file
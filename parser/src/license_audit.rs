@@ -0,0 +1,316 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use crate::inventory::{Component, DependencyKind, Inventory};
+use crate::license_expression_parser::LicenseExpressionParser;
+use crate::license_policy::{LicensePolicy, PolicyViolation};
+use crate::license_text_matcher::LicenseTextCorpus;
+use crate::license_waivers::{self, WaivedViolation};
+use crate::models::{risk_level_order, LicenseAnalysis, NewCopyleftStrength, RiskLevel};
+use crate::source_scanner::{self, SkipReason};
+
+/// Analysis and policy result for one component
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComponentAudit {
+    pub component: Component,
+    /// None if the component declares no license
+    pub analysis: Option<LicenseAnalysis>,
+    pub violations: Vec<PolicyViolation>,
+    /// Violations suppressed by an unexpired waiver
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub waived: Vec<WaivedViolation>,
+    /// Violations in `violations` whose waiver has expired
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub expired_waivers: Vec<WaivedViolation>,
+    /// Display names of the components from a root to this one
+    pub dependency_path: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AuditVerdict {
+    Pass,
+    Fail,
+}
+
+impl fmt::Display for AuditVerdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuditVerdict::Pass => write!(f, "PASS"),
+            AuditVerdict::Fail => write!(f, "FAIL"),
+        }
+    }
+}
+
+/// Result of auditing every component of an inventory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditReport {
+    pub source: String,
+    pub database_version: Option<String>,
+    pub components: Vec<ComponentAudit>,
+    /// Highest risk among the dependencies that are shipped with the project
+    pub overall_risk: RiskLevel,
+    pub verdict: AuditVerdict,
+}
+
+impl ComponentAudit {
+    pub fn risk_level(&self) -> RiskLevel {
+        self.analysis.as_ref().map(|a| a.risk_level.clone()).unwrap_or(RiskLevel::Unknown)
+    }
+
+    /// Category of the license that applies: the recommended choice, or the strongest for conflicts
+    pub fn effective_copyleft(&self) -> Option<NewCopyleftStrength> {
+        let analysis = self.analysis.as_ref()?;
+        Some(analysis.recommended_choice.as_ref()
+            .map(|license| license.copyleft_strength.clone())
+            .unwrap_or_else(|| analysis.strongest_copyleft.clone()))
+    }
+
+    pub fn is_copyleft(&self) -> bool {
+        matches!(self.effective_copyleft(), Some(NewCopyleftStrength::Copyleft | NewCopyleftStrength::CopyleftLimited))
+    }
+}
+
+/// Fills in the expression of components that only ship a license file, by identifying the file's text
+pub fn identify_license_files(inventory: &mut Inventory, corpus: &LicenseTextCorpus) {
+    for component in inventory.components.iter_mut().filter(|c| c.expression.is_none()) {
+        for path in component.license_files.clone() {
+            let text = match source_scanner::read_source_file(&path) {
+                Ok(text) => text,
+                Err(reason @ SkipReason::Unreadable(_)) => {
+                    component.notes.push(format!("Cannot read {}: {}", path.display(), reason));
+                    continue;
+                }
+                Err(_) => continue,
+            };
+            if let Some(expression) = corpus.identify(&text, 1).expression() {
+                component.notes.push(format!("License identified from {}", path.display()));
+                component.expression = Some(expression);
+                break;
+            }
+        }
+    }
+}
+
+/// Analyzes every component of an inventory and checks its dependencies against a policy
+/// Root components are the project itself; they are reported but not gated
+/// Violations matched by one of the policy's waivers are reported as waived instead, unless it expired
+pub fn audit_inventory(parser: &LicenseExpressionParser, inventory: &Inventory, policy: &LicensePolicy) -> AuditReport {
+    let today = license_waivers::today();
+    let components: Vec<ComponentAudit> = inventory.components.iter()
+        .map(|component| {
+            let analysis = component.expression.as_ref().map(|expression| parser.analyze(expression));
+            let gated = match component.kind {
+                DependencyKind::Root => false,
+                DependencyKind::Dev => !policy.ignore_dev_dependencies,
+                DependencyKind::Normal | DependencyKind::Build => true,
+            };
+            let violations = if gated {
+                policy.evaluate(analysis.as_ref())
+            } else {
+                Vec::new()
+            };
+            let (violations, waived, expired_waivers) = license_waivers::apply_waivers(&policy.waivers, component, violations, &today);
+            let dependency_path = inventory.dependency_path(&component.id)
+                .unwrap_or_default()
+                .iter()
+                .map(|id| inventory.component(id).map(Component::display_name).unwrap_or_else(|| id.clone()))
+                .collect();
+            ComponentAudit {
+                component: component.clone(),
+                analysis,
+                violations,
+                waived,
+                expired_waivers,
+                dependency_path,
+            }
+        })
+        .collect();
+
+    let overall_risk = components.iter()
+        .filter(|c| c.component.kind == DependencyKind::Normal)
+        .map(ComponentAudit::risk_level)
+        .max_by_key(risk_level_order)
+        .unwrap_or(RiskLevel::Low);
+    let verdict = if components.iter().any(|c| !c.violations.is_empty()) {
+        AuditVerdict::Fail
+    } else {
+        AuditVerdict::Pass
+    };
+
+    AuditReport {
+        source: inventory.source.clone(),
+        database_version: parser.database_version().map(|v| v.label()),
+        components,
+        overall_risk,
+        verdict,
+    }
+}
+
+fn write_component(f: &mut fmt::Formatter<'_>, audit: &ComponentAudit) -> fmt::Result {
+    let name = audit.component.display_name();
+    match (&audit.component.expression, &audit.analysis) {
+        (Some(expression), Some(analysis)) => {
+            writeln!(f, "  {}: {} [{}, {}]", name, expression, analysis.risk_level, analysis.strongest_copyleft)?
+        }
+        _ => writeln!(f, "  {}: NO LICENSE", name)?,
+    }
+    if let Some(ref declared) = audit.component.declared_license {
+        if audit.component.expression.as_ref() != Some(declared) {
+            writeln!(f, "    Declared: {}", declared)?;
+        }
+    }
+    for note in &audit.component.notes {
+        writeln!(f, "    Note: {}", note)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for AuditReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "License Audit for: {}", self.source)?;
+        if let Some(ref version) = self.database_version {
+            writeln!(f, "Database Version: {}", version)?;
+        }
+
+        let groups = [
+            (DependencyKind::Root, "Project"),
+            (DependencyKind::Normal, "Dependencies"),
+            (DependencyKind::Build, "Build Dependencies"),
+            (DependencyKind::Dev, "Dev Dependencies"),
+        ];
+        for (kind, title) in groups {
+            let members: Vec<&ComponentAudit> = self.components.iter().filter(|c| c.component.kind == kind).collect();
+            if members.is_empty() {
+                continue;
+            }
+            writeln!(f, "{} ({}):", title, members.len())?;
+            for audit in members {
+                write_component(f, audit)?;
+            }
+        }
+
+        let copyleft: Vec<&ComponentAudit> = self.components.iter()
+            .filter(|c| c.component.kind != DependencyKind::Root && c.is_copyleft())
+            .collect();
+        if !copyleft.is_empty() {
+            writeln!(f, "COPYLEFT DEPENDENCIES ({}):", copyleft.len())?;
+            for audit in copyleft {
+                let strength = audit.effective_copyleft().map(|s| s.to_string()).unwrap_or_default();
+                writeln!(f, "  {} ({}) [{}]", audit.component.display_name(), strength, audit.component.kind)?;
+                if audit.dependency_path.len() > 1 {
+                    writeln!(f, "    via: {}", audit.dependency_path.join(" -> "))?;
+                }
+            }
+        }
+
+        let violating: Vec<&ComponentAudit> = self.components.iter().filter(|c| !c.violations.is_empty()).collect();
+        if !violating.is_empty() {
+            writeln!(f, "POLICY VIOLATIONS ({}):", violating.len())?;
+            for audit in violating {
+                writeln!(f, "  {}:", audit.component.display_name())?;
+                for violation in &audit.violations {
+                    writeln!(f, "    [{}] {}", violation.rule, violation.message)?;
+                    if let Some(expired) = audit.expired_waivers.iter().find(|w| &w.violation == violation) {
+                        writeln!(f, "      waiver by {} expired on {}", expired.waiver.approver, expired.waiver.expires)?;
+                    }
+                }
+                if audit.dependency_path.len() > 1 {
+                    writeln!(f, "    via: {}", audit.dependency_path.join(" -> "))?;
+                }
+            }
+        }
+
+        let waived: Vec<&ComponentAudit> = self.components.iter().filter(|c| !c.waived.is_empty()).collect();
+        if !waived.is_empty() {
+            writeln!(f, "WAIVED ({}):", waived.len())?;
+            for audit in waived {
+                writeln!(f, "  {}:", audit.component.display_name())?;
+                for waived in &audit.waived {
+                    writeln!(f, "    [{}] {} (waived)", waived.violation.rule, waived.violation.message)?;
+                    writeln!(f, "      approved by {} until {}: {}", waived.waiver.approver, waived.waiver.expires, waived.waiver.reason)?;
+                }
+            }
+        }
+
+        writeln!(f, "Overall Risk: {}", self.overall_risk)?;
+        writeln!(f, "Verdict: {}", self.verdict)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::license_policy::PolicyRule;
+    use crate::test_support::{parser, temp_dir, write_file};
+
+    fn component(id: &str, kind: DependencyKind, expression: Option<&str>) -> Component {
+        let mut component = Component::new(id, id, Some("1.0"), kind);
+        component.expression = expression.map(String::from);
+        component
+    }
+
+    fn inventory() -> Inventory {
+        let mut root = component("app", DependencyKind::Root, Some("AGPL-3.0-only"));
+        root.dependencies = vec!["lib".to_string(), "test-tool".to_string(), "unlicensed".to_string()];
+        let mut lib = component("lib", DependencyKind::Normal, Some("MIT"));
+        lib.dependencies = vec!["gpl".to_string()];
+        Inventory {
+            source: "test".to_string(),
+            roots: vec!["app".to_string()],
+            components: vec![
+                root,
+                lib,
+                component("gpl", DependencyKind::Normal, Some("GPL-3.0-only")),
+                component("test-tool", DependencyKind::Dev, Some("AGPL-3.0-only")),
+                component("unlicensed", DependencyKind::Build, None),
+            ],
+        }
+    }
+
+    fn audit<'a>(report: &'a AuditReport, id: &str) -> &'a ComponentAudit {
+        report.components.iter().find(|a| a.component.id == id).unwrap()
+    }
+
+    #[test]
+    fn gates_dependencies_but_not_the_project() {
+        let policy = LicensePolicy { fail_on: Some(RiskLevel::High), ..LicensePolicy::default() };
+        let report = audit_inventory(&parser(), &inventory(), &policy);
+        assert_eq!(report.verdict, AuditVerdict::Fail);
+        assert!(audit(&report, "app").violations.is_empty());
+        assert!(audit(&report, "lib").violations.is_empty());
+        assert_eq!(audit(&report, "gpl").violations[0].rule, PolicyRule::FailOn);
+        assert_eq!(audit(&report, "gpl").dependency_path, vec!["app@1.0", "lib@1.0", "gpl@1.0"]);
+        assert_eq!(audit(&report, "unlicensed").violations[0].rule, PolicyRule::MissingLicense);
+        assert_eq!(audit(&report, "test-tool").violations.len(), 1);
+        assert!(report.to_string().contains("    via: app@1.0 -> lib@1.0 -> gpl@1.0\n"));
+    }
+
+    #[test]
+    fn dev_dependencies_can_be_ignored_and_do_not_raise_overall_risk() {
+        let policy = LicensePolicy { fail_on: Some(RiskLevel::High), ignore_dev_dependencies: true, ..LicensePolicy::default() };
+        let mut inventory = inventory();
+        inventory.components.retain(|c| c.id != "gpl" && c.id != "unlicensed");
+        let report = audit_inventory(&parser(), &inventory, &policy);
+        assert!(audit(&report, "test-tool").violations.is_empty());
+        assert_eq!(report.verdict, AuditVerdict::Pass);
+        assert_eq!(report.overall_risk, RiskLevel::Low);
+        assert!(audit(&report, "test-tool").is_copyleft());
+    }
+
+    #[test]
+    fn identifies_license_files_of_unlabeled_components() {
+        let dir = temp_dir("audit-files");
+        let text = "Permission to use, copy, modify, and/or distribute this software for any purpose with or without fee is hereby granted.";
+        let mut corpus = LicenseTextCorpus::default();
+        corpus.add_text("isc", "ISC", text);
+        let mut inventory = inventory();
+        let unlicensed = inventory.components.iter_mut().find(|c| c.id == "unlicensed").unwrap();
+        unlicensed.license_files = vec![dir.join("missing.txt"), write_file(&dir, "LICENSE", text)];
+
+        identify_license_files(&mut inventory, &corpus);
+        let unlicensed = inventory.component("unlicensed").unwrap();
+        assert_eq!(unlicensed.expression.as_deref(), Some("ISC"));
+        assert!(unlicensed.notes.iter().any(|n| n.starts_with("License identified from")));
+        assert_eq!(inventory.component("lib").unwrap().expression.as_deref(), Some("MIT"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
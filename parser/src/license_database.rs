@@ -1,14 +1,26 @@
 use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
 use crate::models::NewCopyleftStrength;
 use crate::license::License;
+use crate::compiled_license_database::{self, CompiledLicenseDatabase};
+use crate::license_overlay::{LicenseOverlay, OverlayFile};
+use serde::{Deserialize, Serialize};
 use serde_json;
 use std::fmt;
 
+/// Default location of the bundled license index, relative to the working directory
+pub const DEFAULT_INDEX_PATH: &str = "index.json";
+
 /// Custom error type for license database operations
 #[derive(Debug)]
 pub enum LicenseDatabaseError {
     FileReadError(String),
     JsonParseError(String),
+    InvalidOverlay(String),
+    InvalidDatabase(String),
+    InvalidPolicy(String),
 }
 
 impl fmt::Display for LicenseDatabaseError {
@@ -16,80 +28,486 @@ impl fmt::Display for LicenseDatabaseError {
         match self {
             LicenseDatabaseError::FileReadError(msg) => write!(f, "Failed to read license file: {}", msg),
             LicenseDatabaseError::JsonParseError(msg) => write!(f, "Failed to parse JSON: {}", msg),
+            LicenseDatabaseError::InvalidOverlay(msg) => write!(f, "Invalid license overlay: {}", msg),
+            LicenseDatabaseError::InvalidDatabase(msg) => write!(f, "Invalid compiled license database: {}", msg),
+            LicenseDatabaseError::InvalidPolicy(msg) => write!(f, "Invalid license policy: {}", msg),
         }
     }
 }
 
 impl std::error::Error for LicenseDatabaseError {}
 
+/// The layer a license definition was resolved from
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LicenseSource {
+    /// Bundled ScanCode index (index.json)
+    ScanCode,
+    /// User-defined overlay file, identified by its path
+    Overlay(String),
+    /// Not defined in any layer
+    Unknown,
+}
+
+impl fmt::Display for LicenseSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LicenseSource::ScanCode => write!(f, "scancode"),
+            LicenseSource::Overlay(path) => write!(f, "overlay: {}", path),
+            LicenseSource::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
 /// New license structure using NewCopyleftStrength
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct NewLicense {
     pub id: String,
     pub name: String,
     pub copyleft_strength: NewCopyleftStrength,
+    pub source: LicenseSource,
+    pub obligations: Vec<String>,
+    pub is_exception: bool,
+    pub is_deprecated: bool,
+    /// Id to write to SPDX documents: an SPDX License List id or a ScanCode LicenseRef, None when ScanCode has neither
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spdx_id: Option<String>,
+}
+
+/// Maps a ScanCode category name to NewCopyleftStrength
+/// Returns None for categories that are not part of the ScanCode classification
+pub fn category_to_copyleft_strength(category: &str) -> Option<NewCopyleftStrength> {
+    match category {
+        "Copyleft" => Some(NewCopyleftStrength::Copyleft),
+        "Copyleft Limited" => Some(NewCopyleftStrength::CopyleftLimited),
+        "Permissive" => Some(NewCopyleftStrength::Permissive),
+        "Commercial" => Some(NewCopyleftStrength::Commercial),
+        "Proprietary Free" => Some(NewCopyleftStrength::ProprietaryFree),
+        "Public Domain" => Some(NewCopyleftStrength::PublicDomain),
+        "Free Restricted" => Some(NewCopyleftStrength::FreeRestricted),
+        "Source-available" => Some(NewCopyleftStrength::SourceAvailable),
+        "Unstated License" => Some(NewCopyleftStrength::UnstatedLicense),
+        "Patent License" => Some(NewCopyleftStrength::PatentLicense),
+        "CLA" => Some(NewCopyleftStrength::ProprietaryFree),
+        _ => None,
+    }
+}
+
+/// Version metadata of a license index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatabaseVersion {
+    /// Explicit version from the index, if the index declares one
+    pub version: Option<String>,
+    /// Release date from the index, if the index declares one
+    pub released: Option<String>,
+    /// FNV-1a checksum of the raw index content, as hex
+    pub checksum: String,
+    pub license_count: usize,
+}
+
+impl DatabaseVersion {
+    /// Human-readable identifier: the declared version, or the checksum for unversioned indexes
+    pub fn label(&self) -> String {
+        match self.version {
+            Some(ref version) => version.clone(),
+            None => format!("sha-{}", self.checksum.get(..12).unwrap_or(&self.checksum)),
+        }
+    }
+}
+
+impl fmt::Display for DatabaseVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label())?;
+        if let Some(ref released) = self.released {
+            write!(f, " (released {})", released)?;
+        }
+        write!(f, ", {} licenses, checksum {}", self.license_count, self.checksum)
+    }
+}
+
+/// A license index together with its version metadata
+#[derive(Debug, Clone)]
+pub struct LicenseIndex {
+    pub version: DatabaseVersion,
+    pub licenses: Vec<License>,
+}
+
+/// Accepted layouts of index.json: the plain ScanCode array, or an object carrying version metadata
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum IndexFile {
+    Versioned {
+        version: Option<String>,
+        released: Option<String>,
+        licenses: Vec<License>,
+    },
+    Plain(Vec<License>),
+}
+
+/// Computes the 64-bit FNV-1a hash of the given bytes as a hex string
+fn fnv1a_hex(bytes: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+/// Reads a license index file together with its version metadata
+pub fn load_license_index(path: &Path) -> Result<LicenseIndex, LicenseDatabaseError> {
+    let json_content = fs::read_to_string(path)
+        .map_err(|e| LicenseDatabaseError::FileReadError(format!("{}: {}", path.display(), e)))?;
+
+    let index: IndexFile = serde_json::from_str(&json_content)
+        .map_err(|e| LicenseDatabaseError::JsonParseError(format!("{}: {}", path.display(), e)))?;
+
+    let (version, released, licenses) = match index {
+        IndexFile::Versioned { version, released, licenses } => (version, released, licenses),
+        IndexFile::Plain(licenses) => (None, None, licenses),
+    };
+
+    Ok(LicenseIndex {
+        version: DatabaseVersion {
+            version,
+            released,
+            checksum: fnv1a_hex(json_content.as_bytes()),
+            license_count: licenses.len(),
+        },
+        licenses,
+    })
 }
 
 /// Loads all licenses from index.json file and returns them as a HashMap
 /// This function reads the JSON file and maps the data to NewLicense format using NewCopyleftStrength
 pub fn load_licenses_from_json() -> Result<HashMap<String, NewLicense>, LicenseDatabaseError> {
-    use std::fs;
-    
     // Read the JSON file from project root directory
-    let json_content = fs::read_to_string("index.json")
-        .map_err(|e| LicenseDatabaseError::FileReadError(e.to_string()))?;
-    
-    // Parse the JSON into a vector of License objects
-    let licenses: Vec<License> = serde_json::from_str(&json_content)
-        .map_err(|e| LicenseDatabaseError::JsonParseError(e.to_string()))?;
+    let index = load_license_index(Path::new(DEFAULT_INDEX_PATH))?;
+    Ok(build_license_db(&index.licenses))
+}
+
+/// Returns the (lowercase key, SPDX name) pairs a ScanCode entry is reachable by, in insertion order
+pub fn license_db_keys(license: &License) -> Vec<(String, String)> {
+    let name = license.spdx_license_key
+        .as_ref()
+        .unwrap_or(&license.license_key)
+        .to_string();
+
+    // Also include other SPDX license keys for better matching
+    let mut keys = vec![
+        (license.license_key.to_lowercase(), name.clone()),
+        (name.to_lowercase(), name),
+    ];
+
+    // Also add keys from other_spdx_license_keys if available
+    for other_key in &license.other_spdx_license_keys {
+        keys.push((other_key.to_lowercase(), other_key.to_string()));
+    }
+
+    keys
+}
+
+/// Maps ScanCode license entries to NewLicense, keyed by every lowercase id that refers to them
+pub fn build_license_db(licenses: &[License]) -> HashMap<String, NewLicense> {
     let mut license_db = HashMap::new();
     
     for license in licenses {
         // Map category to NewCopyleftStrength using exact category mapping
-        let copyleft_strength: NewCopyleftStrength = match license.category.as_str() {
-            "Copyleft" => NewCopyleftStrength::Copyleft,
-            "Copyleft Limited" => NewCopyleftStrength::CopyleftLimited,
-            "Permissive" => NewCopyleftStrength::Permissive,
-            "Commercial" => NewCopyleftStrength::Commercial,
-            "Proprietary Free" => NewCopyleftStrength::ProprietaryFree,
-            "Public Domain" => NewCopyleftStrength::PublicDomain,
-            "Free Restricted" => NewCopyleftStrength::FreeRestricted,
-            "Source-available" => NewCopyleftStrength::SourceAvailable,
-            "Unstated License" => NewCopyleftStrength::UnstatedLicense,
-            "Patent License" => NewCopyleftStrength::PatentLicense,
-            "CLA" => NewCopyleftStrength::ProprietaryFree,
-            _ => NewCopyleftStrength::UnstatedLicense,
-        };
+        let copyleft_strength = category_to_copyleft_strength(&license.category)
+            .unwrap_or(NewCopyleftStrength::UnstatedLicense);
         
-        // Also include other SPDX license keys for better matching
-        let keys_to_insert = vec![
-            license.license_key.to_lowercase(),
-            license.spdx_license_key.as_ref().unwrap_or(&license.license_key).to_lowercase(),
-        ];
-        
-        // Add any additional keys from other_spdx_license_keys
-        for key in keys_to_insert {
+        for (key, name) in license_db_keys(license) {
             let new_license = NewLicense {
                 id: key.clone(),
-                name: license.spdx_license_key
-                    .as_ref()
-                    .unwrap_or(&license.license_key)
-                    .to_string(),
+                name,
                 copyleft_strength: copyleft_strength.clone(),
+                source: LicenseSource::ScanCode,
+                obligations: Vec::new(),
+                is_exception: license.is_exception,
+                is_deprecated: license.is_deprecated,
+                // Aliases share the SPDX id of the entry they belong to
+                spdx_id: license.spdx_license_key.clone(),
             };
             license_db.insert(key, new_license);
         }
-        
-        // Also add keys from other_spdx_license_keys if available
-        for other_key in &license.other_spdx_license_keys {
-            let new_license = NewLicense {
-                id: other_key.to_lowercase(),
-                name: other_key.to_string(),
-                copyleft_strength: copyleft_strength.clone(),
-            };
-            license_db.insert(other_key.to_lowercase(), new_license);
-        }
     }
     
-    Ok(license_db)
-}
\ No newline at end of file
+    license_db
+}
+
+/// Backing storage of the bundled license database
+#[derive(Debug)]
+pub enum LicenseStore {
+    /// JSON index parsed into memory
+    Index(HashMap<String, NewLicense>),
+    /// Precompiled database, memory-mapped from disk
+    Compiled(CompiledLicenseDatabase),
+}
+
+impl LicenseStore {
+    /// Opens a license database file, detecting the compiled format by its magic bytes
+    pub fn open(path: &Path) -> Result<(LicenseStore, DatabaseVersion), LicenseDatabaseError> {
+        if compiled_license_database::is_compiled_database(path) {
+            let database = CompiledLicenseDatabase::open(path)?;
+            let version = database.version();
+            Ok((LicenseStore::Compiled(database), version))
+        } else {
+            let index = load_license_index(path)?;
+            Ok((LicenseStore::Index(build_license_db(&index.licenses)), index.version))
+        }
+    }
+
+    /// Looks up a license by id (case-insensitive)
+    pub fn get(&self, id: &str) -> Option<NewLicense> {
+        match self {
+            LicenseStore::Index(license_db) => license_db.get(&id.to_lowercase()).cloned(),
+            LicenseStore::Compiled(database) => database.get(id).map(|entry| entry.to_new_license()),
+        }
+    }
+
+    /// Returns every lookup key together with the license it resolves to
+    pub fn entries(&self) -> Vec<(String, NewLicense)> {
+        match self {
+            LicenseStore::Index(license_db) => license_db.iter()
+                .map(|(key, license)| (key.clone(), license.clone()))
+                .collect(),
+            LicenseStore::Compiled(database) => database.entries()
+                .map(|entry| (entry.key.to_string(), entry.to_new_license()))
+                .collect(),
+        }
+    }
+
+    /// Groups every lookup key by the SPDX name of the license it resolves to
+    pub fn keys_by_name(&self) -> HashMap<String, Vec<String>> {
+        let mut keys: HashMap<String, Vec<String>> = HashMap::new();
+        match self {
+            LicenseStore::Index(license_db) => {
+                for (key, license) in license_db {
+                    keys.entry(license.name.clone()).or_default().push(key.clone());
+                }
+            }
+            LicenseStore::Compiled(database) => {
+                for entry in database.entries() {
+                    keys.entry(entry.name.to_string()).or_default().push(entry.key.to_string());
+                }
+            }
+        }
+        keys
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            LicenseStore::Index(license_db) => license_db.len(),
+            LicenseStore::Compiled(database) => database.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Shared, immutable handle to a license database that can be cloned cheaply across threads
+pub type SharedLicenseDatabase = Arc<LicenseDatabase>;
+
+/// Files a license database was opened from, used to reload it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatabaseSource {
+    pub database_path: PathBuf,
+    pub overlay_paths: Vec<PathBuf>,
+}
+
+impl DatabaseSource {
+    /// Every file the database depends on, database first
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        std::iter::once(self.database_path.as_path())
+            .chain(self.overlay_paths.iter().map(PathBuf::as_path))
+    }
+}
+
+/// A fully layered license database: the bundled store plus any overlays, with its version
+#[derive(Debug)]
+pub struct LicenseDatabase {
+    /// Shared so that derived databases with extra overlay entries don't copy the bundled licenses
+    store: Arc<LicenseStore>,
+    overlay: LicenseOverlay,
+    version: Option<DatabaseVersion>,
+    source: Option<DatabaseSource>,
+}
+
+static DEFAULT_DATABASE: OnceLock<SharedLicenseDatabase> = OnceLock::new();
+
+impl LicenseDatabase {
+    /// Opens a database file (JSON index or compiled) and applies the overlay files in order
+    pub fn open<P: AsRef<Path>>(database_path: &Path, overlay_paths: &[P]) -> Result<Self, LicenseDatabaseError> {
+        let (store, version) = LicenseStore::open(database_path)?;
+        let mut overlay = LicenseOverlay::new();
+        for path in overlay_paths {
+            overlay.apply_path(path.as_ref(), &store)?;
+        }
+
+        Ok(LicenseDatabase {
+            store: Arc::new(store),
+            overlay,
+            version: Some(version),
+            source: Some(DatabaseSource {
+                database_path: database_path.to_path_buf(),
+                overlay_paths: overlay_paths.iter().map(|p| p.as_ref().to_path_buf()).collect(),
+            }),
+        })
+    }
+
+    /// Creates a database from an already loaded license index
+    pub fn from_index(index: &LicenseIndex) -> Self {
+        LicenseDatabase {
+            store: Arc::new(LicenseStore::Index(build_license_db(&index.licenses))),
+            overlay: LicenseOverlay::new(),
+            version: Some(index.version.clone()),
+            source: None,
+        }
+    }
+
+    /// Creates a database from an already opened compiled database
+    pub fn from_compiled(database: CompiledLicenseDatabase) -> Self {
+        let version = database.version();
+        LicenseDatabase {
+            store: Arc::new(LicenseStore::Compiled(database)),
+            overlay: LicenseOverlay::new(),
+            version: Some(version),
+            source: None,
+        }
+    }
+
+    /// A database without any licenses; every id resolves as unknown
+    pub fn empty() -> Self {
+        LicenseDatabase {
+            store: Arc::new(LicenseStore::Index(HashMap::new())),
+            overlay: LicenseOverlay::new(),
+            version: None,
+            source: None,
+        }
+    }
+
+    /// Process-wide database loaded lazily from index.json on first use
+    /// Falls back to an empty database if the index cannot be loaded
+    pub fn global() -> SharedLicenseDatabase {
+        DEFAULT_DATABASE
+            .get_or_init(|| {
+                let database = LicenseDatabase::open::<&Path>(Path::new(DEFAULT_INDEX_PATH), &[])
+                    .unwrap_or_else(|e| {
+                        log::error!("Failed to load default license database: {}", e);
+                        LicenseDatabase::empty()
+                    });
+                Arc::new(database)
+            })
+            .clone()
+    }
+
+    /// A copy of this database with one more overlay layered on top, such as licenses a document defines
+    /// The bundled store is shared with this database rather than reloaded
+    pub fn with_overlay(&self, file: &OverlayFile, origin: &str) -> Result<Self, LicenseDatabaseError> {
+        let mut overlay = self.overlay.clone();
+        overlay.apply(file, origin, &self.store)?;
+        Ok(LicenseDatabase {
+            store: Arc::clone(&self.store),
+            overlay,
+            version: self.version.clone(),
+            source: None,
+        })
+    }
+
+    /// Resolves a license id through the overlay first, then the bundled store
+    pub fn get(&self, id: &str) -> Option<NewLicense> {
+        self.overlay.get(id)
+            .cloned()
+            .or_else(|| self.store.get(id))
+    }
+
+    /// Returns an explicit compatibility decision from the overlay, if one is defined
+    pub fn compatibility(&self, a: &NewLicense, b: &NewLicense) -> Option<bool> {
+        self.overlay.compatibility(a, b)
+    }
+
+    pub fn store(&self) -> &LicenseStore {
+        &self.store
+    }
+
+    pub fn overlay(&self) -> &LicenseOverlay {
+        &self.overlay
+    }
+
+    /// Version of the underlying index, if one was loaded
+    pub fn version(&self) -> Option<&DatabaseVersion> {
+        self.version.as_ref()
+    }
+
+    /// Files this database was opened from, if it was opened from disk
+    pub fn source(&self) -> Option<&DatabaseSource> {
+        self.source.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{index_path, temp_dir, write_file};
+
+    fn version(version: Option<&str>, checksum: &str) -> DatabaseVersion {
+        DatabaseVersion {
+            version: version.map(String::from),
+            released: None,
+            checksum: checksum.to_string(),
+            license_count: 0,
+        }
+    }
+
+    #[test]
+    fn labels_prefer_the_declared_version() {
+        assert_eq!(version(Some("2024.1"), "0123456789abcdef").label(), "2024.1");
+        assert_eq!(version(None, "0123456789abcdef").label(), "sha-0123456789ab");
+        assert_eq!(version(None, "abc").label(), "sha-abc");
+        assert_eq!(version(None, "").label(), "sha-");
+    }
+
+    #[test]
+    fn displays_release_and_checksum() {
+        let mut released = version(Some("2024.1"), "0123456789abcdef");
+        released.released = Some("2024-03-01".to_string());
+        released.license_count = 2;
+        assert_eq!(released.to_string(), "2024.1 (released 2024-03-01), 2 licenses, checksum 0123456789abcdef");
+    }
+
+    #[test]
+    fn reads_version_metadata_from_either_index_layout() {
+        let dir = temp_dir("license_database");
+        let entry = r#"{"license_key": "mit", "category": "Permissive", "spdx_license_key": "MIT",
+            "other_spdx_license_keys": [], "is_exception": false, "is_deprecated": false,
+            "json": "mit.json", "yaml": "mit.yml", "html": "mit.html", "license": "mit.LICENSE"}"#;
+        let plain = write_file(&dir, "plain.json", &format!("[{}]", entry));
+        let versioned = write_file(&dir, "versioned.json",
+            &format!(r#"{{"version": "2024.1", "released": "2024-03-01", "licenses": [{}]}}"#, entry));
+
+        let plain = load_license_index(&plain).unwrap();
+        assert_eq!((plain.version.version.as_deref(), plain.version.license_count), (None, 1));
+        assert_eq!(plain.version.checksum.len(), 16);
+        assert!(plain.version.label().starts_with("sha-"));
+
+        let versioned = load_license_index(&versioned).unwrap();
+        assert_eq!(versioned.version.version.as_deref(), Some("2024.1"));
+        assert_eq!(versioned.version.released.as_deref(), Some("2024-03-01"));
+        assert_ne!(versioned.version.checksum, plain.version.checksum);
+
+        let database = LicenseDatabase::from_index(&versioned);
+        assert_eq!(database.version().map(DatabaseVersion::label).as_deref(), Some("2024.1"));
+        assert_eq!(database.get("mit").unwrap().spdx_id.as_deref(), Some("MIT"));
+        assert!(matches!(load_license_index(&write_file(&dir, "bad.json", "{}")), Err(LicenseDatabaseError::JsonParseError(_))));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn opened_databases_record_their_source_and_version() {
+        let database = LicenseDatabase::open::<&Path>(&index_path(), &[]).unwrap();
+        assert_eq!(database.source().unwrap().paths().collect::<Vec<_>>(), vec![index_path().as_path()]);
+        assert_eq!(database.version().unwrap().license_count, load_license_index(&index_path()).unwrap().licenses.len());
+        assert!(LicenseDatabase::empty().version().is_none());
+    }
+}
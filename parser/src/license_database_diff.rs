@@ -0,0 +1,257 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use crate::license::License;
+use crate::license_database::{DatabaseVersion, LicenseIndex};
+use crate::license_expression_parser::LicenseExpressionParser;
+use crate::models::RiskLevel;
+
+/// A license whose ScanCode category differs between two releases
+#[derive(Debug, Clone)]
+pub struct CategoryChange {
+    pub license_key: String,
+    pub old_category: String,
+    pub new_category: String,
+}
+
+/// A license whose SPDX keys differ between two releases
+#[derive(Debug, Clone)]
+pub struct AliasChange {
+    pub license_key: String,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+/// An expression that is assessed differently under the new release
+#[derive(Debug, Clone)]
+pub struct RiskChange {
+    pub expression: String,
+    pub old_risk: RiskLevel,
+    pub new_risk: RiskLevel,
+}
+
+/// Differences between two releases of the license index
+#[derive(Debug, Clone)]
+pub struct LicenseDatabaseDiff {
+    pub old_version: DatabaseVersion,
+    pub new_version: DatabaseVersion,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub recategorized: Vec<CategoryChange>,
+    pub alias_changes: Vec<AliasChange>,
+    pub risk_changes: Vec<RiskChange>,
+}
+
+/// All SPDX keys a ScanCode entry can be referred to by
+fn spdx_aliases(license: &License) -> BTreeSet<String> {
+    license.spdx_license_key.iter()
+        .chain(license.other_spdx_license_keys.iter())
+        .cloned()
+        .collect()
+}
+
+/// Compares two license index releases entry by entry, keyed by ScanCode license key
+pub fn diff_license_indexes(old: &LicenseIndex, new: &LicenseIndex) -> LicenseDatabaseDiff {
+    let old_by_key: BTreeMap<&str, &License> = old.licenses.iter()
+        .map(|l| (l.license_key.as_str(), l))
+        .collect();
+    let new_by_key: BTreeMap<&str, &License> = new.licenses.iter()
+        .map(|l| (l.license_key.as_str(), l))
+        .collect();
+
+    let mut diff = LicenseDatabaseDiff {
+        old_version: old.version.clone(),
+        new_version: new.version.clone(),
+        added: Vec::new(),
+        removed: Vec::new(),
+        recategorized: Vec::new(),
+        alias_changes: Vec::new(),
+        risk_changes: Vec::new(),
+    };
+
+    for (key, old_license) in &old_by_key {
+        let Some(new_license) = new_by_key.get(key) else {
+            diff.removed.push(key.to_string());
+            continue;
+        };
+
+        if old_license.category != new_license.category {
+            diff.recategorized.push(CategoryChange {
+                license_key: key.to_string(),
+                old_category: old_license.category.clone(),
+                new_category: new_license.category.clone(),
+            });
+        }
+
+        let old_aliases = spdx_aliases(old_license);
+        let new_aliases = spdx_aliases(new_license);
+        if old_aliases != new_aliases {
+            diff.alias_changes.push(AliasChange {
+                license_key: key.to_string(),
+                added: new_aliases.difference(&old_aliases).cloned().collect(),
+                removed: old_aliases.difference(&new_aliases).cloned().collect(),
+            });
+        }
+    }
+
+    diff.added = new_by_key.keys()
+        .filter(|key| !old_by_key.contains_key(*key))
+        .map(|key| key.to_string())
+        .collect();
+
+    diff
+}
+
+impl LicenseDatabaseDiff {
+    /// Re-analyzes previously analyzed expressions against both releases and records
+    /// every expression whose risk level changes
+    pub fn add_expression_impact(&mut self, old_parser: &LicenseExpressionParser, new_parser: &LicenseExpressionParser, expressions: &[String]) {
+        for expression in expressions {
+            let old_risk = old_parser.analyze(expression).risk_level;
+            let new_risk = new_parser.analyze(expression).risk_level;
+            if old_risk != new_risk {
+                self.risk_changes.push(RiskChange {
+                    expression: expression.clone(),
+                    old_risk,
+                    new_risk,
+                });
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.recategorized.is_empty()
+            && self.alias_changes.is_empty()
+            && self.risk_changes.is_empty()
+    }
+}
+
+impl fmt::Display for LicenseDatabaseDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "License Database Diff")?;
+        writeln!(f, "Old: {}", self.old_version)?;
+        writeln!(f, "New: {}", self.new_version)?;
+
+        if self.is_empty() {
+            writeln!(f, "No differences")?;
+            return Ok(());
+        }
+
+        if !self.added.is_empty() {
+            writeln!(f, "Added Licenses ({}):", self.added.len())?;
+            for key in &self.added {
+                writeln!(f, "  + {}", key)?;
+            }
+        }
+
+        if !self.removed.is_empty() {
+            writeln!(f, "Removed Licenses ({}):", self.removed.len())?;
+            for key in &self.removed {
+                writeln!(f, "  - {}", key)?;
+            }
+        }
+
+        if !self.recategorized.is_empty() {
+            writeln!(f, "Re-categorized Licenses ({}):", self.recategorized.len())?;
+            for change in &self.recategorized {
+                writeln!(f, "  {}: {} -> {}", change.license_key, change.old_category, change.new_category)?;
+            }
+        }
+
+        if !self.alias_changes.is_empty() {
+            writeln!(f, "Changed Aliases ({}):", self.alias_changes.len())?;
+            for change in &self.alias_changes {
+                writeln!(f, "  {}: added [{}], removed [{}]", change.license_key, change.added.join(", "), change.removed.join(", "))?;
+            }
+        }
+
+        if !self.risk_changes.is_empty() {
+            writeln!(f, "Changed Risk Levels ({}):", self.risk_changes.len())?;
+            for change in &self.risk_changes {
+                writeln!(f, "  {}: {} -> {}", change.expression, change.old_risk, change.new_risk)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn license(key: &str, category: &str, spdx: &[&str]) -> License {
+        License {
+            license_key: key.to_string(),
+            category: category.to_string(),
+            spdx_license_key: spdx.first().map(|s| s.to_string()),
+            other_spdx_license_keys: spdx.iter().skip(1).map(|s| s.to_string()).collect(),
+            is_exception: false,
+            is_deprecated: false,
+            json: String::new(),
+            yaml: String::new(),
+            html: String::new(),
+            license: String::new(),
+        }
+    }
+
+    fn index(version: &str, licenses: Vec<License>) -> LicenseIndex {
+        LicenseIndex {
+            version: DatabaseVersion {
+                version: Some(version.to_string()),
+                released: None,
+                checksum: String::new(),
+                license_count: licenses.len(),
+            },
+            licenses,
+        }
+    }
+
+    fn old_index() -> LicenseIndex {
+        index("1", vec![
+            license("mit", "Permissive", &["MIT"]),
+            license("acme", "Permissive", &["LicenseRef-Acme"]),
+            license("gone", "Permissive", &["LicenseRef-Gone"]),
+        ])
+    }
+
+    fn new_index() -> LicenseIndex {
+        index("2", vec![
+            license("mit", "Permissive", &["MIT", "Expat"]),
+            license("acme", "Copyleft", &["LicenseRef-Acme"]),
+            license("fresh", "Public Domain", &["LicenseRef-Fresh"]),
+        ])
+    }
+
+    #[test]
+    fn identical_indexes_have_no_differences() {
+        let diff = diff_license_indexes(&old_index(), &old_index());
+        assert!(diff.is_empty());
+        assert!(diff.to_string().contains("No differences"));
+    }
+
+    #[test]
+    fn reports_added_removed_recategorized_and_aliases() {
+        let diff = diff_license_indexes(&old_index(), &new_index());
+        assert_eq!(diff.added, vec!["fresh"]);
+        assert_eq!(diff.removed, vec!["gone"]);
+        assert_eq!(diff.recategorized.len(), 1);
+        assert_eq!(diff.recategorized[0].license_key, "acme");
+        assert_eq!(diff.recategorized[0].new_category, "Copyleft");
+        assert_eq!(diff.alias_changes.len(), 1);
+        assert_eq!(diff.alias_changes[0].added, vec!["Expat"]);
+        assert!(diff.alias_changes[0].removed.is_empty());
+    }
+
+    #[test]
+    fn records_expressions_whose_risk_changes() {
+        let (old, new) = (old_index(), new_index());
+        let mut diff = diff_license_indexes(&old, &new);
+        let expressions = vec!["LicenseRef-Acme".to_string(), "MIT".to_string()];
+        diff.add_expression_impact(&LicenseExpressionParser::from_index(&old), &LicenseExpressionParser::from_index(&new), &expressions);
+        assert_eq!(diff.risk_changes.len(), 1);
+        assert_eq!(diff.risk_changes[0].expression, "LicenseRef-Acme");
+        assert_ne!(diff.risk_changes[0].old_risk, diff.risk_changes[0].new_risk);
+    }
+}
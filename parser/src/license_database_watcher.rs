@@ -0,0 +1,168 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+use crate::license_expression_parser::LicenseExpressionParser;
+
+/// Background thread that reloads a parser's database when its files change on disk
+///
+/// Files are polled for modification time changes; a failed reload is logged and the
+/// previous database stays active. Dropping the watcher stops the thread.
+#[derive(Debug)]
+pub struct DatabaseWatcher {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+/// Modification times of every file the parser's current database was loaded from
+fn modification_times(parser: &LicenseExpressionParser) -> Vec<(PathBuf, Option<SystemTime>)> {
+    let database = parser.database();
+    let Some(source) = database.source() else {
+        return Vec::new();
+    };
+
+    source.paths()
+        .map(|path| {
+            let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+            (path.to_path_buf(), modified)
+        })
+        .collect()
+}
+
+impl DatabaseWatcher {
+    pub(crate) fn spawn(parser: LicenseExpressionParser, poll_interval: Duration) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        // Taken before spawning, so changes made right after `watch` returns are not mistaken for the baseline
+        let mut last_seen = modification_times(&parser);
+
+        let handle = thread::spawn(move || {
+            while !thread_stop.load(Ordering::Relaxed) {
+                thread::park_timeout(poll_interval);
+                if thread_stop.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                let current = modification_times(&parser);
+                if current == last_seen {
+                    continue;
+                }
+
+                match parser.reload() {
+                    Ok(version) => {
+                        log::info!("License database changed on disk, now at {}",
+                            version.map(|v| v.label()).unwrap_or_else(|| "unknown version".to_string()));
+                    }
+                    Err(e) => log::error!("Failed to reload license database, keeping previous version: {}", e),
+                }
+                // Remember the times seen before reloading, so writes landing during the reload trigger another one
+                last_seen = current;
+            }
+        });
+
+        DatabaseWatcher {
+            stop,
+            handle: Some(handle),
+        }
+    }
+
+    /// Stops watching and waits for the background thread to exit
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            handle.thread().unpark();
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for DatabaseWatcher {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::path::Path;
+    use crate::models::NewCopyleftStrength;
+    use crate::test_support::{index_path, temp_dir, write_file};
+
+    const COPYLEFT_MIT: &str = r#"{"licenses": [{"id": "MIT", "category": "Copyleft"}]}"#;
+
+    fn mit_strength(parser: &LicenseExpressionParser) -> NewCopyleftStrength {
+        parser.database().get("MIT").unwrap().copyleft_strength
+    }
+
+    /// Rewrites a file with a modification time clearly different from the previous one
+    fn rewrite(path: &Path, content: &str, age: Duration) {
+        fs::write(path, content).unwrap();
+        File::options().write(true).open(path).unwrap().set_modified(SystemTime::now() - age).unwrap();
+    }
+
+    #[test]
+    fn reload_reads_changed_overlay_and_keeps_database_on_failure() {
+        let dir = temp_dir("reload");
+        let overlay = write_file(&dir, "overlay.json", r#"{"licenses": []}"#);
+        let parser = LicenseExpressionParser::with_database(&index_path(), &[&overlay]).unwrap();
+        assert_eq!(mit_strength(&parser), NewCopyleftStrength::Permissive);
+
+        fs::write(&overlay, COPYLEFT_MIT).unwrap();
+        assert!(parser.reload().is_ok());
+        assert_eq!(mit_strength(&parser), NewCopyleftStrength::Copyleft);
+
+        fs::write(&overlay, "{ not json").unwrap();
+        assert!(parser.reload().is_err());
+        assert_eq!(mit_strength(&parser), NewCopyleftStrength::Copyleft);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn analyses_record_the_database_version_they_used() {
+        let dir = temp_dir("reload-version");
+        let licenses = fs::read_to_string(index_path()).unwrap();
+        let index = write_file(&dir, "index.json", &format!(r#"{{"version": "1", "licenses": {}}}"#, licenses));
+        let parser = LicenseExpressionParser::with_database::<&Path>(&index, &[]).unwrap();
+        let before = parser.analyze("MIT");
+
+        fs::write(&index, format!(r#"{{"version": "2", "licenses": {}}}"#, licenses)).unwrap();
+        assert_eq!(parser.reload().unwrap().and_then(|v| v.version).as_deref(), Some("2"));
+        assert_eq!(before.database_version.as_deref(), Some("1"));
+        assert_eq!(parser.analyze("MIT").database_version.as_deref(), Some("2"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn in_memory_database_cannot_be_reloaded() {
+        let index = crate::license_database::load_license_index(&index_path()).unwrap();
+        assert!(LicenseExpressionParser::from_index(&index).reload().is_err());
+    }
+
+    #[test]
+    fn watcher_reloads_clones_when_files_change() {
+        let dir = temp_dir("watch");
+        let overlay = write_file(&dir, "overlay.json", r#"{"licenses": []}"#);
+        rewrite(&overlay, r#"{"licenses": []}"#, Duration::from_secs(60));
+        let parser = LicenseExpressionParser::with_database(&index_path(), &[&overlay]).unwrap();
+        let clone = parser.clone();
+        let watcher = parser.watch(Duration::from_millis(10));
+
+        rewrite(&overlay, COPYLEFT_MIT, Duration::ZERO);
+        let mut waited = Duration::ZERO;
+        while mit_strength(&clone) != NewCopyleftStrength::Copyleft && waited < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+            waited += Duration::from_millis(10);
+        }
+        watcher.stop();
+        assert_eq!(mit_strength(&clone), NewCopyleftStrength::Copyleft);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,25 +1,138 @@
-use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use log;
 use crate::models::{NewCopyleftStrength, SpdxExpr, RiskLevel, LicenseAnalysis};
-use crate::license_database::{self, NewLicense};
-
-#[derive(Debug)]
+use crate::compiled_license_database::CompiledLicenseDatabase;
+use crate::license_database_watcher::DatabaseWatcher;
+use crate::license_database::{DatabaseVersion, LicenseDatabase, LicenseDatabaseError, LicenseIndex, LicenseSource, NewLicense, SharedLicenseDatabase, DEFAULT_INDEX_PATH};
+
+/// License expression parser backed by a shared license database
+///
+/// Cloning is cheap: all clones share the same underlying database slot, so a
+/// reload through any clone is visible to all of them.
+#[derive(Debug, Clone)]
 pub struct LicenseExpressionParser {
-    license_db: HashMap<String, NewLicense>,
+    database: Arc<RwLock<SharedLicenseDatabase>>,
+}
+
+// Parsers and database snapshots are handed to worker threads, so keep them Clone + Send + Sync
+const _: fn() = || {
+    fn assert_shareable<T: Clone + Send + Sync>() {}
+    assert_shareable::<LicenseExpressionParser>();
+    assert_shareable::<SharedLicenseDatabase>();
+};
+
+impl Default for LicenseExpressionParser {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl LicenseExpressionParser {
     pub fn new() -> Self {
+        let database = LicenseDatabase::open::<&Path>(Path::new(DEFAULT_INDEX_PATH), &[])
+            .unwrap_or_else(|_| LicenseDatabase::empty());
+        Self::from_shared(Arc::new(database))
+    }
+
+    /// Creates a parser on top of an existing shared database without reloading it
+    pub fn from_shared(database: SharedLicenseDatabase) -> Self {
         LicenseExpressionParser {
-            license_db: license_database::load_licenses_from_json().unwrap_or_default(),
+            database: Arc::new(RwLock::new(database)),
         }
     }
 
+    /// Creates a parser sharing the lazily-initialized process-wide default database
+    pub fn global() -> Self {
+        Self::from_shared(LicenseDatabase::global())
+    }
+
+    /// Creates a parser from an already loaded license index
+    pub fn from_index(index: &LicenseIndex) -> Self {
+        Self::from_shared(Arc::new(LicenseDatabase::from_index(index)))
+    }
+
+    /// Creates a parser from an already opened compiled database
+    pub fn from_compiled(database: CompiledLicenseDatabase) -> Self {
+        Self::from_shared(Arc::new(LicenseDatabase::from_compiled(database)))
+    }
+
+    /// Creates a parser with the given overlay files layered on top of the bundled database
+    /// Overlays are applied in order, so later files take precedence over earlier ones
+    pub fn with_overlays<P: AsRef<Path>>(overlay_paths: &[P]) -> Result<Self, LicenseDatabaseError> {
+        Self::with_database(Path::new(DEFAULT_INDEX_PATH), overlay_paths)
+    }
+
+    /// Creates a parser from a specific database file with the given overlay files layered on top
+    /// The database may be a JSON index or a compiled database; the format is detected automatically
+    pub fn with_database<P: AsRef<Path>>(database_path: &Path, overlay_paths: &[P]) -> Result<Self, LicenseDatabaseError> {
+        let database = LicenseDatabase::open(database_path, overlay_paths)?;
+        Ok(Self::from_shared(Arc::new(database)))
+    }
+
+    /// Snapshot of the database currently backing this parser
+    /// The snapshot stays valid and unchanged even if the parser is reloaded afterwards
+    pub fn database(&self) -> SharedLicenseDatabase {
+        self.database
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Version of the license index currently backing this parser, if one was loaded
+    pub fn database_version(&self) -> Option<DatabaseVersion> {
+        self.database().version().cloned()
+    }
+
+    /// Atomically replaces the database behind this parser and all of its clones
+    /// Analyses already in progress finish against the snapshot they started with
+    pub fn swap_database(&self, database: SharedLicenseDatabase) {
+        *self.database.write().unwrap_or_else(|e| e.into_inner()) = database;
+    }
+
+    /// Reloads the database and overlay files the current database was opened from
+    /// On failure the current database stays in place
+    pub fn reload(&self) -> Result<Option<DatabaseVersion>, LicenseDatabaseError> {
+        let source = self.database().source().cloned().ok_or_else(|| {
+            LicenseDatabaseError::FileReadError("database was not loaded from a file and cannot be reloaded".to_string())
+        })?;
+        let database = LicenseDatabase::open(&source.database_path, &source.overlay_paths)?;
+        let version = database.version().cloned();
+        self.swap_database(Arc::new(database));
+        log::info!("Reloaded license database from {}", source.database_path.display());
+        Ok(version)
+    }
+
+    /// Starts a background thread that reloads the database whenever its files change on disk
+    /// Watching stops when the returned watcher is dropped
+    pub fn watch(&self, poll_interval: Duration) -> DatabaseWatcher {
+        DatabaseWatcher::spawn(self.clone(), poll_interval)
+    }
+
     pub fn parse(&self, expression: &str) -> Result<SpdxExpr, String> {
         let tokens = self.tokenize(expression)?;
         self.parse_or_expression(&tokens, &mut 0)
     }
 
+    /// Parses an expression that must be valid SPDX syntax as a whole
+    /// Unlike `parse`, tokens left over after the expression and operators or `)` used as
+    /// license ids are errors, so `MIT GPL-3.0-only` is rejected instead of read as `MIT`
+    pub fn parse_strict(&self, expression: &str) -> Result<SpdxExpr, String> {
+        let tokens = self.tokenize(expression)?;
+        let mut pos = 0;
+        let expr = self.parse_or_expression(&tokens, &mut pos)?;
+        if let Some(token) = tokens.get(pos) {
+            return Err(format!("Unexpected '{}' after expression", token));
+        }
+        let mut ids = Vec::new();
+        collect_license_tokens(&expr, &mut ids);
+        if let Some(token) = ids.into_iter().find(|t| *t == ")" || ["AND", "OR", "WITH"].contains(&t.to_uppercase().as_str())) {
+            return Err(format!("Expected license id, found '{}'", token));
+        }
+        Ok(expr)
+    }
+
     fn tokenize(&self, expression: &str) -> Result<Vec<String>, String> {
         let mut tokens = Vec::new();
         let mut current_token = String::new();
@@ -127,7 +240,9 @@ impl LicenseExpressionParser {
     }
 
     pub fn analyze(&self, expression: &str) -> LicenseAnalysis {
-        
+        // Pin one snapshot so a concurrent reload can't change the database mid-analysis
+        let database = self.database();
+
         let parsed = match self.parse(expression) {
             Ok(expr) => Some(expr),
             Err(er) => {
@@ -138,7 +253,7 @@ impl LicenseExpressionParser {
 
 
         let possible_licenses = if let Some(ref expr) = parsed {
-            self.evaluate_expression(expr)
+            self.evaluate_expression(&database, expr)
         } else {
             Vec::new()
         };
@@ -158,48 +273,54 @@ impl LicenseExpressionParser {
             risk_level,
             compliance_notes,
             conflicts,
+            database_version: database.version().map(|v| v.label()),
         }
     }
 
-    fn evaluate_expression(&self, expr: &SpdxExpr) -> Vec<NewLicense> {
+    fn evaluate_expression(&self, database: &LicenseDatabase, expr: &SpdxExpr) -> Vec<NewLicense> {
         match expr {
             SpdxExpr::License(id) => {
-                let lowercase_id = id.to_lowercase();
-                if let Some(license) = self.license_db.get(&lowercase_id) {
-                    vec![license.clone()]
+                // Resolves through the overlay first, then the bundled database
+                if let Some(license) = database.get(id) {
+                    vec![license]
                 } else {
                     // Handle unknown licenses
                     vec![NewLicense {
                         id: id.clone(),
                         name: format!("Unknown License: {}", id),
                         copyleft_strength: NewCopyleftStrength::UnstatedLicense,
+                        source: LicenseSource::Unknown,
+                        obligations: Vec::new(),
+                        is_exception: false,
+                        is_deprecated: false,
+                        spdx_id: None,
                     }]
                 }
             }
             SpdxExpr::Or(left, right) => {
-                let mut result = self.evaluate_expression(left);
-                result.extend(self.evaluate_expression(right));
+                let mut result = self.evaluate_expression(database, left);
+                result.extend(self.evaluate_expression(database, right));
                 result
             }
             SpdxExpr::And(left, right) => {
-                let left_licenses = self.evaluate_expression(left);
-                let right_licenses = self.evaluate_expression(right);
-                self.find_compatible_licenses(&left_licenses, &right_licenses)
+                let left_licenses = self.evaluate_expression(database, left);
+                let right_licenses = self.evaluate_expression(database, right);
+                self.find_compatible_licenses(database, &left_licenses, &right_licenses)
             }
             SpdxExpr::With(license_expr, _exception) => {
                 // For now, treat WITH expressions as the base license
                 // In a full implementation, you'd handle specific exceptions
-                self.evaluate_expression(license_expr)
+                self.evaluate_expression(database, license_expr)
             }
         }
     }
 
-    fn find_compatible_licenses(&self, left: &[NewLicense], right: &[NewLicense]) -> Vec<NewLicense> {
+    fn find_compatible_licenses(&self, database: &LicenseDatabase, left: &[NewLicense], right: &[NewLicense]) -> Vec<NewLicense> {
         let mut compatible = Vec::new();
 
         for left_lic in left {
             for right_lic in right {
-                if self.are_licenses_compatible(left_lic, right_lic) {
+                if self.are_licenses_compatible(database, left_lic, right_lic) {
                     let stronger = self.choose_stronger_license(left_lic, right_lic);
                     if !compatible.iter().any(|l: &NewLicense| l.id == stronger.id) {
                         compatible.push(stronger);
@@ -223,7 +344,12 @@ impl LicenseExpressionParser {
         compatible
     }
 
-    fn are_licenses_compatible(&self, a: &NewLicense, b: &NewLicense) -> bool {
+    pub(crate) fn are_licenses_compatible(&self, database: &LicenseDatabase, a: &NewLicense, b: &NewLicense) -> bool {
+        // Explicit overlay rules take precedence over the built-in heuristics
+        if let Some(compatible) = database.compatibility(a, b) {
+            return compatible;
+        }
+
         // Basic compatibility rules based on NewCopyleftStrength risk levels
         match (&a.copyleft_strength, &b.copyleft_strength) {
             // Same license is always compatible
@@ -431,6 +557,12 @@ impl LicenseExpressionParser {
             }
         }
 
+        for license in licenses {
+            for obligation in &license.obligations {
+                notes.push(format!("Obligation ({}): {}", license.id, obligation));
+            }
+        }
+
         if licenses.len() > 1 {
             let alternatives: Vec<String> = licenses.iter()
                 .filter(|l| Some(*l) != recommended.as_ref())
@@ -462,4 +594,70 @@ impl LicenseExpressionParser {
 
         conflicts
     }
-}
\ No newline at end of file
+}
+/// License and exception tokens of an expression, in order
+fn collect_license_tokens<'a>(expr: &'a SpdxExpr, tokens: &mut Vec<&'a str>) {
+    match expr {
+        SpdxExpr::License(id) => tokens.push(id),
+        SpdxExpr::And(left, right) | SpdxExpr::Or(left, right) => {
+            collect_license_tokens(left, tokens);
+            collect_license_tokens(right, tokens);
+        }
+        SpdxExpr::With(license, exception) => {
+            collect_license_tokens(license, tokens);
+            tokens.push(exception);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use super::*;
+    use crate::test_support::parser;
+
+    #[test]
+    fn clones_observe_swapped_databases() {
+        let parser = parser();
+        let clone = parser.clone();
+        let snapshot = clone.database();
+        parser.swap_database(Arc::new(LicenseDatabase::empty()));
+
+        assert!(clone.database().get("MIT").is_none());
+        assert!(thread::spawn(move || clone.database().get("MIT").is_none()).join().unwrap());
+        // Snapshots taken before the swap keep the database they were taken from
+        assert!(snapshot.get("MIT").is_some());
+    }
+
+    #[test]
+    fn global_parsers_share_the_database_but_not_the_slot() {
+        assert!(Arc::ptr_eq(&LicenseDatabase::global(), &LicenseDatabase::global()));
+        let (first, second) = (LicenseExpressionParser::global(), LicenseExpressionParser::global());
+        assert!(Arc::ptr_eq(&first.database(), &second.database()));
+
+        first.swap_database(Arc::new(LicenseDatabase::empty()));
+        assert!(!Arc::ptr_eq(&first.database(), &second.database()));
+        assert!(Arc::ptr_eq(&second.database(), &LicenseDatabase::global()));
+    }
+
+    #[test]
+    fn parse_ignores_trailing_tokens() {
+        assert_eq!(parser().parse("MIT GPL-3.0-only").unwrap().to_string(), "MIT");
+    }
+
+    #[test]
+    fn parse_strict_accepts_valid_expressions() {
+        let parser = parser();
+        for expression in ["MIT", "MIT OR Apache-2.0", "(MIT AND BSD-3-Clause) OR GPL-2.0-only WITH Classpath-exception-2.0"] {
+            assert!(parser.parse_strict(expression).is_ok(), "{}", expression);
+        }
+    }
+
+    #[test]
+    fn parse_strict_rejects_leftover_tokens() {
+        let parser = parser();
+        for expression in ["MIT GPL-3.0-only", "MIT OR Apache-2.0 BSD-3-Clause", "(MIT) Apache-2.0", "MIT AND AND", "MIT OR", "MIT WITH", "", "(MIT"] {
+            assert!(parser.parse_strict(expression).is_err(), "{}", expression);
+        }
+    }
+}
@@ -0,0 +1,587 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::inventory::{DependencyKind, Inventory, InventoryError};
+use crate::license_expression_parser::LicenseExpressionParser;
+use crate::models::{risk_level_order, LicenseAnalysis, NewCopyleftStrength, RiskLevel, SpdxExpr};
+
+/// Paths listed per license holder and root; further paths are only flagged
+const MAX_PATHS_PER_HOLDER: usize = 16;
+
+/// How a dependency ends up in (or next to) the artifact that depends on it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Linkage {
+    /// Compiled or bundled into the same artifact
+    Static,
+    /// Shipped alongside and loaded at run time
+    Dynamic,
+    /// Only used while building, e.g. a code generator
+    BuildOnly,
+    /// Only used while developing or testing
+    DevOnly,
+    /// A separate program the artifact runs, not linked
+    Tool,
+}
+
+impl fmt::Display for Linkage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Linkage::Static => write!(f, "static"),
+            Linkage::Dynamic => write!(f, "dynamic"),
+            Linkage::BuildOnly => write!(f, "build-only"),
+            Linkage::DevOnly => write!(f, "dev-only"),
+            Linkage::Tool => write!(f, "tool"),
+        }
+    }
+}
+
+impl Linkage {
+    /// Whether the obligations of a license in `category` reach the depending artifact over this edge
+    ///
+    /// Everything passes a static link. Weak copyleft (e.g. LGPL) stops at a dynamic link, while
+    /// strong copyleft and commercial terms do not. Nothing passes edges that are not shipped.
+    /// Unlicensed dependencies (`None`) are treated like strong copyleft.
+    pub fn propagates(&self, category: Option<&NewCopyleftStrength>) -> bool {
+        match self {
+            Linkage::Static => true,
+            Linkage::Dynamic => category != Some(&NewCopyleftStrength::CopyleftLimited),
+            Linkage::BuildOnly | Linkage::DevOnly | Linkage::Tool => false,
+        }
+    }
+
+    fn is_shipped(&self) -> bool {
+        matches!(self, Linkage::Static | Linkage::Dynamic)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphNode {
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// SPDX expression of the node's license; None if it declares none
+    #[serde(default)]
+    pub expression: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphEdge {
+    pub from: String,
+    pub to: String,
+    pub linkage: Linkage,
+}
+
+/// Dependency graph whose nodes carry licenses and whose edges carry linkage types
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LicenseGraph {
+    /// Ids of the shipped artifacts; nodes nobody depends on when empty
+    #[serde(default)]
+    pub roots: Vec<String>,
+    pub nodes: Vec<GraphNode>,
+    #[serde(default)]
+    pub edges: Vec<GraphEdge>,
+}
+
+impl LicenseGraph {
+    /// Builds a graph from an inventory, linking shipped dependencies with `shipped_linkage`
+    /// Build and dev dependencies get `BuildOnly` and `DevOnly` edges
+    pub fn from_inventory(inventory: &Inventory, shipped_linkage: Linkage) -> Self {
+        let kinds: HashMap<&str, DependencyKind> = inventory.components.iter().map(|c| (c.id.as_str(), c.kind)).collect();
+        let nodes = inventory.components.iter()
+            .map(|c| GraphNode { id: c.id.clone(), name: Some(c.display_name()), expression: c.expression.clone() })
+            .collect();
+        let edges = inventory.components.iter()
+            .flat_map(|c| c.dependencies.iter().map(move |d| (c, d)))
+            .map(|(component, dependency)| GraphEdge {
+                from: component.id.clone(),
+                to: dependency.clone(),
+                linkage: match kinds.get(dependency.as_str()) {
+                    Some(DependencyKind::Dev) => Linkage::DevOnly,
+                    Some(DependencyKind::Build) => Linkage::BuildOnly,
+                    _ => shipped_linkage,
+                },
+            })
+            .collect();
+        LicenseGraph {
+            roots: inventory.roots.clone(),
+            nodes,
+            edges,
+        }
+    }
+
+    /// Reads a graph from a JSON file (see README: Copyleft Propagation)
+    pub fn load_file(path: &Path) -> Result<Self, InventoryError> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| InventoryError::FileReadError(format!("{}: {}", path.display(), e)))?;
+        serde_json::from_str(&content).map_err(|e| InventoryError::ParseError(format!("{}: {}", path.display(), e)))
+    }
+}
+
+/// One way a copyleft or commercial license reaches a root
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PropagationPath {
+    /// Node whose license propagates
+    pub holder: String,
+    pub expression: String,
+    pub category: NewCopyleftStrength,
+    /// Node ids from the root to the holder
+    pub path: Vec<String>,
+    /// Linkage of each edge along the path
+    pub linkages: Vec<Linkage>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Remediation {
+    /// Dropping the dependency and everything only it pulls in
+    Remove,
+    /// The dependency changing to a permissive license
+    Relicense,
+}
+
+impl fmt::Display for Remediation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Remediation::Remove => write!(f, "remove"),
+            Remediation::Relicense => write!(f, "relicense"),
+        }
+    }
+}
+
+/// A change to one dependency that lowers a root's risk level
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskReduction {
+    pub node: String,
+    pub remediation: Remediation,
+    pub risk_level: RiskLevel,
+}
+
+/// Obligations that reach one root through the graph
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RootPropagation {
+    pub root: String,
+    pub own_expression: Option<String>,
+    /// AND of the root's own license and every license that reaches it
+    pub propagated_expression: Option<String>,
+    pub analysis: Option<LicenseAnalysis>,
+    pub risk_level: RiskLevel,
+    /// Nodes without a license whose (unknown) terms reach the root
+    pub unlicensed: Vec<String>,
+    pub paths: Vec<PropagationPath>,
+    /// Whether some holder had more paths than are listed
+    pub paths_truncated: bool,
+    pub reductions: Vec<RiskReduction>,
+}
+
+/// Result of propagating license obligations through a dependency graph
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphPropagationReport {
+    pub database_version: Option<String>,
+    pub roots: Vec<RootPropagation>,
+}
+
+/// Graph with node indexes, parsed expressions and effective categories
+struct PreparedGraph<'a> {
+    graph: &'a LicenseGraph,
+    index: HashMap<&'a str, usize>,
+    adjacency: Vec<Vec<(usize, Linkage)>>,
+    reverse: Vec<Vec<(usize, Linkage)>>,
+    expressions: Vec<Option<SpdxExpr>>,
+    /// Category of the license that applies: the recommended choice, or the strongest for conflicts
+    categories: Vec<Option<NewCopyleftStrength>>,
+}
+
+impl<'a> PreparedGraph<'a> {
+    fn new(parser: &LicenseExpressionParser, graph: &'a LicenseGraph) -> Self {
+        let index: HashMap<&str, usize> = graph.nodes.iter().enumerate().map(|(i, n)| (n.id.as_str(), i)).collect();
+        let mut adjacency = vec![Vec::new(); graph.nodes.len()];
+        let mut reverse = vec![Vec::new(); graph.nodes.len()];
+        for edge in &graph.edges {
+            match (index.get(edge.from.as_str()), index.get(edge.to.as_str())) {
+                (Some(&from), Some(&to)) => {
+                    adjacency[from].push((to, edge.linkage));
+                    reverse[to].push((from, edge.linkage));
+                }
+                _ => log::warn!("Edge {} -> {} refers to an unknown node", edge.from, edge.to),
+            }
+        }
+        let mut expressions = Vec::new();
+        let mut categories = Vec::new();
+        for node in &graph.nodes {
+            let parsed = node.expression.as_deref().and_then(|e| match parser.parse(e) {
+                Ok(expr) => Some(expr),
+                Err(error) => {
+                    log::warn!("Invalid license expression '{}' of {}: {}", e, node.id, error);
+                    None
+                }
+            });
+            let category = parsed.as_ref().map(|expr| {
+                let analysis = parser.analyze(&expr.to_string());
+                analysis.recommended_choice.map(|l| l.copyleft_strength).unwrap_or(analysis.strongest_copyleft)
+            });
+            expressions.push(parsed);
+            categories.push(category);
+        }
+        PreparedGraph { graph, index, adjacency, reverse, expressions, categories }
+    }
+
+    fn roots(&self) -> Vec<usize> {
+        if !self.graph.roots.is_empty() {
+            return self.graph.roots.iter().filter_map(|r| self.index.get(r.as_str()).copied()).collect();
+        }
+        let mut depended_on = vec![false; self.graph.nodes.len()];
+        for targets in &self.adjacency {
+            for (to, _) in targets {
+                depended_on[*to] = true;
+            }
+        }
+        (0..self.graph.nodes.len()).filter(|i| !depended_on[*i]).collect()
+    }
+
+    /// Nodes reachable from `root` over edges accepted by `follow`, never entering `removed`
+    fn reach(&self, root: usize, removed: Option<usize>, follow: impl Fn(Linkage) -> bool) -> Vec<bool> {
+        let mut seen = vec![false; self.graph.nodes.len()];
+        seen[root] = true;
+        let mut queue = VecDeque::from([root]);
+        while let Some(node) = queue.pop_front() {
+            for (to, linkage) in &self.adjacency[node] {
+                if !seen[*to] && Some(*to) != removed && follow(*linkage) {
+                    seen[*to] = true;
+                    queue.push_back(*to);
+                }
+            }
+        }
+        seen
+    }
+
+    /// Nodes other than the root whose license obligations reach it
+    fn reaching(&self, root: usize, removed: Option<usize>) -> Vec<usize> {
+        let statically = self.reach(root, removed, |l| l == Linkage::Static);
+        let shipped = self.reach(root, removed, |l| l.is_shipped());
+        (0..self.graph.nodes.len())
+            .filter(|&node| node != root)
+            .filter(|&node| match self.categories[node] {
+                Some(NewCopyleftStrength::CopyleftLimited) => statically[node],
+                _ => shipped[node],
+            })
+            .collect()
+    }
+
+    /// Combined expression, its analysis and the resulting risk for a root and the nodes reaching it
+    fn combine(&self, parser: &LicenseExpressionParser, root: usize, reaching: &[usize], relicensed: Option<usize>)
+        -> (Option<SpdxExpr>, Option<LicenseAnalysis>, RiskLevel)
+    {
+        let mut distinct: Vec<SpdxExpr> = Vec::new();
+        let mut unlicensed = false;
+        for node in std::iter::once(root).chain(reaching.iter().copied()).filter(|n| Some(*n) != relicensed) {
+            match self.expressions[node] {
+                Some(ref expr) if !distinct.contains(expr) => distinct.push(expr.clone()),
+                Some(_) => {}
+                None => unlicensed |= node != root,
+            }
+        }
+        distinct.sort_by_key(|expr| expr.to_string());
+        let combined = SpdxExpr::and_all(distinct);
+        let analysis = combined.as_ref().map(|expr| parser.analyze(&expr.to_string()));
+        let mut risk = analysis.as_ref().map(|a| a.risk_level.clone()).unwrap_or(RiskLevel::Low);
+        if unlicensed && risk_level_order(&risk) < risk_level_order(&RiskLevel::Unknown) {
+            risk = RiskLevel::Unknown;
+        }
+        (combined, analysis, risk)
+    }
+
+    /// Up to [`MAX_PATHS_PER_HOLDER`] simple paths from `root` to `holder` over edges that pass the
+    /// holder's license, and whether more exist
+    fn paths(&self, root: usize, holder: usize) -> (Vec<Vec<(usize, Linkage)>>, bool) {
+        let category = self.categories[holder].as_ref();
+        // Only nodes that can still reach the holder are worth entering
+        let mut leads_to_holder = vec![false; self.graph.nodes.len()];
+        leads_to_holder[holder] = true;
+        let mut queue = VecDeque::from([holder]);
+        while let Some(node) = queue.pop_front() {
+            for (from, linkage) in &self.reverse[node] {
+                if !leads_to_holder[*from] && linkage.propagates(category) {
+                    leads_to_holder[*from] = true;
+                    queue.push_back(*from);
+                }
+            }
+        }
+
+        let mut found = Vec::new();
+        let mut on_path = vec![false; self.graph.nodes.len()];
+        let mut path: Vec<(usize, Linkage)> = Vec::new();
+        // Iterative DFS: (node, index of the next edge to try)
+        let mut stack = vec![(root, 0)];
+        on_path[root] = true;
+        while let Some((node, next)) = stack.pop() {
+            if node == holder {
+                if found.len() == MAX_PATHS_PER_HOLDER {
+                    return (found, true);
+                }
+                found.push(path.clone());
+                on_path[node] = false;
+                path.pop();
+                continue;
+            }
+            match self.adjacency[node].get(next) {
+                Some(&(to, linkage)) => {
+                    stack.push((node, next + 1));
+                    if !on_path[to] && leads_to_holder[to] && linkage.propagates(category) {
+                        on_path[to] = true;
+                        path.push((to, linkage));
+                        stack.push((to, 0));
+                    }
+                }
+                None => {
+                    on_path[node] = false;
+                    path.pop();
+                }
+            }
+        }
+        (found, false)
+    }
+}
+
+fn is_reported(category: Option<&NewCopyleftStrength>) -> bool {
+    matches!(category, Some(NewCopyleftStrength::Copyleft | NewCopyleftStrength::CopyleftLimited | NewCopyleftStrength::Commercial))
+}
+
+/// Propagates license obligations from dependencies up to every root of a graph
+///
+/// A license reaches a root if some path to it only crosses edges that pass it (see
+/// [`Linkage::propagates`]). The root's risk comes from analyzing its own license ANDed with
+/// every license that reaches it, so the usual compatibility rules decide the result.
+pub fn analyze_license_graph(parser: &LicenseExpressionParser, graph: &LicenseGraph) -> GraphPropagationReport {
+    let prepared = PreparedGraph::new(parser, graph);
+    let id = |node: usize| graph.nodes[node].id.clone();
+
+    let roots = prepared.roots().into_iter()
+        .map(|root| {
+            let reaching = prepared.reaching(root, None);
+            let (combined, analysis, risk_level) = prepared.combine(parser, root, &reaching, None);
+
+            let mut paths = Vec::new();
+            let mut paths_truncated = false;
+            for &holder in reaching.iter().filter(|&&n| is_reported(prepared.categories[n].as_ref())) {
+                let (mut found, truncated) = prepared.paths(root, holder);
+                found.sort_by_key(Vec::len);
+                paths_truncated |= truncated;
+                for steps in found {
+                    paths.push(PropagationPath {
+                        holder: id(holder),
+                        expression: prepared.expressions[holder].as_ref().map(|e| e.to_string()).unwrap_or_default(),
+                        category: prepared.categories[holder].clone().unwrap_or(NewCopyleftStrength::UnstatedLicense),
+                        path: std::iter::once(id(root)).chain(steps.iter().map(|(n, _)| id(*n))).collect(),
+                        linkages: steps.iter().map(|(_, l)| *l).collect(),
+                    });
+                }
+            }
+
+            let mut reductions = Vec::new();
+            let shipped = prepared.reach(root, None, |l| l.is_shipped());
+            for node in (0..graph.nodes.len()).filter(|&n| n != root && shipped[n]) {
+                let (_, _, without) = prepared.combine(parser, root, &prepared.reaching(root, Some(node)), None);
+                if risk_level_order(&without) < risk_level_order(&risk_level) {
+                    reductions.push(RiskReduction { node: id(node), remediation: Remediation::Remove, risk_level: without });
+                }
+                let holds_restrictive = prepared.categories[node].as_ref()
+                    .is_none_or(|c| !matches!(c, NewCopyleftStrength::Permissive | NewCopyleftStrength::PublicDomain));
+                if reaching.contains(&node) && holds_restrictive {
+                    let (_, _, relicensed) = prepared.combine(parser, root, &reaching, Some(node));
+                    if risk_level_order(&relicensed) < risk_level_order(&risk_level) {
+                        reductions.push(RiskReduction { node: id(node), remediation: Remediation::Relicense, risk_level: relicensed });
+                    }
+                }
+            }
+            reductions.sort_by(|a, b| risk_level_order(&a.risk_level).cmp(&risk_level_order(&b.risk_level)).then(a.node.cmp(&b.node)));
+
+            RootPropagation {
+                root: id(root),
+                own_expression: prepared.expressions[root].as_ref().map(|e| e.to_string()),
+                propagated_expression: combined.map(|e| e.to_string()),
+                analysis,
+                risk_level,
+                unlicensed: reaching.iter().filter(|&&n| prepared.expressions[n].is_none()).map(|&n| id(n)).collect(),
+                paths,
+                paths_truncated,
+                reductions,
+            }
+        })
+        .collect();
+
+    GraphPropagationReport {
+        database_version: parser.database_version().map(|v| v.label()),
+        roots,
+    }
+}
+
+impl fmt::Display for GraphPropagationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Copyleft Propagation")?;
+        if let Some(ref version) = self.database_version {
+            writeln!(f, "Database Version: {}", version)?;
+        }
+        for root in &self.roots {
+            writeln!(f, "Root: {} ({})", root.root, root.own_expression.as_deref().unwrap_or("NO LICENSE"))?;
+            writeln!(f, "  Propagated License: {}", root.propagated_expression.as_deref().unwrap_or("NONE"))?;
+            writeln!(f, "  Risk Level: {}", root.risk_level)?;
+            for conflict in root.analysis.iter().flat_map(|a| a.conflicts.iter()) {
+                writeln!(f, "  Conflict: {}", conflict)?;
+            }
+            if !root.unlicensed.is_empty() {
+                writeln!(f, "  Unlicensed: {}", root.unlicensed.join(", "))?;
+            }
+            if !root.paths.is_empty() {
+                writeln!(f, "  PROPAGATION PATHS ({}):", root.paths.len())?;
+                for path in &root.paths {
+                    let mut chain = path.path[0].clone();
+                    for (node, linkage) in path.path[1..].iter().zip(&path.linkages) {
+                        chain.push_str(&format!(" -[{}]-> {}", linkage, node));
+                    }
+                    writeln!(f, "    {} ({}, {}): {}", path.holder, path.expression, path.category, chain)?;
+                }
+                if root.paths_truncated {
+                    writeln!(f, "    ... more paths omitted ({} listed per license holder)", MAX_PATHS_PER_HOLDER)?;
+                }
+            }
+            if !root.reductions.is_empty() {
+                writeln!(f, "  RISK REDUCTIONS:")?;
+                for reduction in &root.reductions {
+                    writeln!(f, "    {} {}: {} -> {}", reduction.remediation, reduction.node, root.risk_level, reduction.risk_level)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inventory::Component;
+    use crate::test_support::{parser, temp_dir, write_file};
+
+    fn graph(nodes: &[(&str, Option<&str>)], edges: &[(&str, &str, Linkage)]) -> LicenseGraph {
+        LicenseGraph {
+            roots: Vec::new(),
+            nodes: nodes.iter()
+                .map(|(id, expression)| GraphNode { id: id.to_string(), name: None, expression: expression.map(String::from) })
+                .collect(),
+            edges: edges.iter()
+                .map(|(from, to, linkage)| GraphEdge { from: from.to_string(), to: to.to_string(), linkage: *linkage })
+                .collect(),
+        }
+    }
+
+    fn root(report: &GraphPropagationReport) -> &RootPropagation {
+        assert_eq!(report.roots.len(), 1);
+        &report.roots[0]
+    }
+
+    #[test]
+    fn weak_copyleft_stops_at_dynamic_links() {
+        assert!(Linkage::Static.propagates(Some(&NewCopyleftStrength::CopyleftLimited)));
+        assert!(!Linkage::Dynamic.propagates(Some(&NewCopyleftStrength::CopyleftLimited)));
+        assert!(Linkage::Dynamic.propagates(Some(&NewCopyleftStrength::Copyleft)));
+        assert!(Linkage::Dynamic.propagates(None));
+        assert!(!Linkage::Tool.propagates(Some(&NewCopyleftStrength::Copyleft)));
+
+        let parser = parser();
+        let dynamic = graph(&[("app", Some("MIT")), ("lgpl", Some("LGPL-2.1-only"))], &[("app", "lgpl", Linkage::Dynamic)]);
+        let report = analyze_license_graph(&parser, &dynamic);
+        assert_eq!(root(&report).propagated_expression.as_deref(), Some("MIT"));
+        assert_eq!(root(&report).risk_level, RiskLevel::Low);
+
+        let linked = graph(&[("app", Some("MIT")), ("lgpl", Some("LGPL-2.1-only"))], &[("app", "lgpl", Linkage::Static)]);
+        let report = analyze_license_graph(&parser, &linked);
+        assert_eq!(root(&report).propagated_expression.as_deref(), Some("LGPL-2.1-only AND MIT"));
+        assert_eq!(root(&report).paths[0].path, vec!["app", "lgpl"]);
+    }
+
+    #[test]
+    fn strong_copyleft_reaches_through_transitive_shipped_edges_only() {
+        let parser = parser();
+        let nodes = [("app", Some("MIT")), ("lib", Some("MIT")), ("gpl", Some("GPL-3.0-only")), ("codegen", Some("AGPL-3.0-only"))];
+        let edges = [("app", "lib", Linkage::Static), ("lib", "gpl", Linkage::Dynamic), ("app", "codegen", Linkage::BuildOnly)];
+        let report = analyze_license_graph(&parser, &graph(&nodes, &edges));
+        let app = root(&report);
+        assert_eq!(app.root, "app");
+        assert_eq!(app.propagated_expression.as_deref(), Some("GPL-3.0-only AND MIT"));
+        assert_eq!(app.paths.len(), 1);
+        assert_eq!(app.paths[0].linkages, vec![Linkage::Static, Linkage::Dynamic]);
+        assert!(risk_level_order(&app.risk_level) > risk_level_order(&RiskLevel::Low));
+
+        let remediations: Vec<(&str, Remediation)> = app.reductions.iter().map(|r| (r.node.as_str(), r.remediation)).collect();
+        assert!(remediations.contains(&("lib", Remediation::Remove)));
+        assert!(remediations.contains(&("gpl", Remediation::Remove)));
+        assert!(remediations.contains(&("gpl", Remediation::Relicense)));
+        assert!(!remediations.iter().any(|(node, _)| *node == "codegen"));
+        assert!(report.to_string().contains("gpl (GPL-3.0-only, Copyleft): app -[static]-> lib -[dynamic]-> gpl"));
+    }
+
+    #[test]
+    fn unlicensed_dependencies_make_the_risk_unknown() {
+        let nodes = [("app", Some("MIT")), ("mystery", None)];
+        let report = analyze_license_graph(&parser(), &graph(&nodes, &[("app", "mystery", Linkage::Static)]));
+        assert_eq!(root(&report).unlicensed, vec!["mystery"]);
+        assert_eq!(root(&report).risk_level, RiskLevel::Unknown);
+    }
+
+    #[test]
+    fn paths_survive_cycles_and_are_capped_per_holder() {
+        let parser = parser();
+        let mut cyclic = graph(
+            &[("app", Some("MIT")), ("a", Some("MIT")), ("b", Some("MIT")), ("gpl", Some("GPL-2.0-only"))],
+            &[("app", "a", Linkage::Static), ("a", "b", Linkage::Static), ("b", "a", Linkage::Static), ("b", "gpl", Linkage::Static)],
+        );
+        cyclic.roots = vec!["app".to_string()];
+        let report = analyze_license_graph(&parser, &cyclic);
+        assert_eq!(root(&report).paths.len(), 1);
+        assert!(!root(&report).paths_truncated);
+
+        // Five layers of two nodes each give 2^5 paths to the holder
+        let mut nodes = vec![("app".to_string(), Some("MIT")), ("gpl".to_string(), Some("GPL-2.0-only"))];
+        let mut edges = Vec::new();
+        let mut previous = vec!["app".to_string()];
+        for layer in 0..5 {
+            let current: Vec<String> = (0..2).map(|i| format!("n{}-{}", layer, i)).collect();
+            for from in &previous {
+                for to in &current {
+                    edges.push((from.clone(), to.clone(), Linkage::Static));
+                }
+            }
+            nodes.extend(current.iter().map(|id| (id.clone(), Some("MIT"))));
+            previous = current;
+        }
+        edges.extend(previous.iter().map(|from| (from.clone(), "gpl".to_string(), Linkage::Static)));
+        let node_refs: Vec<(&str, Option<&str>)> = nodes.iter().map(|(id, e)| (id.as_str(), *e)).collect();
+        let edge_refs: Vec<(&str, &str, Linkage)> = edges.iter().map(|(f, t, l)| (f.as_str(), t.as_str(), *l)).collect();
+        let report = analyze_license_graph(&parser, &graph(&node_refs, &edge_refs));
+        assert_eq!(root(&report).paths.len(), MAX_PATHS_PER_HOLDER);
+        assert!(root(&report).paths_truncated);
+    }
+
+    #[test]
+    fn builds_graphs_from_inventories_and_files() {
+        let mut app = Component::new("app", "app", None, DependencyKind::Root);
+        app.dependencies = vec!["lib".to_string(), "test".to_string()];
+        let inventory = Inventory {
+            source: "test".to_string(),
+            roots: vec!["app".to_string()],
+            components: vec![app, Component::new("lib", "lib", None, DependencyKind::Normal), Component::new("test", "test", None, DependencyKind::Dev)],
+        };
+        let graph = LicenseGraph::from_inventory(&inventory, Linkage::Dynamic);
+        let linkages: Vec<Linkage> = graph.edges.iter().map(|e| e.linkage).collect();
+        assert_eq!(linkages, vec![Linkage::Dynamic, Linkage::DevOnly]);
+
+        let dir = temp_dir("graph");
+        let path = write_file(&dir, "graph.json", r#"{"nodes": [{"id": "a", "expression": "MIT"}, {"id": "b"}], "edges": [{"from": "a", "to": "b", "linkage": "build-only"}]}"#);
+        let loaded = LicenseGraph::load_file(&path).unwrap();
+        assert_eq!(loaded.edges[0].linkage, Linkage::BuildOnly);
+        let bad = write_file(&dir, "bad.json", r#"{"nodes": [], "edges": [{"from": "a", "to": "b", "linkage": "sideways"}]}"#);
+        assert!(matches!(LicenseGraph::load_file(&bad), Err(InventoryError::ParseError(_))));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
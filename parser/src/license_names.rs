@@ -0,0 +1,329 @@
+use std::fmt;
+use std::sync::OnceLock;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use crate::license_database::LicenseDatabase;
+use crate::license_query::{edit_distance, normalize_id};
+
+/// Common free-text license names and the SPDX id they stand for
+/// Keys are compared after normalize_id, so case, spaces and punctuation don't matter.
+const KNOWN_LICENSE_NAMES: &[(&str, &str)] = &[
+    ("mit license", "MIT"),
+    ("the mit license", "MIT"),
+    ("expat", "MIT"),
+    ("apache", "Apache-2.0"),
+    ("apache 2", "Apache-2.0"),
+    ("apache 2.0", "Apache-2.0"),
+    ("apache license 2.0", "Apache-2.0"),
+    ("apache license version 2.0", "Apache-2.0"),
+    ("apache software license", "Apache-2.0"),
+    ("apache software license 2.0", "Apache-2.0"),
+    ("the apache software license version 2.0", "Apache-2.0"),
+    ("asl 2.0", "Apache-2.0"),
+    ("bsd 2 clause", "BSD-2-Clause"),
+    ("simplified bsd", "BSD-2-Clause"),
+    ("freebsd", "BSD-2-Clause"),
+    ("bsd 3 clause", "BSD-3-Clause"),
+    ("bsd", "BSD-3-Clause"),
+    ("new bsd", "BSD-3-Clause"),
+    ("new bsd license", "BSD-3-Clause"),
+    ("modified bsd", "BSD-3-Clause"),
+    ("revised bsd", "BSD-3-Clause"),
+    ("isc license", "ISC"),
+    ("zlib license", "Zlib"),
+    ("zlib/libpng", "Zlib"),
+    ("mpl 2.0", "MPL-2.0"),
+    ("mozilla public license 2.0", "MPL-2.0"),
+    ("gplv2", "GPL-2.0-only"),
+    ("gpl v2", "GPL-2.0-only"),
+    ("gplv2+", "GPL-2.0-or-later"),
+    ("gplv3", "GPL-3.0-only"),
+    ("gpl v3", "GPL-3.0-only"),
+    ("gplv3+", "GPL-3.0-or-later"),
+    ("gnu gpl v3", "GPL-3.0-only"),
+    ("gnu general public license v2", "GPL-2.0-only"),
+    ("gnu general public license v3", "GPL-3.0-only"),
+    ("lgpl", "LGPL-2.0-or-later"),
+    ("lgplv2", "LGPL-2.1-only"),
+    ("lgplv2+", "LGPL-2.1-or-later"),
+    ("lgplv3", "LGPL-3.0-only"),
+    ("lgplv3+", "LGPL-3.0-or-later"),
+    ("agplv3", "AGPL-3.0-only"),
+    ("agplv3+", "AGPL-3.0-or-later"),
+    ("psf", "PSF-2.0"),
+    ("psfl", "PSF-2.0"),
+    ("python software foundation license", "PSF-2.0"),
+    ("public domain", "LicenseRef-scancode-public-domain"),
+    ("unlicense", "Unlicense"),
+    ("the unlicense", "Unlicense"),
+    ("cc0", "CC0-1.0"),
+    ("boost software license", "BSL-1.0"),
+    ("eclipse public license 2.0", "EPL-2.0"),
+    ("eclipse public license 1.0", "EPL-1.0"),
+    ("eupl 1.2", "EUPL-1.2"),
+    // Names found in Maven POMs
+    ("the apache license version 2.0", "Apache-2.0"),
+    ("apache license v2.0", "Apache-2.0"),
+    ("the bsd license", "BSD-3-Clause"),
+    ("gnu lesser general public license", "LGPL-2.1-or-later"),
+    ("gnu lesser general public license version 2.1", "LGPL-2.1-only"),
+    ("gnu lesser general public license v2.1", "LGPL-2.1-only"),
+    ("lgpl 2.1", "LGPL-2.1-only"),
+    ("mozilla public license version 2.0", "MPL-2.0"),
+    ("eclipse public license v1.0", "EPL-1.0"),
+    ("eclipse public license v 1.0", "EPL-1.0"),
+    ("eclipse public license v2.0", "EPL-2.0"),
+    ("eclipse distribution license v1.0", "BSD-3-Clause"),
+    ("cddl 1.1", "CDDL-1.1"),
+    ("common development and distribution license (cddl) v1.0", "CDDL-1.0"),
+    ("gpl2 w/ cpe", "GPL-2.0-only WITH Classpath-exception-2.0"),
+    ("gnu general public license version 2 with the classpath exception", "GPL-2.0-only WITH Classpath-exception-2.0"),
+];
+
+/// License URLs, without scheme, `www.` and extension, and the SPDX id they stand for
+const KNOWN_LICENSE_URLS: &[(&str, &str)] = &[
+    ("apache.org/licenses/license-2.0", "Apache-2.0"),
+    ("opensource.org/licenses/mit-license", "MIT"),
+    ("opensource.org/licenses/bsd-license", "BSD-3-Clause"),
+    ("gnu.org/licenses/gpl-2.0", "GPL-2.0-only"),
+    ("gnu.org/licenses/old-licenses/gpl-2.0", "GPL-2.0-only"),
+    ("gnu.org/licenses/gpl-3.0", "GPL-3.0-only"),
+    // Unversioned pages stand for any version of the license
+    ("gnu.org/licenses/gpl", "GPL-1.0-or-later"),
+    ("gnu.org/licenses/lgpl-2.1", "LGPL-2.1-only"),
+    ("gnu.org/licenses/old-licenses/lgpl-2.1", "LGPL-2.1-only"),
+    ("gnu.org/licenses/lgpl-3.0", "LGPL-3.0-only"),
+    ("gnu.org/licenses/lgpl", "LGPL-2.0-or-later"),
+    ("gnu.org/licenses/agpl-3.0", "AGPL-3.0-only"),
+    ("gnu.org/software/classpath/license", "GPL-2.0-only WITH Classpath-exception-2.0"),
+    ("mozilla.org/mpl/2.0", "MPL-2.0"),
+    ("mozilla.org/mpl/mpl-1.1", "MPL-1.1"),
+    ("eclipse.org/legal/epl-v10", "EPL-1.0"),
+    ("eclipse.org/legal/epl-2.0", "EPL-2.0"),
+    ("eclipse.org/legal/epl-v20", "EPL-2.0"),
+    ("eclipse.org/org/documents/edl-v10", "BSD-3-Clause"),
+    ("creativecommons.org/publicdomain/zero/1.0", "CC0-1.0"),
+    ("unlicense.org", "Unlicense"),
+];
+
+/// How a free-text license name was mapped to an SPDX id
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NameMatchMethod {
+    /// The name is an id or alias in the license database
+    Exact,
+    /// The name is in the table of common license names
+    KnownName,
+    /// The license URL is in the table of common license URLs
+    KnownUrl,
+    /// The closest id in the database, which deserves review
+    Suggested,
+}
+
+impl fmt::Display for NameMatchMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NameMatchMethod::Exact => write!(f, "exact"),
+            NameMatchMethod::KnownName => write!(f, "known name"),
+            NameMatchMethod::KnownUrl => write!(f, "known url"),
+            NameMatchMethod::Suggested => write!(f, "suggested"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LicenseNameMatch {
+    pub id: String,
+    pub method: NameMatchMethod,
+}
+
+/// A free-text license field converted to an SPDX expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizedLicense {
+    pub expression: String,
+    /// One note per name that was not an exact id, for the reader to review
+    pub notes: Vec<String>,
+}
+
+static OR_SEPARATOR: OnceLock<Regex> = OnceLock::new();
+static AND_SEPARATOR: OnceLock<Regex> = OnceLock::new();
+
+/// Free-text fields longer than this are taken to be license texts rather than names
+pub(crate) const MAX_LICENSE_NAME_LENGTH: usize = 100;
+
+/// Drops trailing punctuation and a "License"/"Licence" suffix, which don't help matching
+fn strip_noise(name: &str) -> String {
+    let mut name = name.trim().trim_end_matches(['.', ',', ';']).to_string();
+    for suffix in [" license", " licence"] {
+        let split = name.len().saturating_sub(suffix.len());
+        if name.get(split..).is_some_and(|end| end.eq_ignore_ascii_case(suffix)) {
+            name.truncate(split);
+        }
+    }
+    name.trim().to_string()
+}
+
+impl LicenseDatabase {
+    /// Maps a free-text license name such as "Apache Software License" or "GPLv3" to an SPDX id
+    pub fn map_license_name(&self, name: &str) -> Option<LicenseNameMatch> {
+        let name = name.trim();
+        if name.is_empty() {
+            return None;
+        }
+        if let Some(license) = self.get(name) {
+            return Some(LicenseNameMatch { id: license.name, method: NameMatchMethod::Exact });
+        }
+
+        for candidate in [name.to_string(), strip_noise(name)] {
+            let normalized = normalize_id(&candidate);
+            if let Some((_, id)) = KNOWN_LICENSE_NAMES.iter().find(|(known, _)| normalize_id(known) == normalized) {
+                return Some(LicenseNameMatch { id: id.to_string(), method: NameMatchMethod::KnownName });
+            }
+            // "Apache 2.0" and "apache-2.0" only differ in punctuation from a database id
+            let hyphenated = candidate.split_whitespace().collect::<Vec<_>>().join("-");
+            if let Some(license) = self.get(&hyphenated) {
+                return Some(LicenseNameMatch { id: license.name, method: NameMatchMethod::Exact });
+            }
+        }
+
+        let stripped = strip_noise(name);
+        if let Some(id) = self.suggest_license_ids(&stripped, 1).into_iter().next() {
+            return Some(LicenseNameMatch { id, method: NameMatchMethod::Suggested });
+        }
+
+        // Misspelled common names such as "Apache Lisence 2.0"
+        let normalized = normalize_id(&stripped);
+        let max_distance = (normalized.len() / 5).max(1);
+        KNOWN_LICENSE_NAMES.iter()
+            .map(|(known, id)| (edit_distance(&normalized, &normalize_id(&strip_noise(known))), id))
+            .filter(|(distance, _)| *distance <= max_distance)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, id)| LicenseNameMatch { id: id.to_string(), method: NameMatchMethod::Suggested })
+    }
+
+    /// Maps a license URL such as `https://www.apache.org/licenses/LICENSE-2.0.txt` to an SPDX id
+    ///
+    /// Besides the table of common URLs, `opensource.org/licenses/<id>` and `spdx.org/licenses/<id>`
+    /// are looked up in the database.
+    pub fn map_license_url(&self, url: &str) -> Option<LicenseNameMatch> {
+        let url = url.trim().to_lowercase();
+        let url = url.split_once("://").map_or(url.as_str(), |(_, rest)| rest);
+        let url = url.strip_prefix("www.").unwrap_or(url).trim_end_matches('/');
+        let url = [".html", ".htm", ".txt", ".php"].iter()
+            .find_map(|extension| url.strip_suffix(extension))
+            .unwrap_or(url);
+
+        if let Some((_, id)) = KNOWN_LICENSE_URLS.iter().find(|(known, _)| *known == url) {
+            return Some(LicenseNameMatch { id: id.to_string(), method: NameMatchMethod::KnownUrl });
+        }
+        for prefix in ["opensource.org/licenses/", "spdx.org/licenses/"] {
+            if let Some(license) = url.strip_prefix(prefix).and_then(|id| self.get(id)) {
+                return Some(LicenseNameMatch { id: license.name, method: NameMatchMethod::KnownUrl });
+            }
+        }
+        None
+    }
+
+    /// Converts a free-text license field ("MIT or Apache 2.0", "GPLv2+/BSD") to an SPDX expression
+    ///
+    /// Alternatives separated by "or", "/" or "|" become OR, "and" becomes AND. Returns None if
+    /// the field looks like a full license text or any part cannot be mapped.
+    pub fn normalize_license_field(&self, field: &str) -> Option<NormalizedLicense> {
+        let field = field.trim();
+        if field.is_empty() || field.len() > MAX_LICENSE_NAME_LENGTH || field.lines().count() > 1 {
+            return None;
+        }
+
+        // Already a valid expression of known ids
+        let is_spdx = field.split(|c: char| c.is_whitespace() || c == '(' || c == ')')
+            .filter(|token| !token.is_empty())
+            .all(|token| matches!(token, "AND" | "OR" | "WITH") || self.get(token).is_some_and(|l| l.name == token));
+        if is_spdx {
+            return Some(NormalizedLicense { expression: field.to_string(), notes: Vec::new() });
+        }
+
+        // Names such as "zlib/libpng License" contain a separator themselves
+        if let Some(mapped) = self.map_license_name(field).filter(|m| m.method != NameMatchMethod::Suggested) {
+            let notes = vec![format!("'{}' read as {} ({})", field, mapped.id, mapped.method)];
+            return Some(NormalizedLicense { expression: mapped.id, notes });
+        }
+
+        let or_separator = OR_SEPARATOR.get_or_init(|| Regex::new(r"(?i)\s+or\s+|\s*/\s*|\s*\|\s*").expect("valid regex"));
+        let and_separator = AND_SEPARATOR.get_or_init(|| Regex::new(r"(?i)\s+and\s+|\s*&\s*").expect("valid regex"));
+
+        let mut notes = Vec::new();
+        let mut alternatives: Vec<Vec<String>> = Vec::new();
+        for alternative in or_separator.split(field) {
+            let mut parts = Vec::new();
+            for part in and_separator.split(alternative.trim_matches(['(', ')', ' '])) {
+                let mapped = self.map_license_name(part)?;
+                if mapped.id != part.trim() {
+                    notes.push(format!("'{}' read as {} ({})", part.trim(), mapped.id, mapped.method));
+                }
+                parts.push(mapped.id);
+            }
+            alternatives.push(parts);
+        }
+
+        let expression = if alternatives.len() == 1 {
+            alternatives[0].join(" AND ")
+        } else {
+            alternatives.iter()
+                .map(|parts| if parts.len() > 1 { format!("({})", parts.join(" AND ")) } else { parts.join("") })
+                .collect::<Vec<_>>()
+                .join(" OR ")
+        };
+        Some(NormalizedLicense { expression, notes })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::parser;
+
+    fn normalize(field: &str) -> Option<String> {
+        parser().database().normalize_license_field(field).map(|n| n.expression)
+    }
+
+    #[test]
+    fn maps_common_names() {
+        let database = parser().database();
+        let mapped = |name: &str| database.map_license_name(name);
+        assert_eq!(mapped("MIT"), Some(LicenseNameMatch { id: "MIT".to_string(), method: NameMatchMethod::Exact }));
+        assert_eq!(mapped("Apache Software License").unwrap().id, "Apache-2.0");
+        assert_eq!(mapped("GPLv3+").unwrap().id, "GPL-3.0-or-later");
+        assert_eq!(mapped("LGPL"), Some(LicenseNameMatch { id: "LGPL-2.0-or-later".to_string(), method: NameMatchMethod::KnownName }));
+        assert_eq!(mapped("Apache Lisence 2.0").unwrap().method, NameMatchMethod::Suggested);
+        assert_eq!(mapped("  "), None);
+    }
+
+    #[test]
+    fn normalizes_free_text_fields() {
+        assert_eq!(normalize("MIT OR Apache-2.0").as_deref(), Some("MIT OR Apache-2.0"));
+        assert_eq!(normalize("MIT or Apache 2.0").as_deref(), Some("MIT OR Apache-2.0"));
+        assert_eq!(normalize("GPLv2+/BSD").as_deref(), Some("GPL-2.0-or-later OR BSD-3-Clause"));
+        assert_eq!(normalize("MIT and ISC License | Zlib").as_deref(), Some("(MIT AND ISC) OR Zlib"));
+    }
+
+    #[test]
+    fn whole_field_is_tried_before_splitting() {
+        assert_eq!(normalize("zlib/libpng License").as_deref(), Some("Zlib"));
+        assert_eq!(normalize("zlib/libpng").as_deref(), Some("Zlib"));
+    }
+
+    #[test]
+    fn rejects_license_texts() {
+        assert_eq!(normalize("Permission is hereby granted\nfree of charge"), None);
+        assert_eq!(normalize(&"x".repeat(MAX_LICENSE_NAME_LENGTH + 1)), None);
+    }
+
+    #[test]
+    fn maps_license_urls() {
+        let database = parser().database();
+        let url = |url: &str| database.map_license_url(url).map(|m| m.id);
+        assert_eq!(url("https://www.apache.org/licenses/LICENSE-2.0.txt").as_deref(), Some("Apache-2.0"));
+        assert_eq!(url("http://opensource.org/licenses/ISC").as_deref(), Some("ISC"));
+        assert_eq!(url("https://spdx.org/licenses/MPL-2.0.html").as_deref(), Some("MPL-2.0"));
+        assert_eq!(url("https://example.com/license"), None);
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::license_database::{self, LicenseDatabaseError, LicenseSource, LicenseStore, NewLicense};
+
+/// A single license definition inside an overlay file
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct OverlayLicense {
+    pub id: String,
+    /// ScanCode category name; may be omitted when only overriding obligations or compatibility
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub obligations: Vec<String>,
+    #[serde(default)]
+    pub compatible_with: Vec<String>,
+    #[serde(default)]
+    pub incompatible_with: Vec<String>,
+}
+
+/// On-disk format of a license overlay file
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct OverlayFile {
+    pub licenses: Vec<OverlayLicense>,
+}
+
+/// User-defined license layer placed on top of the bundled license database
+///
+/// Precedence: overlay entries always win over the bundled index, and when several
+/// overlay files are applied, the file applied last wins.
+#[derive(Debug, Clone, Default)]
+pub struct LicenseOverlay {
+    licenses: HashMap<String, NewLicense>,
+    compatibility: HashMap<(String, String), bool>,
+}
+
+/// Reads and parses an overlay file without applying it
+pub fn load_overlay_file(path: &Path) -> Result<OverlayFile, LicenseDatabaseError> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| LicenseDatabaseError::FileReadError(format!("{}: {}", path.display(), e)))?;
+
+    serde_json::from_str(&content)
+        .map_err(|e| LicenseDatabaseError::JsonParseError(format!("{}: {}", path.display(), e)))
+}
+
+impl LicenseOverlay {
+    pub fn new() -> Self {
+        LicenseOverlay::default()
+    }
+
+    /// Loads an overlay file and applies it on top of the current layers
+    pub fn apply_path(&mut self, path: &Path, base: &LicenseStore) -> Result<(), LicenseDatabaseError> {
+        let file = load_overlay_file(path)?;
+        self.apply(&file, &path.display().to_string(), base)
+    }
+
+    /// Applies an overlay definition; `origin` is recorded as the source of every license it defines
+    pub fn apply(&mut self, file: &OverlayFile, origin: &str, base: &LicenseStore) -> Result<(), LicenseDatabaseError> {
+        // Built on the first override, then shared by every entry of the file
+        let mut base_keys: Option<HashMap<String, Vec<String>>> = None;
+        for entry in &file.licenses {
+            let existing = self.resolve(&entry.id, base);
+
+            let copyleft_strength = match (&entry.category, &existing) {
+                (Some(category), _) => license_database::category_to_copyleft_strength(category)
+                    .ok_or_else(|| LicenseDatabaseError::InvalidOverlay(
+                        format!("{}: unknown category '{}' for {}", origin, category, entry.id)
+                    ))?,
+                (None, Some(license)) => license.copyleft_strength.clone(),
+                (None, None) => {
+                    return Err(LicenseDatabaseError::InvalidOverlay(
+                        format!("{}: new license {} must declare a category", origin, entry.id)
+                    ));
+                }
+            };
+
+            let name = existing.as_ref()
+                .map(|l| l.name.clone())
+                .unwrap_or_else(|| entry.id.clone());
+
+            let mut obligations = existing.as_ref()
+                .map(|l| l.obligations.clone())
+                .unwrap_or_default();
+            if !entry.obligations.is_empty() {
+                obligations = entry.obligations.clone();
+            }
+
+            let is_exception = existing.as_ref().map(|l| l.is_exception).unwrap_or(false);
+            let is_deprecated = existing.as_ref().map(|l| l.is_deprecated).unwrap_or(false);
+            let spdx_id = existing.as_ref().map(|l| l.spdx_id.clone()).unwrap_or_else(|| Some(name.clone()));
+
+            // Overriding an existing license also overrides every key that resolves to it
+            let mut keys = vec![entry.id.to_lowercase()];
+            keys.extend(entry.aliases.iter().map(|a| a.to_lowercase()));
+            if let Some(ref license) = existing {
+                let base_keys = base_keys.get_or_insert_with(|| base.keys_by_name());
+                keys.extend(base_keys.get(&license.name).into_iter().flatten().cloned());
+                keys.extend(self.keys_for_name(&license.name));
+            }
+            keys.sort();
+            keys.dedup();
+
+            for key in keys {
+                self.licenses.insert(key.clone(), NewLicense {
+                    id: key,
+                    name: name.clone(),
+                    copyleft_strength: copyleft_strength.clone(),
+                    source: LicenseSource::Overlay(origin.to_string()),
+                    obligations: obligations.clone(),
+                    is_exception,
+                    is_deprecated,
+                    spdx_id: spdx_id.clone(),
+                });
+            }
+
+            let own = name.to_lowercase();
+            for (others, compatible) in [(&entry.compatible_with, true), (&entry.incompatible_with, false)] {
+                for other in others {
+                    let other_name = self.resolve(other, base)
+                        .map(|l| l.name.to_lowercase())
+                        .unwrap_or_else(|| other.to_lowercase());
+                    self.compatibility.insert((own.clone(), other_name.clone()), compatible);
+                    self.compatibility.insert((other_name, own.clone()), compatible);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Looks up a license id in the overlay only
+    pub fn get(&self, id: &str) -> Option<&NewLicense> {
+        self.licenses.get(&id.to_lowercase())
+    }
+
+    /// Returns an explicit compatibility decision between two licenses, if the overlay defines one
+    pub fn compatibility(&self, a: &NewLicense, b: &NewLicense) -> Option<bool> {
+        self.compatibility
+            .get(&(a.name.to_lowercase(), b.name.to_lowercase()))
+            .copied()
+    }
+
+    /// Iterates over every key defined by the overlay and the license it resolves to
+    pub fn entries(&self) -> impl Iterator<Item = (&String, &NewLicense)> {
+        self.licenses.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.licenses.is_empty() && self.compatibility.is_empty()
+    }
+
+    fn resolve(&self, id: &str, base: &LicenseStore) -> Option<NewLicense> {
+        self.get(id).cloned().or_else(|| base.get(id))
+    }
+
+    /// Overlay keys that resolve to the license with the given SPDX name
+    fn keys_for_name(&self, name: &str) -> Vec<String> {
+        self.licenses.iter()
+            .filter(|(_, license)| license.name == name)
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::license_database::{LicenseDatabase, LicenseStore};
+    use crate::license_expression_parser::LicenseExpressionParser;
+    use crate::models::NewCopyleftStrength;
+    use crate::test_support::{index_path, temp_dir, write_file};
+
+    fn overlay(json: &str) -> OverlayFile {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn missing_base_database_is_an_error() {
+        let dir = temp_dir("overlay-missing");
+        let result = LicenseExpressionParser::with_database::<&Path>(&dir.join("index.json"), &[]);
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(result, Err(LicenseDatabaseError::FileReadError(_))));
+    }
+
+    #[test]
+    fn corrupt_base_database_is_an_error() {
+        let dir = temp_dir("overlay-corrupt");
+        let corrupt = write_file(&dir, "index.json", "{ not json");
+        let result = LicenseExpressionParser::with_database::<&Path>(&corrupt, &[]);
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(result, Err(LicenseDatabaseError::JsonParseError(_))));
+    }
+
+    #[test]
+    fn overlay_defines_new_license_with_aliases() {
+        let (store, _) = LicenseStore::open(&index_path()).unwrap();
+        let mut layer = LicenseOverlay::new();
+        let file = overlay(r#"{"licenses": [{"id": "LicenseRef-Acme", "category": "Proprietary Free", "aliases": ["acme-eula"]}]}"#);
+        layer.apply(&file, "acme.json", &store).unwrap();
+
+        let license = layer.get("acme-eula").unwrap();
+        assert_eq!(license.name, "LicenseRef-Acme");
+        assert_eq!(license.source, LicenseSource::Overlay("acme.json".to_string()));
+        assert!(layer.get("licenseref-acme").is_some());
+    }
+
+    #[test]
+    fn new_license_without_category_is_rejected() {
+        let (store, _) = LicenseStore::open(&index_path()).unwrap();
+        let file = overlay(r#"{"licenses": [{"id": "LicenseRef-Acme"}]}"#);
+        let result = LicenseOverlay::new().apply(&file, "acme.json", &store);
+        assert!(matches!(result, Err(LicenseDatabaseError::InvalidOverlay(_))));
+    }
+
+    #[test]
+    fn override_applies_to_every_key_of_the_license() {
+        let dir = temp_dir("overlay-override");
+        let path = write_file(&dir, "override.json", r#"{"licenses": [{"id": "MIT", "category": "Copyleft"}]}"#);
+        let database = LicenseDatabase::open(&index_path(), &[&path]).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        // "mit" and "MIT" resolve to the same ScanCode entry, so both pick up the override
+        assert_eq!(database.get("MIT").unwrap().copyleft_strength, NewCopyleftStrength::Copyleft);
+        assert_eq!(database.get("mit").unwrap().copyleft_strength, NewCopyleftStrength::Copyleft);
+        assert_eq!(database.get("Apache-2.0").unwrap().copyleft_strength, NewCopyleftStrength::Permissive);
+    }
+
+    #[test]
+    fn override_on_compiled_store_applies_to_every_key() {
+        let dir = temp_dir("overlay-compiled");
+        let compiled = dir.join("index.db");
+        crate::compiled_license_database::compile_license_index_file(&index_path(), &compiled).unwrap();
+        let (store, _) = LicenseStore::open(&compiled).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let mut layer = LicenseOverlay::new();
+        layer.apply(&overlay(r#"{"licenses": [{"id": "bsd-new", "category": "Copyleft"}]}"#), "compiled", &store).unwrap();
+        let keys = store.keys_by_name().remove("BSD-3-Clause").unwrap();
+        assert!(keys.contains(&"bsd-new".to_string()) && keys.contains(&"bsd-3-clause".to_string()));
+        for key in keys {
+            assert_eq!(layer.get(&key).unwrap().copyleft_strength, NewCopyleftStrength::Copyleft);
+        }
+    }
+
+    #[test]
+    fn later_overlay_wins() {
+        let (store, _) = LicenseStore::open(&index_path()).unwrap();
+        let mut layer = LicenseOverlay::new();
+        layer.apply(&overlay(r#"{"licenses": [{"id": "MIT", "category": "Copyleft"}]}"#), "first", &store).unwrap();
+        layer.apply(&overlay(r#"{"licenses": [{"id": "MIT", "category": "Permissive"}]}"#), "second", &store).unwrap();
+        assert_eq!(layer.get("mit").unwrap().copyleft_strength, NewCopyleftStrength::Permissive);
+        assert_eq!(layer.get("mit").unwrap().source, LicenseSource::Overlay("second".to_string()));
+    }
+
+    #[test]
+    fn compatibility_is_symmetric() {
+        let (store, _) = LicenseStore::open(&index_path()).unwrap();
+        let mut layer = LicenseOverlay::new();
+        let file = overlay(r#"{"licenses": [{"id": "MIT", "incompatible_with": ["Apache-2.0"]}]}"#);
+        layer.apply(&file, "compat.json", &store).unwrap();
+        let mit = store.get("MIT").unwrap();
+        let apache = store.get("Apache-2.0").unwrap();
+        assert_eq!(layer.compatibility(&mit, &apache), Some(false));
+        assert_eq!(layer.compatibility(&apache, &mit), Some(false));
+    }
+}
@@ -0,0 +1,250 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::license_database::{LicenseDatabaseError, LicenseSource, NewLicense};
+use crate::license_waivers::Waiver;
+use crate::models::{risk_level_order, LicenseAnalysis, NewCopyleftStrength, RiskLevel};
+
+/// The policy rule a finding breaks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PolicyRule {
+    /// Risk level at or above the fail-on threshold
+    FailOn,
+    DeniedLicense,
+    DeniedCategory,
+    /// Not on the allowlist
+    NotAllowed,
+    Conflict,
+    MissingLicense,
+}
+
+impl fmt::Display for PolicyRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyRule::FailOn => write!(f, "fail-on"),
+            PolicyRule::DeniedLicense => write!(f, "denied-license"),
+            PolicyRule::DeniedCategory => write!(f, "denied-category"),
+            PolicyRule::NotAllowed => write!(f, "not-allowed"),
+            PolicyRule::Conflict => write!(f, "conflict"),
+            PolicyRule::MissingLicense => write!(f, "missing-license"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyViolation {
+    pub rule: PolicyRule,
+    pub message: String,
+}
+
+/// Rules an analyzed license expression must satisfy
+///
+/// License rules apply to the choices of an expression: an OR expression only breaks
+/// them if none of its alternatives is acceptable.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LicensePolicy {
+    /// Fail when the risk level reaches this level (Unknown ranks between High and Critical)
+    #[serde(default)]
+    pub fail_on: Option<RiskLevel>,
+    #[serde(default)]
+    pub denied_categories: Vec<NewCopyleftStrength>,
+    /// Denied SPDX ids, compared case-insensitively
+    #[serde(default)]
+    pub denied_licenses: Vec<String>,
+    /// If not empty, only these SPDX ids are accepted
+    #[serde(default)]
+    pub allowed_licenses: Vec<String>,
+    #[serde(default)]
+    pub fail_on_conflicts: bool,
+    /// Do not gate dependencies only used for development and tests
+    #[serde(default)]
+    pub ignore_dev_dependencies: bool,
+    /// Accepted findings, loaded from a separate baseline file
+    #[serde(default)]
+    pub waivers: Vec<Waiver>,
+}
+
+/// Policy file as written by users; categories accept ScanCode names such as "Copyleft Limited"
+#[derive(Debug, Deserialize)]
+struct PolicyFile {
+    #[serde(default)]
+    fail_on: Option<String>,
+    #[serde(default)]
+    denied_categories: Vec<String>,
+    #[serde(default)]
+    denied_licenses: Vec<String>,
+    #[serde(default)]
+    allowed_licenses: Vec<String>,
+    #[serde(default)]
+    fail_on_conflicts: bool,
+    #[serde(default)]
+    ignore_dev_dependencies: bool,
+}
+
+/// Loads a license policy from a JSON file
+pub fn load_policy_file(path: &Path) -> Result<LicensePolicy, LicenseDatabaseError> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| LicenseDatabaseError::FileReadError(format!("{}: {}", path.display(), e)))?;
+    let file: PolicyFile = serde_json::from_str(&content)
+        .map_err(|e| LicenseDatabaseError::JsonParseError(format!("{}: {}", path.display(), e)))?;
+
+    let invalid = |e: String| LicenseDatabaseError::InvalidPolicy(format!("{}: {}", path.display(), e));
+    Ok(LicensePolicy {
+        fail_on: file.fail_on.map(|risk| risk.parse()).transpose().map_err(invalid)?,
+        denied_categories: file.denied_categories.iter()
+            .map(|category| category.parse())
+            .collect::<Result<_, _>>()
+            .map_err(invalid)?,
+        denied_licenses: file.denied_licenses,
+        allowed_licenses: file.allowed_licenses,
+        fail_on_conflicts: file.fail_on_conflicts,
+        ignore_dev_dependencies: file.ignore_dev_dependencies,
+        waivers: Vec::new(),
+    })
+}
+
+/// SPDX id of a license as written in expressions
+fn spdx_id(license: &NewLicense) -> &str {
+    if license.source == LicenseSource::Unknown {
+        &license.id
+    } else {
+        &license.name
+    }
+}
+
+fn contains_id(ids: &[String], id: &str) -> bool {
+    ids.iter().any(|i| i.eq_ignore_ascii_case(id))
+}
+
+impl LicensePolicy {
+    /// Reasons a single license is unacceptable
+    fn license_violations(&self, license: &NewLicense) -> Vec<PolicyViolation> {
+        let id = spdx_id(license);
+        let mut violations = Vec::new();
+        if contains_id(&self.denied_licenses, &license.id) || contains_id(&self.denied_licenses, &license.name) {
+            violations.push(PolicyViolation {
+                rule: PolicyRule::DeniedLicense,
+                message: format!("{} is denied", id),
+            });
+        }
+        if self.denied_categories.contains(&license.copyleft_strength) {
+            violations.push(PolicyViolation {
+                rule: PolicyRule::DeniedCategory,
+                message: format!("{} is {}, a denied category", id, license.copyleft_strength),
+            });
+        }
+        if !self.allowed_licenses.is_empty()
+            && !contains_id(&self.allowed_licenses, &license.id)
+            && !contains_id(&self.allowed_licenses, &license.name)
+        {
+            violations.push(PolicyViolation {
+                rule: PolicyRule::NotAllowed,
+                message: format!("{} is not on the allowlist", id),
+            });
+        }
+        violations
+    }
+
+    /// Checks an analysis against the policy; `None` stands for a component without any declared license
+    pub fn evaluate(&self, analysis: Option<&LicenseAnalysis>) -> Vec<PolicyViolation> {
+        let Some(analysis) = analysis else {
+            return match self.fail_on {
+                Some(ref threshold) if risk_level_order(&RiskLevel::Unknown) >= risk_level_order(threshold) => {
+                    vec![PolicyViolation {
+                        rule: PolicyRule::MissingLicense,
+                        message: "No license declared".to_string(),
+                    }]
+                }
+                _ => Vec::new(),
+            };
+        };
+
+        let mut violations = Vec::new();
+        if let Some(ref threshold) = self.fail_on {
+            if risk_level_order(&analysis.risk_level) >= risk_level_order(threshold) {
+                violations.push(PolicyViolation {
+                    rule: PolicyRule::FailOn,
+                    message: format!("Risk level {} reaches the fail-on threshold {}", analysis.risk_level, threshold),
+                });
+            }
+        }
+
+        // An expression passes the license rules if any one of its choices does
+        let per_license: Vec<Vec<PolicyViolation>> = analysis.possible_licenses.iter()
+            .map(|license| self.license_violations(license))
+            .collect();
+        if !per_license.iter().any(|v| v.is_empty()) {
+            for violation in per_license.into_iter().flatten() {
+                if !violations.contains(&violation) {
+                    violations.push(violation);
+                }
+            }
+        }
+
+        if self.fail_on_conflicts {
+            for conflict in &analysis.conflicts {
+                violations.push(PolicyViolation {
+                    rule: PolicyRule::Conflict,
+                    message: conflict.clone(),
+                });
+            }
+        }
+
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{parser, temp_dir, write_file};
+
+    fn rules(policy: &LicensePolicy, expression: &str) -> Vec<PolicyRule> {
+        policy.evaluate(Some(&parser().analyze(expression))).into_iter().map(|v| v.rule).collect()
+    }
+
+    #[test]
+    fn loads_policy_files_with_scancode_category_names() {
+        let dir = temp_dir("policy");
+        let path = write_file(&dir, "policy.json", r#"{"fail_on": "High", "denied_categories": ["Copyleft Limited"], "denied_licenses": ["AGPL-3.0-only"]}"#);
+        let policy = load_policy_file(&path).unwrap();
+        assert_eq!(policy.fail_on, Some(RiskLevel::High));
+        assert_eq!(policy.denied_categories, vec![NewCopyleftStrength::CopyleftLimited]);
+
+        let invalid = write_file(&dir, "invalid.json", r#"{"denied_categories": ["Mostly Harmless"]}"#);
+        assert!(matches!(load_policy_file(&invalid), Err(LicenseDatabaseError::InvalidPolicy(_))));
+        let malformed = write_file(&dir, "malformed.json", "{");
+        assert!(matches!(load_policy_file(&malformed), Err(LicenseDatabaseError::JsonParseError(_))));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn or_expressions_pass_if_any_choice_is_acceptable() {
+        let policy = LicensePolicy { denied_categories: vec![NewCopyleftStrength::Copyleft], ..LicensePolicy::default() };
+        assert!(rules(&policy, "MIT OR GPL-3.0-only").is_empty());
+        assert_eq!(rules(&policy, "MIT AND GPL-3.0-only"), vec![PolicyRule::DeniedCategory]);
+        assert_eq!(rules(&policy, "GPL-3.0-only"), vec![PolicyRule::DeniedCategory]);
+    }
+
+    #[test]
+    fn denylist_and_allowlist_compare_ids_case_insensitively() {
+        let denied = LicensePolicy { denied_licenses: vec!["apache-2.0".to_string()], ..LicensePolicy::default() };
+        assert_eq!(rules(&denied, "Apache-2.0"), vec![PolicyRule::DeniedLicense]);
+        let allowed = LicensePolicy { allowed_licenses: vec!["mit".to_string()], ..LicensePolicy::default() };
+        assert!(rules(&allowed, "MIT").is_empty());
+        assert_eq!(rules(&allowed, "ISC"), vec![PolicyRule::NotAllowed]);
+    }
+
+    #[test]
+    fn fail_on_threshold_covers_missing_licenses() {
+        let policy = LicensePolicy { fail_on: Some(RiskLevel::High), ..LicensePolicy::default() };
+        assert_eq!(rules(&policy, "GPL-3.0-only"), vec![PolicyRule::FailOn]);
+        assert!(rules(&policy, "MIT").is_empty());
+        assert_eq!(policy.evaluate(None)[0].rule, PolicyRule::MissingLicense);
+        let critical = LicensePolicy { fail_on: Some(RiskLevel::Critical), ..LicensePolicy::default() };
+        assert!(critical.evaluate(None).is_empty());
+        assert!(LicensePolicy::default().evaluate(None).is_empty());
+    }
+}
@@ -0,0 +1,314 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use regex::Regex;
+use serde::Serialize;
+use crate::license_database::{LicenseDatabase, LicenseSource, NewLicense};
+use crate::models::{new_copyleft_strength_order, NewCopyleftStrength};
+
+/// A license as returned by database queries, with all ids that resolve to it
+#[derive(Debug, Clone, Serialize)]
+pub struct LicenseRecord {
+    pub id: String,
+    pub aliases: Vec<String>,
+    pub copyleft_strength: NewCopyleftStrength,
+    pub is_exception: bool,
+    pub is_deprecated: bool,
+    pub source: LicenseSource,
+}
+
+/// Pattern matched against a license id and its aliases
+#[derive(Debug, Clone)]
+pub enum IdPattern {
+    /// Case-insensitive glob supporting `*` and `?`
+    Glob(String),
+    Regex(Regex),
+}
+
+impl IdPattern {
+    pub fn matches(&self, id: &str) -> bool {
+        match self {
+            IdPattern::Glob(pattern) => glob_matches(&pattern.to_lowercase(), &id.to_lowercase()),
+            IdPattern::Regex(regex) => regex.is_match(id),
+        }
+    }
+}
+
+/// Matches `text` against a glob pattern where `*` matches any run and `?` any single character
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LicenseSortKey {
+    #[default]
+    Id,
+    /// Ordered by new_copyleft_strength_order, then id
+    CopyleftStrength,
+}
+
+/// Filters, ordering and pagination for listing licenses
+#[derive(Debug, Clone, Default)]
+pub struct LicenseQuery {
+    /// Keep licenses in any of these categories; empty keeps all
+    pub copyleft_strengths: Vec<NewCopyleftStrength>,
+    pub is_exception: Option<bool>,
+    pub is_deprecated: Option<bool>,
+    pub id_pattern: Option<IdPattern>,
+    pub sort_by: LicenseSortKey,
+    pub descending: bool,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+/// One page of query results
+#[derive(Debug, Clone, Serialize)]
+pub struct LicenseQueryResult {
+    /// Number of matching licenses before pagination
+    pub total: usize,
+    pub offset: usize,
+    pub licenses: Vec<LicenseRecord>,
+}
+
+impl LicenseQuery {
+    fn matches(&self, record: &LicenseRecord) -> bool {
+        if !self.copyleft_strengths.is_empty() && !self.copyleft_strengths.contains(&record.copyleft_strength) {
+            return false;
+        }
+        if self.is_exception.is_some_and(|flag| flag != record.is_exception) {
+            return false;
+        }
+        if self.is_deprecated.is_some_and(|flag| flag != record.is_deprecated) {
+            return false;
+        }
+        match self.id_pattern {
+            Some(ref pattern) => pattern.matches(&record.id) || record.aliases.iter().any(|a| pattern.matches(a)),
+            None => true,
+        }
+    }
+}
+
+impl LicenseDatabase {
+    /// Returns one record per license, with overlay definitions taking precedence over the bundled store
+    pub fn records(&self) -> Vec<LicenseRecord> {
+        let mut by_key: BTreeMap<String, NewLicense> = self.store().entries().into_iter().collect();
+        by_key.extend(self.overlay().entries().map(|(key, license)| (key.clone(), license.clone())));
+
+        let mut by_id: BTreeMap<String, LicenseRecord> = BTreeMap::new();
+        for (key, license) in by_key {
+            let record = by_id.entry(license.name.clone()).or_insert_with(|| LicenseRecord {
+                id: license.name.clone(),
+                aliases: Vec::new(),
+                copyleft_strength: license.copyleft_strength.clone(),
+                is_exception: license.is_exception,
+                is_deprecated: license.is_deprecated,
+                source: license.source.clone(),
+            });
+            if key != record.id.to_lowercase() {
+                record.aliases.push(key);
+            }
+        }
+
+        by_id.into_values().collect()
+    }
+
+    /// Lists licenses matching the query, sorted and paginated
+    pub fn query(&self, query: &LicenseQuery) -> LicenseQueryResult {
+        let mut matching: Vec<LicenseRecord> = self.records()
+            .into_iter()
+            .filter(|record| query.matches(record))
+            .collect();
+
+        match query.sort_by {
+            LicenseSortKey::Id => matching.sort_by_key(|r| r.id.to_lowercase()),
+            LicenseSortKey::CopyleftStrength => matching.sort_by_key(|r| {
+                (new_copyleft_strength_order(&r.copyleft_strength), r.id.to_lowercase())
+            }),
+        }
+        if query.descending {
+            matching.reverse();
+        }
+
+        let total = matching.len();
+        let licenses = matching.into_iter()
+            .skip(query.offset)
+            .take(query.limit.unwrap_or(usize::MAX))
+            .collect();
+
+        LicenseQueryResult {
+            total,
+            offset: query.offset,
+            licenses,
+        }
+    }
+}
+
+impl fmt::Display for LicenseQueryResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let headers = ["ID", "CATEGORY", "EXCEPTION", "DEPRECATED", "SOURCE"];
+        let rows: Vec<[String; 5]> = self.licenses.iter()
+            .map(|r| [
+                r.id.clone(),
+                r.copyleft_strength.to_string(),
+                if r.is_exception { "yes" } else { "no" }.to_string(),
+                if r.is_deprecated { "yes" } else { "no" }.to_string(),
+                r.source.to_string(),
+            ])
+            .collect();
+
+        let mut widths = headers.map(str::len);
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.len());
+            }
+        }
+
+        let header_cells: Vec<String> = headers.iter().map(|h| h.to_string()).collect();
+        for row in std::iter::once(&header_cells[..]).chain(rows.iter().map(|r| &r[..])) {
+            let line: Vec<String> = row.iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect();
+            writeln!(f, "{}", line.join("  ").trim_end())?;
+        }
+
+        let shown_end = self.offset + self.licenses.len();
+        writeln!(f, "Showing {}-{} of {} licenses", (self.offset + 1).min(shown_end), shown_end, self.total)
+    }
+}
+
+/// Lowercase alphanumeric form of an id, so "GPL 2.0", "gpl-2.0" and "GPL_2.0" compare equal
+pub(crate) fn normalize_id(id: &str) -> String {
+    id.chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '+')
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Levenshtein edit distance between two strings
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+impl LicenseDatabase {
+    /// Suggests known license ids for an id that is unknown or written in a non-canonical form
+    ///
+    /// Case-insensitive and punctuation-insensitive matches come first, followed by
+    /// ids within a small edit distance, closest first.
+    pub fn suggest_license_ids(&self, id: &str, max_suggestions: usize) -> Vec<String> {
+        if let Some(license) = self.get(id) {
+            return if license.name == id { Vec::new() } else { vec![license.name] };
+        }
+
+        let normalized = normalize_id(id);
+        let max_distance = (normalized.len() / 5).max(1);
+        let mut scored: Vec<(usize, String)> = Vec::new();
+        for (key, license) in self.store().entries().into_iter()
+            .chain(self.overlay().entries().map(|(key, license)| (key.clone(), license.clone())))
+        {
+            let distance = edit_distance(&normalized, &normalize_id(&key));
+            if distance <= max_distance && !scored.iter().any(|(_, name)| *name == license.name) {
+                scored.push((distance, license.name));
+            }
+        }
+        scored.sort();
+
+        scored.into_iter()
+            .take(max_suggestions)
+            .map(|(_, name)| name)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::index_path;
+
+    fn database() -> LicenseDatabase {
+        LicenseDatabase::open::<&std::path::Path>(&index_path(), &[]).unwrap()
+    }
+
+    #[test]
+    fn globs_match_case_insensitively() {
+        assert!(glob_matches("gpl-*-only", "gpl-3.0-only"));
+        assert!(glob_matches("?pl-2.0*", "lpl-2.0-or-later"));
+        assert!(glob_matches("*", ""));
+        assert!(!glob_matches("gpl-?.0", "gpl-10.0"));
+        assert!(IdPattern::Glob("MIT*".to_string()).matches("mit-0"));
+    }
+
+    #[test]
+    fn query_filters_sorts_and_paginates() {
+        let database = database();
+        let query = LicenseQuery {
+            copyleft_strengths: vec![NewCopyleftStrength::Copyleft],
+            id_pattern: Some(IdPattern::Glob("GPL-*".to_string())),
+            is_exception: Some(false),
+            limit: Some(2),
+            ..LicenseQuery::default()
+        };
+        let result = database.query(&query);
+        assert!(result.total > 2);
+        assert_eq!(result.licenses.len(), 2);
+        assert!(result.licenses.iter().all(|r| r.copyleft_strength == NewCopyleftStrength::Copyleft));
+        assert!(result.licenses[0].id.to_lowercase() <= result.licenses[1].id.to_lowercase());
+
+        let next = database.query(&LicenseQuery { offset: 2, ..query.clone() });
+        assert_eq!(next.total, result.total);
+        assert_ne!(next.licenses[0].id, result.licenses[1].id);
+        assert!(next.to_string().ends_with(&format!("Showing 3-4 of {} licenses\n", result.total)));
+
+        let descending = database.query(&LicenseQuery { descending: true, limit: None, ..query });
+        assert_eq!(descending.licenses.last().unwrap().id, result.licenses[0].id);
+    }
+
+    #[test]
+    fn regex_patterns_match_aliases() {
+        let query = LicenseQuery { id_pattern: Some(IdPattern::Regex(Regex::new("^bsd-new$").unwrap())), ..LicenseQuery::default() };
+        let result = database().query(&query);
+        assert_eq!(result.licenses.len(), 1);
+        assert_eq!(result.licenses[0].id, "BSD-3-Clause");
+        assert!(result.licenses[0].aliases.contains(&"bsd-new".to_string()));
+    }
+
+    #[test]
+    fn suggests_close_license_ids() {
+        let database = database();
+        assert_eq!(database.suggest_license_ids("apache 2.0", 3).first().map(String::as_str), Some("Apache-2.0"));
+        assert_eq!(database.suggest_license_ids("Apache-2.0", 3), Vec::<String>::new());
+        assert!(database.suggest_license_ids("zzzz-unknown-license-zzzz", 3).is_empty());
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(normalize_id("GPL_2.0+"), "gpl20+");
+    }
+}
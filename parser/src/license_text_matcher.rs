@@ -0,0 +1,332 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::sync::OnceLock;
+use regex::Regex;
+use crate::license_database::{LicenseDatabase, LicenseDatabaseError};
+
+/// Default directory holding ScanCode `<license_key>.LICENSE` text files
+pub const DEFAULT_LICENSE_TEXTS_DIR: &str = "licenses";
+
+/// Number of consecutive words per shingle
+const SHINGLE_SIZE: usize = 3;
+/// Minimum share of a license text that must be present for it to count as detected
+const DETECTION_THRESHOLD: f64 = 0.8;
+
+/// A four-digit year from 1900 to 2099
+static YEAR: OnceLock<Regex> = OnceLock::new();
+
+/// A reference license text, reduced to its normalized word shingles
+#[derive(Debug, Clone)]
+struct ReferenceText {
+    license_key: String,
+    license_id: String,
+    shingles: HashSet<u64>,
+}
+
+/// Known license texts to identify unlabeled license files against
+#[derive(Debug, Clone, Default)]
+pub struct LicenseTextCorpus {
+    texts: Vec<ReferenceText>,
+}
+
+/// A candidate license for a given text
+#[derive(Debug, Clone)]
+pub struct TextMatch {
+    pub license_id: String,
+    pub license_key: String,
+    /// Overall similarity between the two texts (Dice coefficient over word shingles)
+    pub confidence: f64,
+    /// Share of the reference license text found in the input
+    pub coverage: f64,
+}
+
+/// Result of identifying a license text
+#[derive(Debug, Clone)]
+pub struct TextIdentification {
+    /// Best candidates, highest confidence first
+    pub candidates: Vec<TextMatch>,
+    /// Licenses detected as present in the text; more than one for combined texts
+    pub detected: Vec<TextMatch>,
+    /// Share of the input not explained by any detected license, e.g. an appended notice
+    pub unmatched_ratio: f64,
+}
+
+/// Strips the YAML front matter ScanCode puts at the top of .LICENSE files
+pub(crate) fn strip_front_matter(text: &str) -> &str {
+    let Some(rest) = text.strip_prefix("---") else {
+        return text;
+    };
+    match rest.find("\n---") {
+        Some(end) => &rest[end + 4..],
+        None => text,
+    }
+}
+
+/// Whether a line, without leading comment markers, is a copyright statement
+///
+/// A leading "(c)" alone is also how license texts number clauses ("(c) You must retain ..."),
+/// so the line must also carry a year, a ©, or "Copyright (c)".
+pub(crate) fn is_copyright_line(text: &str) -> bool {
+    let lower = text.to_lowercase();
+    let marked = lower.starts_with("copyright") || lower.starts_with("(c)") || text.starts_with('©');
+    let year = YEAR.get_or_init(|| Regex::new(r"\b(19|20)\d{2}\b").expect("valid regex"));
+    let dated = year.is_match(text) || text.contains('©') || (lower.starts_with("copyright") && lower.contains("(c)"));
+    marked && dated
+}
+
+/// Lowercases, drops copyright lines and punctuation, and splits into words
+pub fn normalize_license_text(text: &str) -> Vec<String> {
+    text.lines()
+        .filter(|line| !is_copyright_line(line.trim_start_matches(|c: char| !c.is_alphanumeric() && c != '©' && c != '(')))
+        .flat_map(|line| {
+            line.split(|c: char| !c.is_alphanumeric())
+                .filter(|word| !word.is_empty())
+                .map(str::to_lowercase)
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+fn shingles(words: &[String]) -> HashSet<u64> {
+    if words.len() < SHINGLE_SIZE {
+        return words.iter().map(|w| hash_words(std::slice::from_ref(w))).collect();
+    }
+    words.windows(SHINGLE_SIZE).map(hash_words).collect()
+}
+
+fn hash_words(words: &[String]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    words.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl LicenseTextCorpus {
+    /// Loads every `<license_key>.LICENSE` file in `texts_dir`, resolving keys to SPDX ids through the database
+    pub fn load(texts_dir: &Path, database: &LicenseDatabase) -> Result<Self, LicenseDatabaseError> {
+        let entries = fs::read_dir(texts_dir)
+            .map_err(|e| LicenseDatabaseError::FileReadError(format!("{}: {}", texts_dir.display(), e)))?;
+
+        let mut corpus = LicenseTextCorpus::default();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("LICENSE") {
+                continue;
+            }
+            let Some(license_key) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let Ok(text) = fs::read_to_string(&path) else {
+                log::warn!("Skipping unreadable license text {}", path.display());
+                continue;
+            };
+            let license_id = database.get(license_key)
+                .map(|l| l.name)
+                .unwrap_or_else(|| license_key.to_string());
+            corpus.add_text(license_key, &license_id, &text);
+        }
+
+        Ok(corpus)
+    }
+
+    /// Adds a reference text for a license
+    pub fn add_text(&mut self, license_key: &str, license_id: &str, text: &str) {
+        let words = normalize_license_text(strip_front_matter(text));
+        if words.is_empty() {
+            return;
+        }
+        self.texts.push(ReferenceText {
+            license_key: license_key.to_string(),
+            license_id: license_id.to_string(),
+            shingles: shingles(&words),
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.texts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.texts.is_empty()
+    }
+
+    /// Scores a license file against all known texts and detects the licenses it contains
+    pub fn identify(&self, text: &str, max_candidates: usize) -> TextIdentification {
+        let input = shingles(&normalize_license_text(text));
+        if input.is_empty() {
+            return TextIdentification {
+                candidates: Vec::new(),
+                detected: Vec::new(),
+                unmatched_ratio: 1.0,
+            };
+        }
+
+        let mut candidates: Vec<TextMatch> = self.texts.iter()
+            .filter_map(|reference| {
+                let shared = reference.shingles.intersection(&input).count();
+                if shared == 0 {
+                    return None;
+                }
+                Some(TextMatch {
+                    license_id: reference.license_id.clone(),
+                    license_key: reference.license_key.clone(),
+                    confidence: 2.0 * shared as f64 / (reference.shingles.len() + input.len()) as f64,
+                    coverage: shared as f64 / reference.shingles.len() as f64,
+                })
+            })
+            .collect();
+        candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence).then(b.coverage.total_cmp(&a.coverage)));
+
+        // Greedily explain the input with the licenses covering it best; what remains is unmatched text
+        let mut remaining = input.clone();
+        let mut detected = Vec::new();
+        let mut by_coverage: Vec<&TextMatch> = candidates.iter()
+            .filter(|m| m.coverage >= DETECTION_THRESHOLD)
+            .collect();
+        by_coverage.sort_by(|a, b| b.coverage.total_cmp(&a.coverage).then(b.confidence.total_cmp(&a.confidence)));
+        for candidate in by_coverage {
+            let Some(reference) = self.texts.iter().find(|r| r.license_key == candidate.license_key) else {
+                continue;
+            };
+            let before = remaining.len();
+            remaining.retain(|s| !reference.shingles.contains(s));
+            // Skip variants of an already detected license that explain nothing new
+            let newly_explained = (before - remaining.len()) as f64 / reference.shingles.len() as f64;
+            if newly_explained >= DETECTION_THRESHOLD / 2.0 && !detected.iter().any(|d: &TextMatch| d.license_id == candidate.license_id) {
+                detected.push(candidate.clone());
+            }
+        }
+
+        candidates.truncate(max_candidates);
+        TextIdentification {
+            candidates,
+            detected,
+            unmatched_ratio: remaining.len() as f64 / input.len() as f64,
+        }
+    }
+}
+
+impl TextIdentification {
+    /// SPDX expression for the detected licenses, combined with AND for combined texts
+    pub fn expression(&self) -> Option<String> {
+        if self.detected.is_empty() {
+            return None;
+        }
+        let ids: Vec<&str> = self.detected.iter().map(|m| m.license_id.as_str()).collect();
+        Some(ids.join(" AND "))
+    }
+
+    /// True if the text contains more than one license, or one license plus substantial extra text
+    pub fn is_combined(&self) -> bool {
+        self.detected.len() > 1 || (self.detected.len() == 1 && self.unmatched_ratio > 1.0 - DETECTION_THRESHOLD)
+    }
+}
+
+impl fmt::Display for TextIdentification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "License Text Identification")?;
+
+        if self.candidates.is_empty() {
+            writeln!(f, "Candidates: None")?;
+        } else {
+            writeln!(f, "Candidates ({}):", self.candidates.len())?;
+            for candidate in &self.candidates {
+                writeln!(f, "  - {} ({}): confidence {:.1}%, coverage {:.1}%",
+                    candidate.license_id, candidate.license_key, candidate.confidence * 100.0, candidate.coverage * 100.0)?;
+            }
+        }
+
+        match self.expression() {
+            Some(expression) => writeln!(f, "Detected: {}", expression)?,
+            None => writeln!(f, "Detected: None (manual review required)")?,
+        }
+        if self.is_combined() {
+            writeln!(f, "Combined Text: yes ({:.1}% of the text not matched by a known license)", self.unmatched_ratio * 100.0)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{index_path, temp_dir, write_file};
+
+    const MIT: &str = "Permission is hereby granted, free of charge, to any person obtaining a copy of this software \
+and associated documentation files (the \"Software\"), to deal in the Software without restriction, including without \
+limitation the rights to use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of the Software, \
+and to permit persons to whom the Software is furnished to do so, subject to the following conditions: The above \
+copyright notice and this permission notice shall be included in all copies or substantial portions of the Software.";
+
+    const ISC: &str = "Permission to use, copy, modify, and/or distribute this software for any purpose with or without \
+fee is hereby granted, provided that the above copyright notice and this permission notice appear in all copies. \
+THE SOFTWARE IS PROVIDED \"AS IS\" AND THE AUTHOR DISCLAIMS ALL WARRANTIES WITH REGARD TO THIS SOFTWARE INCLUDING ALL \
+IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS.";
+
+    fn corpus() -> LicenseTextCorpus {
+        let mut corpus = LicenseTextCorpus::default();
+        corpus.add_text("mit", "MIT", &format!("---\nkey: mit\n---\n{}", MIT));
+        corpus.add_text("isc", "ISC", ISC);
+        corpus
+    }
+
+    #[test]
+    fn normalization_drops_copyright_lines_and_punctuation() {
+        let words = normalize_license_text("Copyright (c) 2020 Jane Doe\n  (C) 2021 Acme\nPermission is GRANTED, freely.");
+        assert_eq!(words, vec!["permission", "is", "granted", "freely"]);
+        let clause = normalize_license_text("      (c) You must retain all notices");
+        assert_eq!(clause, vec!["c", "you", "must", "retain", "all", "notices"]);
+        assert_eq!(strip_front_matter("---\nkey: mit\n---\ntext"), "\ntext");
+        assert_eq!(strip_front_matter("--- no end"), "--- no end");
+    }
+
+    #[test]
+    fn identifies_a_license_despite_reformatting() {
+        let text = format!("Copyright 2021 Jane Doe\n\n{}", MIT.to_uppercase().replace(", ", ",\n"));
+        let identification = corpus().identify(&text, 5);
+        assert_eq!(identification.expression().as_deref(), Some("MIT"));
+        assert_eq!(identification.candidates[0].license_key, "mit");
+        assert!(identification.candidates[0].confidence > 0.95);
+        assert!(!identification.is_combined());
+    }
+
+    #[test]
+    fn detects_combined_texts_and_extra_notices() {
+        let combined = corpus().identify(&format!("{}\n\n{}", MIT, ISC), 5);
+        assert_eq!(combined.expression().as_deref(), Some("MIT AND ISC"));
+        assert!(combined.is_combined());
+
+        let notice = "This product bundles the frobnicator library, which is available under its own terms from the vendor \
+and must not be redistributed separately without written consent of the vendor's legal department.";
+        let with_notice = corpus().identify(&format!("{}\n\n{}", MIT, notice), 5);
+        assert_eq!(with_notice.expression().as_deref(), Some("MIT"));
+        assert!(with_notice.is_combined());
+    }
+
+    #[test]
+    fn unknown_text_needs_manual_review() {
+        let identification = corpus().identify("All rights reserved. Do not copy.", 5);
+        assert!(identification.detected.is_empty());
+        assert!(identification.to_string().contains("manual review required"));
+        assert_eq!(corpus().identify("", 5).unmatched_ratio, 1.0);
+    }
+
+    #[test]
+    fn loads_texts_resolving_keys_to_ids() {
+        let dir = temp_dir("texts");
+        write_file(&dir, "bsd-new.LICENSE", ISC);
+        write_file(&dir, "acme-internal.LICENSE", MIT);
+        write_file(&dir, "README.md", MIT);
+        let database = LicenseDatabase::open::<&Path>(&index_path(), &[]).unwrap();
+        let corpus = LicenseTextCorpus::load(&dir, &database).unwrap();
+        assert_eq!(corpus.len(), 2);
+        assert_eq!(corpus.identify(ISC, 1).expression().as_deref(), Some("BSD-3-Clause"));
+        assert_eq!(corpus.identify(MIT, 1).expression().as_deref(), Some("acme-internal"));
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(LicenseTextCorpus::load(&dir, &database).is_err());
+    }
+}
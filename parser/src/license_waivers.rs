@@ -0,0 +1,345 @@
+use std::cmp::Ordering;
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::inventory::Component;
+use crate::license_database::LicenseDatabaseError;
+use crate::license_policy::{PolicyRule, PolicyViolation};
+use crate::spdx_export::utc_timestamp;
+
+/// An accepted finding: policy violations of a component that do not fail the audit until `expires`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Waiver {
+    /// Component name or id
+    pub component: String,
+    /// Version range such as `>=1.2, <2`, an exact version, or `*`; any version when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// License expression the approval was given for; any expression when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expression: Option<String>,
+    /// Policy rule waived; every rule when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule: Option<PolicyRule>,
+    pub approver: String,
+    pub reason: String,
+    /// Last day (`YYYY-MM-DD`) the waiver applies
+    pub expires: String,
+}
+
+/// A policy violation with the waiver that matched it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WaivedViolation {
+    pub violation: PolicyViolation,
+    pub waiver: Waiver,
+}
+
+#[derive(Debug, Deserialize)]
+struct WaiverFile {
+    #[serde(default)]
+    waivers: Vec<Waiver>,
+}
+
+fn is_date(value: &str) -> bool {
+    let parts: Vec<&str> = value.split('-').collect();
+    parts.len() == 3
+        && [4, 2, 2].iter().zip(&parts).all(|(len, part)| part.len() == *len && part.bytes().all(|b| b.is_ascii_digit()))
+        && (1..=12).contains(&parts[1].parse::<u32>().unwrap_or(0))
+        && (1..=31).contains(&parts[2].parse::<u32>().unwrap_or(0))
+}
+
+/// Loads a waiver baseline from a JSON file with a `waivers` array
+pub fn load_waiver_file(path: &Path) -> Result<Vec<Waiver>, LicenseDatabaseError> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| LicenseDatabaseError::FileReadError(format!("{}: {}", path.display(), e)))?;
+    let file: WaiverFile = serde_json::from_str(&content)
+        .map_err(|e| LicenseDatabaseError::JsonParseError(format!("{}: {}", path.display(), e)))?;
+
+    for waiver in &file.waivers {
+        let invalid = |e: String| LicenseDatabaseError::InvalidPolicy(format!("{}: waiver for {}: {}", path.display(), waiver.component, e));
+        if !is_date(&waiver.expires) {
+            return Err(invalid(format!("expiry '{}' is not a YYYY-MM-DD date", waiver.expires)));
+        }
+        if waiver.approver.trim().is_empty() || waiver.reason.trim().is_empty() {
+            return Err(invalid("approver and reason are required".to_string()));
+        }
+        if let Some(ref range) = waiver.version {
+            parse_range(range).map_err(invalid)?;
+        }
+    }
+    Ok(file.waivers)
+}
+
+/// Current UTC date as `YYYY-MM-DD`
+pub fn today() -> String {
+    utc_timestamp()[..10].to_string()
+}
+
+/// Compares dotted versions part by part, numerically where both parts are numbers
+///
+/// As in semver, `+build` metadata is ignored and a `-pre` release sorts below its release,
+/// so `2.0.0-rc1` is below `2` and `1.0.0-alpha` below `1.0.0-alpha.1`.
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    fn compare_part(x: &str, y: &str) -> Ordering {
+        match (x.parse::<u64>(), y.parse::<u64>()) {
+            (Ok(x), Ok(y)) => x.cmp(&y),
+            (Ok(_), Err(_)) => Ordering::Less,
+            (Err(_), Ok(_)) => Ordering::Greater,
+            _ => x.cmp(y),
+        }
+    }
+    fn split(v: &str) -> (Vec<&str>, Option<Vec<&str>>) {
+        let v = v.trim_start_matches('v');
+        let v = v.split_once('+').map_or(v, |(version, _)| version);
+        let (release, pre) = v.split_once('-').map_or((v, None), |(release, pre)| (release, Some(pre)));
+        (release.split('.').collect(), pre.map(|pre| pre.split('.').collect()))
+    }
+    let ((a, a_pre), (b, b_pre)) = (split(a), split(b));
+    for i in 0..a.len().max(b.len()) {
+        let ordering = compare_part(a.get(i).copied().unwrap_or("0"), b.get(i).copied().unwrap_or("0"));
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    match (a_pre, b_pre) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a), Some(b)) => a.iter().zip(&b)
+            .map(|(x, y)| compare_part(x, y))
+            .find(|ordering| *ordering != Ordering::Equal)
+            .unwrap_or(a.len().cmp(&b.len())),
+    }
+}
+
+/// Splits a range into (operator, version) comparators; `*` yields none
+fn parse_range(range: &str) -> Result<Vec<(&str, &str)>, String> {
+    let mut comparators = Vec::new();
+    for part in range.split(',').map(str::trim).filter(|p| !p.is_empty() && *p != "*") {
+        let split = part.find(|c: char| !matches!(c, '<' | '>' | '=')).unwrap_or(part.len());
+        let (operator, version) = (&part[..split], part[split..].trim());
+        if !matches!(operator, "" | "=" | "==" | "<" | "<=" | ">" | ">=") || !version.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+            return Err(format!("invalid version range '{}'", range));
+        }
+        comparators.push((operator, version));
+    }
+    Ok(comparators)
+}
+
+fn version_matches(range: &str, version: Option<&str>) -> bool {
+    let Ok(comparators) = parse_range(range) else {
+        return false;
+    };
+    if comparators.is_empty() {
+        return true;
+    }
+    let Some(version) = version else {
+        return false;
+    };
+    comparators.iter().all(|(operator, bound)| {
+        let ordering = compare_versions(version, bound);
+        match *operator {
+            "<" => ordering == Ordering::Less,
+            "<=" => ordering != Ordering::Greater,
+            ">" => ordering == Ordering::Greater,
+            ">=" => ordering != Ordering::Less,
+            _ => ordering == Ordering::Equal,
+        }
+    })
+}
+
+/// Expressions compared ignoring case and whitespace
+fn same_expression(a: &str, b: &str) -> bool {
+    let normalize = |e: &str| e.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    normalize(a) == normalize(b)
+}
+
+impl Waiver {
+    /// Whether the waiver covers a violation of a component, regardless of expiry
+    pub fn matches(&self, component: &Component, violation: &PolicyViolation) -> bool {
+        (self.component == component.name || self.component == component.id)
+            && self.version.as_deref().is_none_or(|range| version_matches(range, component.version.as_deref()))
+            && self.expression.as_deref().is_none_or(|expression| {
+                component.expression.as_deref().is_some_and(|e| same_expression(expression, e))
+            })
+            && self.rule.is_none_or(|rule| rule == violation.rule)
+    }
+
+    /// Expired waivers no longer suppress findings; dates compare as `YYYY-MM-DD` strings
+    pub fn is_expired(&self, today: &str) -> bool {
+        self.expires.as_str() < today
+    }
+}
+
+/// Splits violations into those still failing and those waived by an unexpired waiver
+///
+/// Failing violations matched only by expired waivers are returned with those waivers as well.
+pub fn apply_waivers(waivers: &[Waiver], component: &Component, violations: Vec<PolicyViolation>, today: &str)
+    -> (Vec<PolicyViolation>, Vec<WaivedViolation>, Vec<WaivedViolation>)
+{
+    let mut failing = Vec::new();
+    let mut waived = Vec::new();
+    let mut expired = Vec::new();
+    for violation in violations {
+        let matching: Vec<&Waiver> = waivers.iter().filter(|w| w.matches(component, &violation)).collect();
+        if let Some(waiver) = matching.iter().find(|w| !w.is_expired(today)) {
+            waived.push(WaivedViolation { violation, waiver: (*waiver).clone() });
+            continue;
+        }
+        // The most recently expired waiver explains why the finding is back
+        if let Some(waiver) = matching.iter().max_by(|a, b| a.expires.cmp(&b.expires)) {
+            expired.push(WaivedViolation { violation: violation.clone(), waiver: (*waiver).clone() });
+        }
+        failing.push(violation);
+    }
+    (failing, waived, expired)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inventory::DependencyKind;
+    use crate::test_support::{temp_dir, write_file};
+
+    fn waiver(version: Option<&str>, expires: &str) -> Waiver {
+        Waiver {
+            component: "openssl".to_string(),
+            version: version.map(String::from),
+            expression: None,
+            rule: None,
+            approver: "legal".to_string(),
+            reason: "approved for internal use".to_string(),
+            expires: expires.to_string(),
+        }
+    }
+
+    fn violation(rule: PolicyRule) -> PolicyViolation {
+        PolicyViolation { rule, message: "denied".to_string() }
+    }
+
+    fn component(version: &str) -> Component {
+        let mut component = Component::new("openssl@1.2.3", "openssl", Some(version), DependencyKind::Normal);
+        component.expression = Some("Apache-2.0 OR MIT".to_string());
+        component
+    }
+
+    #[test]
+    fn parses_version_ranges() {
+        assert_eq!(parse_range(">=1.2, <2").unwrap(), vec![(">=", "1.2"), ("<", "2")]);
+        assert_eq!(parse_range("1.0.0").unwrap(), vec![("", "1.0.0")]);
+        assert!(parse_range("*").unwrap().is_empty());
+        assert!(parse_range("=>1.0").is_err());
+        assert!(parse_range(">= ").is_err());
+    }
+
+    #[test]
+    fn matches_versions_numerically() {
+        assert!(version_matches(">=1.2, <2", Some("1.10.0")));
+        assert!(!version_matches(">=1.2, <2", Some("2.0")));
+        assert!(!version_matches(">=1.2, <2", Some("1.1.9")));
+        assert!(version_matches("=1.2", Some("v1.2.0")));
+        assert!(version_matches("*", None));
+        assert!(!version_matches("1.2", None));
+        assert!(!version_matches("~1.2", Some("1.2")));
+    }
+
+    #[test]
+    fn pre_releases_sort_below_their_release() {
+        assert!(version_matches("<2", Some("2.0.0-rc1")));
+        assert!(!version_matches(">=2", Some("2.0.0-rc1")));
+        assert!(version_matches(">=2.0.0-rc.1, <2.0.0", Some("2.0.0-rc.2")));
+        assert!(version_matches("=1.2.3", Some("1.2.3+build.7")));
+        assert_eq!(compare_versions("1.0.0-alpha", "1.0.0-alpha.1"), Ordering::Less);
+        assert_eq!(compare_versions("1.0.0-alpha.1", "1.0.0-alpha.beta"), Ordering::Less);
+        assert_eq!(compare_versions("1.0.0-rc.11", "1.0.0-rc.2"), Ordering::Greater);
+    }
+
+    #[test]
+    fn matches_component_expression_and_rule() {
+        let component = component("1.2.3");
+        let mut waiver = waiver(Some(">=1, <2"), "2099-12-31");
+        waiver.expression = Some("apache-2.0  or MIT".to_string());
+        waiver.rule = Some(PolicyRule::DeniedLicense);
+        assert!(waiver.matches(&component, &violation(PolicyRule::DeniedLicense)));
+        assert!(!waiver.matches(&component, &violation(PolicyRule::Conflict)));
+
+        waiver.expression = Some("MIT".to_string());
+        assert!(!waiver.matches(&component, &violation(PolicyRule::DeniedLicense)));
+
+        let mut by_id = self::waiver(None, "2099-12-31");
+        by_id.component = "openssl@1.2.3".to_string();
+        assert!(by_id.matches(&component, &violation(PolicyRule::FailOn)));
+        assert!(!self::waiver(Some("<1"), "2099-12-31").matches(&component, &violation(PolicyRule::FailOn)));
+    }
+
+    #[test]
+    fn expires_after_the_last_day() {
+        let waiver = waiver(None, "2024-06-30");
+        assert!(!waiver.is_expired("2024-06-30"));
+        assert!(waiver.is_expired("2024-07-01"));
+    }
+
+    #[test]
+    fn applies_unexpired_waivers_and_reports_expired_ones() {
+        let component = component("1.2.3");
+        let violations = vec![violation(PolicyRule::DeniedLicense), violation(PolicyRule::FailOn)];
+
+        let mut current = waiver(None, "2024-12-31");
+        current.rule = Some(PolicyRule::DeniedLicense);
+        let older = waiver(None, "2023-01-31");
+        let newer = waiver(None, "2024-01-31");
+        let (failing, waived, expired) =
+            apply_waivers(&[older, current.clone(), newer.clone()], &component, violations, "2024-06-01");
+
+        assert_eq!(waived.len(), 1);
+        assert_eq!(waived[0].waiver, current);
+        assert_eq!(failing.len(), 1);
+        assert_eq!(failing[0].rule, PolicyRule::FailOn);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].violation.rule, PolicyRule::FailOn);
+        assert_eq!(expired[0].waiver, newer);
+    }
+
+    #[test]
+    fn unmatched_violations_stay_failing() {
+        let (failing, waived, expired) =
+            apply_waivers(&[waiver(Some("<1"), "2099-12-31")], &component("1.2.3"), vec![violation(PolicyRule::Conflict)], "2024-06-01");
+        assert_eq!(failing.len(), 1);
+        assert!(waived.is_empty());
+        assert!(expired.is_empty());
+    }
+
+    #[test]
+    fn loads_and_validates_waiver_files() {
+        let dir = temp_dir("license_waivers");
+        let valid = write_file(&dir, "valid.json", r#"{"waivers": [{
+            "component": "openssl", "version": ">=1, <2", "rule": "denied-license",
+            "approver": "legal", "reason": "approved", "expires": "2024-12-31"}]}"#);
+        let waivers = load_waiver_file(&valid).unwrap();
+        assert_eq!(waivers.len(), 1);
+        assert_eq!(waivers[0].rule, Some(PolicyRule::DeniedLicense));
+        assert!(load_waiver_file(&write_file(&dir, "empty.json", "{}")).unwrap().is_empty());
+
+        let cases = [
+            ("date.json", r#"{"waivers": [{"component": "a", "approver": "x", "reason": "y", "expires": "2024-13-01"}]}"#),
+            ("approver.json", r#"{"waivers": [{"component": "a", "approver": " ", "reason": "y", "expires": "2024-12-01"}]}"#),
+            ("range.json", r#"{"waivers": [{"component": "a", "version": "~1", "approver": "x", "reason": "y", "expires": "2024-12-01"}]}"#),
+        ];
+        for (name, content) in cases {
+            let result = load_waiver_file(&write_file(&dir, name, content));
+            assert!(matches!(result, Err(LicenseDatabaseError::InvalidPolicy(_))), "{}", name);
+        }
+        assert!(matches!(
+            load_waiver_file(&write_file(&dir, "broken.json", "{")),
+            Err(LicenseDatabaseError::JsonParseError(_))
+        ));
+        assert!(matches!(load_waiver_file(&dir.join("missing.json")), Err(LicenseDatabaseError::FileReadError(_))));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn today_is_a_date() {
+        assert!(is_date(&today()));
+        assert!(!is_date("2024-6-01"));
+    }
+}
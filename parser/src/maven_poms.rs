@@ -0,0 +1,375 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use regex::Regex;
+use roxmltree::{Document, Node};
+use crate::inventory::{any_of_licenses, resolve_kinds, Component, DependencyKind, Inventory, InventoryError};
+use crate::license_database::LicenseDatabase;
+use crate::license_names::{LicenseNameMatch, NameMatchMethod};
+use crate::source_scanner::collect_files;
+
+/// Parents are followed this many levels up for inherited licenses and properties
+const MAX_PARENT_DEPTH: usize = 10;
+
+static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+
+#[derive(Debug, Default)]
+struct PomLicense {
+    name: Option<String>,
+    url: Option<String>,
+}
+
+#[derive(Debug)]
+struct PomDependency {
+    group_id: String,
+    artifact_id: String,
+    version: Option<String>,
+    scope: Option<String>,
+}
+
+/// The parts of a POM the audit needs
+#[derive(Debug, Default)]
+struct Pom {
+    path: PathBuf,
+    group_id: String,
+    artifact_id: String,
+    version: String,
+    packaging: String,
+    parent: Option<(String, String, String)>,
+    licenses: Vec<PomLicense>,
+    dependencies: Vec<PomDependency>,
+    properties: HashMap<String, String>,
+}
+
+impl Pom {
+    fn coordinates(&self) -> String {
+        format!("{}:{}:{}", self.group_id, self.artifact_id, self.version)
+    }
+
+    /// Replaces `${project.version}`, `${project.groupId}` and `${project.artifactId}` references,
+    /// and references to `properties`, which include those inherited from parents
+    fn interpolate(&self, value: &str, properties: &HashMap<String, String>) -> String {
+        let placeholder = PLACEHOLDER.get_or_init(|| Regex::new(r"\$\{([^}]+)\}").expect("valid regex"));
+        placeholder.replace_all(value, |captures: &regex::Captures| {
+            let key = &captures[1];
+            match key {
+                "project.version" | "pom.version" | "version" => self.version.clone(),
+                "project.groupId" | "pom.groupId" => self.group_id.clone(),
+                "project.artifactId" => self.artifact_id.clone(),
+                _ => properties.get(key).cloned().unwrap_or_else(|| captures[0].to_string()),
+            }
+        }).into_owned()
+    }
+
+    /// A dependency of this POM with its coordinates interpolated
+    fn resolve_dependency(&self, dependency: &PomDependency, properties: &HashMap<String, String>) -> PomDependency {
+        PomDependency {
+            group_id: self.interpolate(&dependency.group_id, properties),
+            artifact_id: self.interpolate(&dependency.artifact_id, properties),
+            version: dependency.version.as_ref().map(|v| self.interpolate(v, properties)),
+            scope: dependency.scope.clone(),
+        }
+    }
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.is_element() && n.tag_name().name() == name)
+}
+
+fn child_text(node: Node, name: &str) -> Option<String> {
+    child(node, name).and_then(|n| n.text()).map(str::trim).filter(|t| !t.is_empty()).map(String::from)
+}
+
+fn read_pom(path: &Path) -> Result<Pom, InventoryError> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| InventoryError::FileReadError(format!("{}: {}", path.display(), e)))?;
+    let document = Document::parse(&content)
+        .map_err(|e| InventoryError::ParseError(format!("{}: {}", path.display(), e)))?;
+    let project = document.root_element();
+    if project.tag_name().name() != "project" {
+        return Err(InventoryError::ParseError(format!("{}: not a Maven POM", path.display())));
+    }
+
+    let parent = child(project, "parent").and_then(|parent| {
+        Some((child_text(parent, "groupId")?, child_text(parent, "artifactId")?, child_text(parent, "version")?))
+    });
+    let mut pom = Pom {
+        path: path.to_path_buf(),
+        // groupId and version may be inherited from the parent
+        group_id: child_text(project, "groupId").or_else(|| parent.as_ref().map(|p| p.0.clone())).unwrap_or_default(),
+        artifact_id: child_text(project, "artifactId").unwrap_or_default(),
+        version: child_text(project, "version").or_else(|| parent.as_ref().map(|p| p.2.clone())).unwrap_or_default(),
+        packaging: child_text(project, "packaging").unwrap_or_else(|| "jar".to_string()),
+        parent,
+        ..Pom::default()
+    };
+
+    if let Some(properties) = child(project, "properties") {
+        for property in properties.children().filter(Node::is_element) {
+            pom.properties.insert(property.tag_name().name().to_string(), property.text().unwrap_or_default().trim().to_string());
+        }
+    }
+    for license in child(project, "licenses").into_iter().flat_map(|l| l.children()).filter(|n| n.has_tag_name("license")) {
+        pom.licenses.push(PomLicense {
+            name: child_text(license, "name"),
+            url: child_text(license, "url"),
+        });
+    }
+
+    pom.dependencies = child(project, "dependencies").into_iter()
+        .flat_map(|d| d.children())
+        .filter(|n| n.has_tag_name("dependency"))
+        .filter_map(|dependency| Some(PomDependency {
+            group_id: child_text(dependency, "groupId")?,
+            artifact_id: child_text(dependency, "artifactId")?,
+            version: child_text(dependency, "version"),
+            scope: child_text(dependency, "scope"),
+        }))
+        .collect();
+    Ok(pom)
+}
+
+fn dependency_kind(scope: Option<&str>) -> DependencyKind {
+    match scope {
+        Some("test") => DependencyKind::Dev,
+        Some("provided") => DependencyKind::Build,
+        _ => DependencyKind::Normal,
+    }
+}
+
+/// Database ids that name a license family rather than one version of it
+const GENERIC_LICENSE_IDS: &[&str] = &["GPL", "LGPL", "AGPL", "BSD", "Apache", "MPL", "EPL", "CDDL", "Artistic", "GFDL"];
+
+/// Maps one `<license>` entry, preferring an exact SPDX name, then the URL, then a heuristic name match
+/// A family name such as `GPL` only wins when the URL doesn't pin a version
+fn map_pom_license(database: &LicenseDatabase, license: &PomLicense) -> Option<LicenseNameMatch> {
+    let by_name = license.name.as_deref().and_then(|name| database.map_license_name(name));
+    if let Some(ref matched) = by_name {
+        let generic = GENERIC_LICENSE_IDS.iter().any(|id| id.eq_ignore_ascii_case(&matched.id));
+        if matched.method == NameMatchMethod::Exact && !generic {
+            return by_name;
+        }
+    }
+    license.url.as_deref().and_then(|url| database.map_license_url(url)).or(by_name)
+}
+
+/// Records the `<licenses>` of a POM on its component; several entries are read as OR (see `any_of_licenses`)
+fn set_maven_license(component: &mut Component, database: &LicenseDatabase, licenses: &[PomLicense]) {
+    if licenses.is_empty() {
+        return;
+    }
+    component.declared_license = Some(licenses.iter()
+        .filter_map(|l| l.name.clone().or_else(|| l.url.clone()))
+        .collect::<Vec<_>>()
+        .join("; "));
+
+    let mut ids: Vec<String> = Vec::new();
+    for license in licenses {
+        let label = license.name.as_deref().or(license.url.as_deref()).unwrap_or("<license>");
+        match map_pom_license(database, license) {
+            Some(matched) => {
+                if matched.method != NameMatchMethod::Exact {
+                    component.notes.push(format!("'{}' read as {} ({})", label, matched.id, matched.method));
+                }
+                if !ids.contains(&matched.id) {
+                    ids.push(matched.id);
+                }
+            }
+            None => component.notes.push(format!("License '{}' not recognized", label)),
+        }
+    }
+
+    if licenses.len() > 1 {
+        component.notes.push("Several <license> entries read as OR".to_string());
+    }
+    component.expression = any_of_licenses(&ids);
+}
+
+/// Builds an inventory from the POMs below a directory, such as a local `~/.m2/repository`
+///
+/// `pom.xml` files are the project itself, `*.pom` files are artifacts. POMs without `<licenses>`
+/// inherit them from their parent when the parent POM is present. Parent and BOM POMs
+/// (`<packaging>pom</packaging>`) are not reported unless they are project POMs.
+pub fn inventory_from_maven_poms(database: &LicenseDatabase, dir: &Path) -> Result<Inventory, InventoryError> {
+    if !dir.is_dir() {
+        return Err(InventoryError::FileReadError(format!("{}: not a directory", dir.display())));
+    }
+    let mut files = Vec::new();
+    collect_files(dir, &[], &mut files);
+
+    let mut poms: Vec<Pom> = Vec::new();
+    for path in files {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        if name != "pom.xml" && !name.ends_with(".pom") {
+            continue;
+        }
+        match read_pom(&path) {
+            Ok(pom) => poms.push(pom),
+            Err(e) => log::warn!("Skipping POM: {}", e),
+        }
+    }
+
+    let index: HashMap<String, usize> = poms.iter().enumerate().map(|(i, pom)| (pom.coordinates(), i)).collect();
+    let find = |group_id: &str, artifact_id: &str, version: Option<&str>| -> Option<&Pom> {
+        if let Some(version) = version {
+            if let Some(&i) = index.get(&format!("{}:{}:{}", group_id, artifact_id, version)) {
+                return Some(&poms[i]);
+            }
+        }
+        // Managed or ranged versions: take any version that is present
+        poms.iter().find(|p| p.group_id == group_id && p.artifact_id == artifact_id)
+    };
+
+    let is_project = |pom: &Pom| pom.path.file_name().is_some_and(|n| n == "pom.xml");
+    let roots: Vec<String> = poms.iter().filter(|p| is_project(p)).map(Pom::coordinates).collect();
+
+    let parent_of = |pom: &Pom| pom.parent.as_ref().and_then(|(g, a, v)| find(g, a, Some(v)));
+
+    let mut edges: HashMap<String, Vec<(String, DependencyKind)>> = HashMap::new();
+    for pom in &poms {
+        // Properties of nearer POMs override those of their parents
+        let mut properties: HashMap<String, String> = HashMap::new();
+        let mut declaring = Some(pom);
+        for _ in 0..MAX_PARENT_DEPTH {
+            let Some(current) = declaring else {
+                break;
+            };
+            for (key, value) in &current.properties {
+                properties.entry(key.clone()).or_insert_with(|| value.clone());
+            }
+            declaring = parent_of(current);
+        }
+
+        let pom_edges = edges.entry(pom.coordinates()).or_default();
+        for dependency in pom.dependencies.iter().map(|d| pom.resolve_dependency(d, &properties)) {
+            match find(&dependency.group_id, &dependency.artifact_id, dependency.version.as_deref()) {
+                Some(target) => pom_edges.push((target.coordinates(), dependency_kind(dependency.scope.as_deref()))),
+                None => log::debug!("Dependency {}:{} of {} is not in the repository", dependency.group_id, dependency.artifact_id, pom.coordinates()),
+            }
+        }
+    }
+    let kinds = resolve_kinds(&roots, &edges);
+
+    let components = poms.iter()
+        .filter(|pom| pom.packaging != "pom" || is_project(pom))
+        .map(|pom| {
+            let id = pom.coordinates();
+            let name = format!("{}:{}", pom.group_id, pom.artifact_id);
+            let kind = kinds.get(&id).copied().unwrap_or(DependencyKind::Normal);
+            let mut component = Component::new(&id, &name, Some(&pom.version), kind);
+            component.dependencies = edges.get(&id).into_iter().flatten().map(|(d, _)| d.clone()).collect();
+
+            let mut declaring = pom;
+            for _ in 0..MAX_PARENT_DEPTH {
+                if !declaring.licenses.is_empty() {
+                    break;
+                }
+                match parent_of(declaring) {
+                    Some(parent) => declaring = parent,
+                    None => break,
+                }
+            }
+            if !std::ptr::eq(declaring, pom) && !declaring.licenses.is_empty() {
+                component.notes.push(format!("Licenses inherited from parent {}", declaring.coordinates()));
+            }
+            set_maven_license(&mut component, database, &declaring.licenses);
+            component
+        })
+        .collect();
+
+    Ok(Inventory {
+        source: dir.display().to_string(),
+        roots,
+        components,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{parser, temp_dir, write_file};
+
+    fn pom_license(name: Option<&str>, url: Option<&str>) -> PomLicense {
+        PomLicense { name: name.map(String::from), url: url.map(String::from) }
+    }
+
+    fn mapped(name: Option<&str>, url: Option<&str>) -> Option<String> {
+        map_pom_license(&parser().database(), &pom_license(name, url)).map(|m| m.id)
+    }
+
+    #[test]
+    fn versioned_url_wins_over_family_name() {
+        assert_eq!(mapped(Some("GPL"), Some("https://www.gnu.org/licenses/old-licenses/gpl-2.0.html")).as_deref(), Some("GPL-2.0-only"));
+        assert_eq!(mapped(Some("GPL"), None).as_deref(), Some("GPL"));
+        assert_eq!(mapped(Some("MIT"), Some("https://www.apache.org/licenses/LICENSE-2.0")).as_deref(), Some("MIT"));
+        assert_eq!(mapped(Some("The Apache Software License, Version 2.0"), Some("http://www.apache.org/licenses/LICENSE-2.0.txt")).as_deref(), Some("Apache-2.0"));
+    }
+
+    #[test]
+    fn unversioned_gnu_urls_map_to_any_version() {
+        assert_eq!(mapped(None, Some("https://www.gnu.org/licenses/gpl.html")).as_deref(), Some("GPL-1.0-or-later"));
+        assert_eq!(mapped(None, Some("http://www.gnu.org/licenses/lgpl.txt")).as_deref(), Some("LGPL-2.0-or-later"));
+    }
+
+    fn pom(coordinates: (&str, &str, &str), extra: &str) -> String {
+        format!(
+            "<project xmlns=\"http://maven.apache.org/POM/4.0.0\"><groupId>{}</groupId><artifactId>{}</artifactId><version>{}</version>{}</project>",
+            coordinates.0, coordinates.1, coordinates.2, extra
+        )
+    }
+
+    #[test]
+    fn inventory_with_parent_licenses_and_scopes() {
+        let dir = temp_dir("maven");
+        write_file(&dir, "pom.xml", &pom(("com.acme", "app", "1.0"), "<properties><lib.version>2.0</lib.version></properties>\
+            <licenses><license><name>Apache-2.0</name></license></licenses>\
+            <dependencies>\
+            <dependency><groupId>org.lib</groupId><artifactId>lib</artifactId><version>${lib.version}</version></dependency>\
+            <dependency><groupId>org.test</groupId><artifactId>junit</artifactId><version>4.13</version><scope>test</scope></dependency>\
+            </dependencies>"));
+        write_file(&dir, "repo/org/lib/lib/2.0/lib-2.0.pom", &pom(("org.lib", "lib", "2.0"),
+            "<parent><groupId>org.lib</groupId><artifactId>parent</artifactId><version>1</version></parent>"));
+        write_file(&dir, "repo/org/lib/parent/1/parent-1.pom", &pom(("org.lib", "parent", "1"),
+            "<packaging>pom</packaging><licenses><license><name>MIT License</name></license><license><name>ACME EULA</name></license></licenses>"));
+        write_file(&dir, "repo/org/test/junit/4.13/junit-4.13.pom", &pom(("org.test", "junit", "4.13"),
+            "<licenses><license><name>Eclipse Public License 1.0</name><url>http://www.eclipse.org/legal/epl-v10.html</url></license></licenses>"));
+
+        let inventory = inventory_from_maven_poms(&parser().database(), &dir).unwrap();
+        let component = |id: &str| inventory.components.iter().find(|c| c.id == id).unwrap();
+        assert_eq!(inventory.roots, vec!["com.acme:app:1.0"]);
+        assert!(inventory.components.iter().all(|c| c.id != "org.lib:parent:1"));
+
+        let lib = component("org.lib:lib:2.0");
+        assert_eq!(lib.kind, DependencyKind::Normal);
+        assert_eq!(lib.expression.as_deref(), Some("MIT"));
+        assert!(lib.notes.iter().any(|n| n.contains("inherited from parent org.lib:parent:1")));
+        assert!(lib.notes.iter().any(|n| n.contains("'ACME EULA' not recognized")));
+
+        let junit = component("org.test:junit:4.13");
+        assert_eq!(junit.kind, DependencyKind::Dev);
+        assert_eq!(junit.expression.as_deref(), Some("EPL-1.0"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn dependencies_interpolate_artifact_ids_and_parent_properties() {
+        let dir = temp_dir("maven-properties");
+        write_file(&dir, "parent/pom.xml", &pom(("com.acme", "parent", "1"), "<packaging>pom</packaging>\
+            <properties><scala.binary>2.13</scala.binary><json.version>3.1</json.version><lib.version>1.0</lib.version></properties>"));
+        write_file(&dir, "app/pom.xml", &pom(("com.acme", "app", "1"), "<parent><groupId>com.acme</groupId><artifactId>parent</artifactId><version>1</version></parent>\
+            <properties><lib.version>2.0</lib.version></properties>\
+            <dependencies>\
+            <dependency><groupId>org.json</groupId><artifactId>json_${scala.binary}</artifactId><version>${json.version}</version></dependency>\
+            <dependency><groupId>org.lib</groupId><artifactId>lib</artifactId><version>${lib.version}</version></dependency>\
+            </dependencies>"));
+        write_file(&dir, "repo/json_2.13-3.1.pom", &pom(("org.json", "json_2.13", "3.1"), "<licenses><license><name>MIT</name></license></licenses>"));
+        write_file(&dir, "repo/lib-1.0.pom", &pom(("org.lib", "lib", "1.0"), ""));
+        write_file(&dir, "repo/lib-2.0.pom", &pom(("org.lib", "lib", "2.0"), ""));
+
+        let inventory = inventory_from_maven_poms(&parser().database(), &dir).unwrap();
+        let app = inventory.components.iter().find(|c| c.id == "com.acme:app:1").unwrap();
+        assert_eq!(app.dependencies, vec!["org.json:json_2.13:3.1", "org.lib:lib:2.0"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,7 +1,9 @@
 use std::fmt;
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
 use crate::license_database::NewLicense;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum SpdxExpr {
     License(String),
     And(Box<SpdxExpr>, Box<SpdxExpr>),
@@ -9,8 +11,59 @@ pub enum SpdxExpr {
     With(Box<SpdxExpr>, String), // License WITH exception
 }
 
+impl SpdxExpr {
+    /// Combines expressions with AND; returns None for an empty list
+    pub fn and_all(exprs: Vec<SpdxExpr>) -> Option<SpdxExpr> {
+        exprs.into_iter().reduce(|left, right| SpdxExpr::And(Box::new(left), Box::new(right)))
+    }
+
+    /// Combines expressions with OR; returns None for an empty list
+    pub fn or_all(exprs: Vec<SpdxExpr>) -> Option<SpdxExpr> {
+        exprs.into_iter().reduce(|left, right| SpdxExpr::Or(Box::new(left), Box::new(right)))
+    }
+
+    /// All license ids referenced by the expression, excluding WITH exceptions
+    pub fn license_ids(&self) -> Vec<&str> {
+        match self {
+            SpdxExpr::License(id) => vec![id.as_str()],
+            SpdxExpr::And(left, right) | SpdxExpr::Or(left, right) => {
+                let mut ids = left.license_ids();
+                ids.extend(right.license_ids());
+                ids
+            }
+            SpdxExpr::With(license, _) => license.license_ids(),
+        }
+    }
+}
+
+/// Formats the expression as SPDX text, adding parentheses only where precedence requires them
+impl fmt::Display for SpdxExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpdxExpr::License(id) => write!(f, "{}", id),
+            SpdxExpr::Or(left, right) => write!(f, "{} OR {}", left, right),
+            SpdxExpr::And(left, right) => {
+                for (i, side) in [left, right].into_iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " AND ")?;
+                    }
+                    match side.as_ref() {
+                        SpdxExpr::Or(..) => write!(f, "({})", side)?,
+                        _ => write!(f, "{}", side)?,
+                    }
+                }
+                Ok(())
+            }
+            SpdxExpr::With(license, exception) => match license.as_ref() {
+                SpdxExpr::License(_) => write!(f, "{} WITH {}", license, exception),
+                _ => write!(f, "({}) WITH {}", license, exception),
+            },
+        }
+    }
+}
+
 /// New copyleft strength categories based on detailed license classifications
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum NewCopyleftStrength {
     /// Contributor License Agreement (CLA)
     /// Describes contribution acceptance rules for software projects
@@ -57,7 +110,7 @@ pub enum NewCopyleftStrength {
     UnstatedLicense,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RiskLevel {
     Low,
     Medium,
@@ -66,7 +119,7 @@ pub enum RiskLevel {
     Unknown,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LicenseAnalysis {
     pub original_expression: String,
     pub parsed_expression: Option<SpdxExpr>,
@@ -76,6 +129,9 @@ pub struct LicenseAnalysis {
     pub risk_level: RiskLevel,
     pub compliance_notes: Vec<String>,
     pub conflicts: Vec<String>,
+    /// Label of the license database version the analysis ran against
+    #[serde(default)]
+    pub database_version: Option<String>,
 }
 
 impl fmt::Display for LicenseAnalysis {
@@ -84,6 +140,10 @@ impl fmt::Display for LicenseAnalysis {
         writeln!(f, "Risk Level: {}", self.risk_level)?;
         writeln!(f, "Strongest Copyleft: {}", self.strongest_copyleft)?;
 
+        if let Some(ref version) = self.database_version {
+            writeln!(f, "Database Version: {}", version)?;
+        }
+
         if let Some(ref expr) = self.parsed_expression {
             writeln!(f, "Parsed Expression: {:?}", expr)?;
         }
@@ -91,7 +151,7 @@ impl fmt::Display for LicenseAnalysis {
         if !self.possible_licenses.is_empty() {
             writeln!(f, "Possible Licenses ({}):", self.possible_licenses.len())?;
             for license in &self.possible_licenses {
-                writeln!(f, "  - {} ({})", license.id, license.copyleft_strength)?;
+                writeln!(f, "  - {} ({}) [{}]", license.id, license.copyleft_strength, license.source)?;
             }
         } else {
             writeln!(f, "Possible Licenses: None (CONFLICT)")?;
@@ -149,6 +209,60 @@ impl fmt::Display for NewCopyleftStrength {
     }
 }
 
+impl FromStr for NewCopyleftStrength {
+    type Err = String;
+
+    /// Accepts ScanCode category names ("Copyleft Limited") as well as variant names ("CopyleftLimited", "copyleft-limited")
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalized: String = s.chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+
+        match normalized.as_str() {
+            "cla" | "contributorlicenseagreement" => Ok(NewCopyleftStrength::CLA),
+            "commercial" => Ok(NewCopyleftStrength::Commercial),
+            "copyleft" => Ok(NewCopyleftStrength::Copyleft),
+            "copyleftlimited" => Ok(NewCopyleftStrength::CopyleftLimited),
+            "freerestricted" => Ok(NewCopyleftStrength::FreeRestricted),
+            "patentlicense" => Ok(NewCopyleftStrength::PatentLicense),
+            "permissive" => Ok(NewCopyleftStrength::Permissive),
+            "proprietaryfree" => Ok(NewCopyleftStrength::ProprietaryFree),
+            "publicdomain" => Ok(NewCopyleftStrength::PublicDomain),
+            "sourceavailable" => Ok(NewCopyleftStrength::SourceAvailable),
+            "unstatedlicense" => Ok(NewCopyleftStrength::UnstatedLicense),
+            _ => Err(format!("Unknown copyleft category: {}", s)),
+        }
+    }
+}
+
+impl FromStr for RiskLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "low" => Ok(RiskLevel::Low),
+            "medium" => Ok(RiskLevel::Medium),
+            "high" => Ok(RiskLevel::High),
+            "critical" => Ok(RiskLevel::Critical),
+            "unknown" => Ok(RiskLevel::Unknown),
+            _ => Err(format!("Unknown risk level: {}", s)),
+        }
+    }
+}
+
+/// Returns a numeric value representing the severity of a RiskLevel
+/// Unknown ranks between High and Critical, since an unidentified license needs review before use
+pub fn risk_level_order(risk: &RiskLevel) -> u8 {
+    match risk {
+        RiskLevel::Low => 0,
+        RiskLevel::Medium => 1,
+        RiskLevel::High => 2,
+        RiskLevel::Unknown => 3,
+        RiskLevel::Critical => 4,
+    }
+}
+
 /// Returns a numeric value representing the strength order of NewCopyleftStrength variants
 /// Higher values indicate stronger copyleft requirements
 /// Ordered by risk level from highest (avoid) to lowest (safe)
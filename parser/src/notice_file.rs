@@ -0,0 +1,404 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Write as _};
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use crate::inventory::DependencyKind;
+use crate::license_audit::{AuditReport, ComponentAudit};
+use crate::license_expression_parser::LicenseExpressionParser;
+use crate::license_text_matcher::{is_copyright_line, strip_front_matter};
+use crate::models::SpdxExpr;
+use crate::source_scanner::{collect_files, extract_tag_value, read_text_file};
+use crate::spdx_export::concluded_expression;
+
+/// Directories holding other packages or build output, not the component's own sources
+const SKIPPED_DIRS: [&str; 4] = ["node_modules", "target", "vendor", "site-packages"];
+
+/// Placeholders of license templates, which are not actual copyright statements
+const COPYRIGHT_PLACEHOLDERS: [&str; 5] = ["yyyy", "<year>", "[year]", "name of copyright owner", "<copyright holder"];
+
+/// Copyright lines longer than this are taken for minified code and skipped
+const MAX_COPYRIGHT_LINE: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NoticeFormat {
+    #[default]
+    Text,
+    Markdown,
+    Html,
+}
+
+impl NoticeFormat {
+    /// Format matching a file extension: `.md` is Markdown, `.html`/`.htm` HTML, anything else text
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()).map(str::to_lowercase).as_deref() {
+            Some("md" | "markdown") => NoticeFormat::Markdown,
+            Some("html" | "htm") => NoticeFormat::Html,
+            _ => NoticeFormat::Text,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NoticeOptions {
+    /// Heading of the document
+    pub title: String,
+    /// Directory of ScanCode `<license_key>.LICENSE` texts
+    pub license_texts_dir: PathBuf,
+}
+
+/// A shipped component as attributed in the NOTICE file
+#[derive(Debug, Clone, Serialize)]
+pub struct NoticeComponent {
+    pub name: String,
+    pub version: Option<String>,
+    pub expression: Option<String>,
+    /// Copyright lines found in the component's sources and license files
+    pub copyrights: Vec<String>,
+    /// Contents of the component's NOTICE files, which the Apache License requires to be redistributed
+    pub notices: Vec<String>,
+}
+
+/// Components used under one license, with its full text
+#[derive(Debug, Clone, Serialize)]
+pub struct NoticeLicense {
+    /// SPDX id, or the id as written for licenses missing from the database
+    pub license: String,
+    /// ScanCode key, for licenses in the database
+    pub license_key: Option<String>,
+    pub text: Option<String>,
+    /// Display names of the components
+    pub components: Vec<String>,
+}
+
+/// Third-party attribution for the shipped components of an audit
+#[derive(Debug, Clone, Serialize)]
+pub struct ThirdPartyNotice {
+    pub title: String,
+    pub components: Vec<NoticeComponent>,
+    pub licenses: Vec<NoticeLicense>,
+    /// Components that declare no license
+    pub unlicensed: Vec<String>,
+}
+
+impl NoticeComponent {
+    fn display_name(&self) -> String {
+        match self.version {
+            Some(ref version) => format!("{}@{}", self.name, version),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// Licenses and exceptions of an expression, in order of appearance
+fn attributed_ids(expr: &SpdxExpr, ids: &mut Vec<String>) {
+    match expr {
+        SpdxExpr::License(id) => ids.push(id.clone()),
+        SpdxExpr::And(left, right) | SpdxExpr::Or(left, right) => {
+            attributed_ids(left, ids);
+            attributed_ids(right, ids);
+        }
+        SpdxExpr::With(license, exception) => {
+            attributed_ids(license, ids);
+            ids.push(exception.clone());
+        }
+    }
+}
+
+/// Full text of a license from the ScanCode texts
+fn license_text(license_key: &str, texts_dir: &Path) -> Option<String> {
+    let text = fs::read_to_string(texts_dir.join(format!("{}.LICENSE", license_key))).ok()?;
+    Some(strip_front_matter(&text).trim().to_string()).filter(|text| !text.is_empty())
+}
+
+/// A copyright statement on a source line, without comment markers
+fn copyright_statement(line: &str) -> Option<String> {
+    if let Some(text) = extract_tag_value(line, "SPDX-FileCopyrightText:") {
+        let lower = text.to_lowercase();
+        let marked = lower.starts_with("copyright") || lower.starts_with("(c)") || text.starts_with('©');
+        return Some(if marked { text } else { format!("Copyright {}", text) });
+    }
+    let mut text = line.trim().trim_start_matches(|c: char| "/*#;!-%'\"<> \t".contains(c)).trim_end();
+    for closer in ["*/", "-->"] {
+        text = text.strip_suffix(closer).unwrap_or(text).trim_end();
+    }
+    let lower = text.to_lowercase();
+    if !is_copyright_line(text) || text.len() > MAX_COPYRIGHT_LINE || COPYRIGHT_PLACEHOLDERS.iter().any(|p| lower.contains(p)) {
+        return None;
+    }
+    Some(text.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+fn is_notice_file(path: &Path) -> bool {
+    path.file_stem().and_then(|s| s.to_str()).is_some_and(|stem| stem.eq_ignore_ascii_case("NOTICE"))
+}
+
+/// Collects copyright lines and NOTICE file contents of a component
+fn scan_component(audit: &ComponentAudit) -> (Vec<String>, Vec<String>) {
+    let mut files = audit.component.license_files.clone();
+    if let Some(ref dir) = audit.component.source_dir {
+        let excluded: Vec<String> = SKIPPED_DIRS.iter().map(|d| d.to_string()).collect();
+        collect_files(dir, &excluded, &mut files);
+    }
+    files.sort();
+    files.dedup();
+
+    let mut copyrights: Vec<String> = Vec::new();
+    let mut notices: Vec<String> = Vec::new();
+    for path in &files {
+        let Some(text) = read_text_file(path) else {
+            continue;
+        };
+        if is_notice_file(path) {
+            let notice = text.trim().to_string();
+            if !notice.is_empty() && !notices.contains(&notice) {
+                notices.push(notice);
+            }
+        }
+        for statement in text.lines().filter_map(copyright_statement) {
+            if !copyrights.contains(&statement) {
+                copyrights.push(statement);
+            }
+        }
+    }
+    (copyrights, notices)
+}
+
+/// Builds the third-party NOTICE for the components an audit found shipped with the project
+///
+/// Each component is attributed under the licenses of its expression, or of the recommended
+/// alternative for OR expressions. Root, build and dev components are left out.
+pub fn generate_notice(parser: &LicenseExpressionParser, report: &AuditReport, options: &NoticeOptions) -> ThirdPartyNotice {
+    let database = parser.database();
+    let mut shipped: Vec<&ComponentAudit> = report.components.iter()
+        .filter(|audit| audit.component.kind == DependencyKind::Normal)
+        .collect();
+    shipped.sort_by(|a, b| (&a.component.name, &a.component.version).cmp(&(&b.component.name, &b.component.version)));
+
+    let mut components = Vec::new();
+    let mut by_license: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut unlicensed = Vec::new();
+    for audit in shipped {
+        let (copyrights, notices) = scan_component(audit);
+        let component = NoticeComponent {
+            name: audit.component.name.clone(),
+            version: audit.component.version.clone(),
+            expression: audit.component.expression.clone(),
+            copyrights,
+            notices,
+        };
+        let mut ids = Vec::new();
+        if let Some(expr) = concluded_expression(audit, true) {
+            attributed_ids(&expr, &mut ids);
+        }
+        if ids.is_empty() {
+            unlicensed.push(component.display_name());
+        }
+        for id in ids {
+            let license = database.get(&id).map(|l| l.name).unwrap_or(id);
+            let names = by_license.entry(license).or_default();
+            if !names.contains(&component.display_name()) {
+                names.push(component.display_name());
+            }
+        }
+        components.push(component);
+    }
+
+    let licenses = by_license.into_iter()
+        .map(|(license, components)| {
+            let license_key = database.get(&license).map(|l| l.id);
+            NoticeLicense {
+                text: license_key.as_deref().and_then(|key| license_text(key, &options.license_texts_dir)),
+                license_key,
+                license,
+                components,
+            }
+        })
+        .collect();
+    ThirdPartyNotice {
+        title: options.title.clone(),
+        components,
+        licenses,
+        unlicensed,
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Anchor of a license section in Markdown and HTML output
+fn anchor(license: &str) -> String {
+    format!("license-{}", license.to_lowercase().replace(|c: char| !c.is_ascii_alphanumeric(), "-"))
+}
+
+impl NoticeLicense {
+    /// The full text, or where to find it when the local texts lack it
+    fn text_or_reference(&self) -> String {
+        match (&self.text, &self.license_key) {
+            (Some(text), _) => text.clone(),
+            (None, Some(key)) => format!("The license text is not included; see https://scancode-licensedb.aboutcode.org/{}.html", key),
+            (None, None) => "The license text was not available when this file was generated.".to_string(),
+        }
+    }
+}
+
+impl ThirdPartyNotice {
+    /// Licenses a component is attributed under
+    fn licenses_of(&self, component: &NoticeComponent) -> Vec<&str> {
+        let name = component.display_name();
+        self.licenses.iter().filter(|l| l.components.contains(&name)).map(|l| l.license.as_str()).collect()
+    }
+
+    pub fn render(&self, format: NoticeFormat) -> String {
+        let mut out = String::new();
+        let result = match format {
+            NoticeFormat::Text => self.write_text(&mut out),
+            NoticeFormat::Markdown => self.write_markdown(&mut out),
+            NoticeFormat::Html => self.write_html(&mut out),
+        };
+        result.expect("writing to a String does not fail");
+        out
+    }
+
+    fn write_text(&self, out: &mut String) -> fmt::Result {
+        let rule = "=".repeat(80);
+        writeln!(out, "{}\n\nThis product includes the following third-party components.\n", self.title)?;
+        for component in &self.components {
+            writeln!(out, "{}", rule)?;
+            writeln!(out, "{}", component.display_name())?;
+            writeln!(out, "License: {}", component.expression.as_deref().unwrap_or("NONE"))?;
+            for copyright in &component.copyrights {
+                writeln!(out, "{}", copyright)?;
+            }
+            for notice in &component.notices {
+                writeln!(out, "\nNOTICE:\n{}", notice)?;
+            }
+            writeln!(out)?;
+        }
+        for license in &self.licenses {
+            writeln!(out, "{}", rule)?;
+            writeln!(out, "{}\nUsed by: {}\n", license.license, license.components.join(", "))?;
+            writeln!(out, "{}\n", license.text_or_reference())?;
+        }
+        if !self.unlicensed.is_empty() {
+            writeln!(out, "{}", rule)?;
+            writeln!(out, "Components without a declared license: {}", self.unlicensed.join(", "))?;
+        }
+        Ok(())
+    }
+
+    fn write_markdown(&self, out: &mut String) -> fmt::Result {
+        writeln!(out, "# {}\n\nThis product includes the following third-party components.\n", self.title)?;
+        writeln!(out, "## Components\n")?;
+        for component in &self.components {
+            let links: Vec<String> = self.licenses_of(component).iter()
+                .map(|license| format!("[{}](#{})", license, anchor(license)))
+                .collect();
+            writeln!(out, "### {}\n", component.display_name())?;
+            writeln!(out, "License: {}\n", if links.is_empty() { "none declared".to_string() } else { links.join(", ") })?;
+            for copyright in &component.copyrights {
+                writeln!(out, "- {}", copyright)?;
+            }
+            if !component.copyrights.is_empty() {
+                writeln!(out)?;
+            }
+            for notice in &component.notices {
+                writeln!(out, "NOTICE:\n\n```text\n{}\n```\n", notice)?;
+            }
+        }
+        writeln!(out, "## Licenses\n")?;
+        for license in &self.licenses {
+            writeln!(out, "<a id=\"{}\"></a>\n", anchor(&license.license))?;
+            writeln!(out, "### {}\n", license.license)?;
+            writeln!(out, "Used by: {}\n", license.components.join(", "))?;
+            writeln!(out, "```text\n{}\n```\n", license.text_or_reference())?;
+        }
+        if !self.unlicensed.is_empty() {
+            writeln!(out, "## Components Without a Declared License\n")?;
+            for name in &self.unlicensed {
+                writeln!(out, "- {}", name)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_html(&self, out: &mut String) -> fmt::Result {
+        let title = escape_html(&self.title);
+        writeln!(out, "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>", title)?;
+        writeln!(out, "<h1>{}</h1>\n<p>This product includes the following third-party components.</p>", title)?;
+        writeln!(out, "<h2>Components</h2>")?;
+        for component in &self.components {
+            let links: Vec<String> = self.licenses_of(component).iter()
+                .map(|license| format!("<a href=\"#{}\">{}</a>", anchor(license), escape_html(license)))
+                .collect();
+            writeln!(out, "<h3>{}</h3>", escape_html(&component.display_name()))?;
+            writeln!(out, "<p>License: {}</p>", if links.is_empty() { "none declared".to_string() } else { links.join(", ") })?;
+            if !component.copyrights.is_empty() {
+                writeln!(out, "<ul>")?;
+                for copyright in &component.copyrights {
+                    writeln!(out, "<li>{}</li>", escape_html(copyright))?;
+                }
+                writeln!(out, "</ul>")?;
+            }
+            for notice in &component.notices {
+                writeln!(out, "<p>NOTICE:</p>\n<pre>{}</pre>", escape_html(notice))?;
+            }
+        }
+        writeln!(out, "<h2>Licenses</h2>")?;
+        for license in &self.licenses {
+            writeln!(out, "<h3 id=\"{}\">{}</h3>", anchor(&license.license), escape_html(&license.license))?;
+            writeln!(out, "<p>Used by: {}</p>", escape_html(&license.components.join(", ")))?;
+            writeln!(out, "<pre>{}</pre>", escape_html(&license.text_or_reference()))?;
+        }
+        if !self.unlicensed.is_empty() {
+            writeln!(out, "<h2>Components Without a Declared License</h2>\n<ul>")?;
+            for name in &self.unlicensed {
+                writeln!(out, "<li>{}</li>", escape_html(name))?;
+            }
+            writeln!(out, "</ul>")?;
+        }
+        writeln!(out, "</body>\n</html>")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inventory::Component;
+    use crate::test_support::{temp_dir, write_file};
+
+    #[test]
+    fn extracts_copyright_lines() {
+        assert_eq!(copyright_statement(" * Copyright 2020  Jane Doe */").as_deref(), Some("Copyright 2020 Jane Doe"));
+        assert_eq!(copyright_statement("# (c) 2019 Acme Inc.").as_deref(), Some("(c) 2019 Acme Inc."));
+        assert_eq!(copyright_statement("// © Acme Inc.").as_deref(), Some("© Acme Inc."));
+        assert_eq!(copyright_statement("Copyright (c) The Authors").as_deref(), Some("Copyright (c) The Authors"));
+        assert_eq!(copyright_statement("// SPDX-FileCopyrightText: 2021 Jane Doe").as_deref(), Some("Copyright 2021 Jane Doe"));
+    }
+
+    #[test]
+    fn skips_license_clauses_and_placeholders() {
+        let clause = "      (c) You must retain, in the Source form of any Derivative Works";
+        assert_eq!(copyright_statement(clause), None);
+        assert_eq!(copyright_statement("   Copyright [yyyy] [name of copyright owner]"), None);
+        assert_eq!(copyright_statement("Copyright holders are not liable"), None);
+    }
+
+    #[test]
+    fn scans_component_sources_and_notice() {
+        let dir = temp_dir("notice");
+        write_file(&dir, "LICENSE", "   Copyright [yyyy] [name of copyright owner]\n\n      (c) You must retain, in the Source form of any Derivative Works\n");
+        write_file(&dir, "NOTICE", "Acme Widgets\nCopyright 2015 Acme Inc.\n");
+        write_file(&dir, "src/lib.rs", "// Copyright 2015 Acme Inc.\n// Copyright 2016-2018 Jane Doe\n");
+        write_file(&dir, "node_modules/dep/index.js", "// Copyright 2001 Someone Else\n");
+        let mut component = Component::new("acme", "acme", Some("1.0"), DependencyKind::Normal);
+        component.source_dir = Some(dir.clone());
+        let audit = ComponentAudit { component, analysis: None, violations: Vec::new(), waived: Vec::new(), expired_waivers: Vec::new(), dependency_path: Vec::new() };
+
+        let (copyrights, notices) = scan_component(&audit);
+        assert_eq!(copyrights, vec!["Copyright 2015 Acme Inc.", "Copyright 2016-2018 Jane Doe"]);
+        assert_eq!(notices, vec!["Acme Widgets\nCopyright 2015 Acme Inc."]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
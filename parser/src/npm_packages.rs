@@ -0,0 +1,389 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use serde_json::Value;
+use crate::inventory::{any_of_licenses, resolve_kinds, Component, DependencyKind, Inventory, InventoryError};
+
+/// Prefix of license fields pointing at a license file inside the package
+const SEE_LICENSE_IN: &str = "SEE LICENSE IN ";
+
+/// A package read from package-lock.json or an installed package.json
+#[derive(Debug, Default)]
+struct NpmPackage {
+    name: Option<String>,
+    version: Option<String>,
+    license: Option<Value>,
+    /// Deprecated `licenses` array
+    licenses: Option<Value>,
+    dependencies: Vec<(String, DependencyKind)>,
+    /// Target of a workspace symlink entry
+    link: Option<String>,
+    /// Kind recorded by the lockfile's `dev` flag; None for project packages and installed package.json files
+    lock_kind: Option<DependencyKind>,
+}
+
+fn read_json(path: &Path) -> Result<Value, InventoryError> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| InventoryError::FileReadError(format!("{}: {}", path.display(), e)))?;
+    serde_json::from_str(&content).map_err(|e| InventoryError::ParseError(format!("{}: {}", path.display(), e)))
+}
+
+/// Reads a package entry; `is_project` (the root and workspace members) also takes devDependencies into account
+fn read_package(value: &Value, is_project: bool) -> NpmPackage {
+    let text = |field: &str| value.get(field).and_then(Value::as_str).map(String::from);
+    let mut sections = vec![
+        ("dependencies", DependencyKind::Normal),
+        ("optionalDependencies", DependencyKind::Normal),
+        ("peerDependencies", DependencyKind::Normal),
+    ];
+    if is_project {
+        sections.push(("devDependencies", DependencyKind::Dev));
+    }
+
+    let mut dependencies: Vec<(String, DependencyKind)> = Vec::new();
+    for (section, kind) in sections {
+        for name in value.get(section).and_then(Value::as_object).into_iter().flat_map(|o| o.keys()) {
+            if !dependencies.iter().any(|(n, _)| n == name) {
+                dependencies.push((name.clone(), kind));
+            }
+        }
+    }
+
+    NpmPackage {
+        name: text("name"),
+        version: text("version"),
+        license: value.get("license").cloned(),
+        licenses: value.get("licenses").cloned(),
+        dependencies,
+        link: value.get("link").and_then(Value::as_bool).filter(|l| *l).and(text("resolved")),
+        lock_kind: None,
+    }
+}
+
+/// Kind npm recorded for a lockfile entry
+///
+/// `dev` marks packages only needed for development. `devOptional` packages are also optional
+/// dependencies of production packages, so npm still installs them without dev dependencies.
+fn lock_kind(value: &Value) -> DependencyKind {
+    let flag = |field: &str| value.get(field).and_then(Value::as_bool).unwrap_or(false);
+    if flag("dev") {
+        DependencyKind::Dev
+    } else {
+        DependencyKind::Normal
+    }
+}
+
+/// Whether a package key is the project root or a workspace member rather than an installed dependency
+fn is_project_key(key: &str) -> bool {
+    !key.contains("node_modules")
+}
+
+/// Name of the package installed at a `node_modules/...` path
+fn name_from_path(path: &str) -> &str {
+    path.rfind("node_modules/").map(|i| &path[i + "node_modules/".len()..]).unwrap_or(path)
+}
+
+/// Finds the package `name` required from `from` with Node's lookup through parent node_modules directories
+fn resolve_package(from: &str, name: &str, packages: &BTreeMap<String, NpmPackage>) -> Option<String> {
+    let mut base = from;
+    loop {
+        let candidate = if base.is_empty() {
+            format!("node_modules/{}", name)
+        } else {
+            format!("{}/node_modules/{}", base, name)
+        };
+        if packages.contains_key(&candidate) {
+            return Some(candidate);
+        }
+        if base.is_empty() {
+            return None;
+        }
+        base = match base.rfind("/node_modules/") {
+            Some(i) => &base[..i],
+            None => "",
+        };
+    }
+}
+
+/// Text of a license entry, which may be a string or a legacy `{type, url}` object
+fn license_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.trim().to_string()),
+        Value::Object(object) => object.get("type").and_then(Value::as_str).map(|t| t.trim().to_string()),
+        _ => None,
+    }
+}
+
+/// Records the license fields of a package.json on its component
+///
+/// Handles SPDX strings, legacy `{type, url}` objects and `licenses` arrays (read as OR),
+/// `SEE LICENSE IN <file>` references and `UNLICENSED`.
+fn set_npm_license(component: &mut Component, license: Option<&Value>, licenses: Option<&Value>, dir: &Path) {
+    let declared = match (license, licenses) {
+        (Some(value), _) => value,
+        (None, Some(value)) => value,
+        (None, None) => return,
+    };
+    component.declared_license = Some(match declared {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    });
+
+    let mut alternatives: Vec<String> = Vec::new();
+    match declared {
+        Value::Array(entries) => {
+            alternatives.extend(entries.iter().filter_map(license_text));
+            component.notes.push("Legacy licenses array read as OR".to_string());
+        }
+        Value::Object(_) => {
+            alternatives.extend(license_text(declared));
+            component.notes.push("Legacy {type, url} license object".to_string());
+        }
+        _ => alternatives.extend(license_text(declared)),
+    }
+
+    let mut expressions = Vec::new();
+    for alternative in alternatives {
+        let references_file = alternative.get(..SEE_LICENSE_IN.len()).is_some_and(|p| p.eq_ignore_ascii_case(SEE_LICENSE_IN));
+        if references_file {
+            let file = alternative[SEE_LICENSE_IN.len()..].trim();
+            component.license_files.push(dir.join(file));
+            component.notes.push(format!("License text referenced in {}", file));
+        } else if alternative.eq_ignore_ascii_case("UNLICENSED") {
+            component.notes.push("UNLICENSED: the author grants no rights to use the package".to_string());
+        } else if !alternative.is_empty() {
+            expressions.push(alternative);
+        }
+    }
+
+    component.expression = any_of_licenses(&expressions);
+}
+
+/// Walks `dir/node_modules` recursively, including scoped packages, keyed by path relative to the project
+fn collect_node_modules(root: &Path, dir: &str, packages: &mut BTreeMap<String, NpmPackage>) {
+    let node_modules = if dir.is_empty() { "node_modules".to_string() } else { format!("{}/node_modules", dir) };
+    let Ok(entries) = fs::read_dir(root.join(&node_modules)) else {
+        return;
+    };
+    let mut names: Vec<String> = Vec::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('@') {
+            let scoped = fs::read_dir(entry.path()).into_iter().flatten().flatten();
+            names.extend(scoped.map(|e| format!("{}/{}", name, e.file_name().to_string_lossy())));
+        } else if !name.starts_with('.') {
+            names.push(name);
+        }
+    }
+
+    for name in names {
+        let key = format!("{}/{}", node_modules, name);
+        let Ok(value) = read_json(&root.join(&key).join("package.json")) else {
+            continue;
+        };
+        packages.insert(key.clone(), read_package(&value, false));
+        collect_node_modules(root, &key, packages);
+    }
+}
+
+fn build_inventory(root: &Path, packages: BTreeMap<String, NpmPackage>, source: &str) -> Inventory {
+    // Workspace members live outside node_modules and belong to the project
+    let roots: Vec<String> = packages.keys().filter(|key| is_project_key(key)).cloned().collect();
+
+    let mut edges: HashMap<String, Vec<(String, DependencyKind)>> = HashMap::new();
+    for (key, package) in &packages {
+        let package_edges = edges.entry(key.clone()).or_default();
+        if let Some(ref target) = package.link {
+            package_edges.push((target.clone(), DependencyKind::Normal));
+            continue;
+        }
+        for (name, kind) in &package.dependencies {
+            match resolve_package(key, name, &packages) {
+                Some(dependency) => package_edges.push((dependency, *kind)),
+                None => log::debug!("Dependency {} of {} is not installed", name, key),
+            }
+        }
+    }
+    let kinds = resolve_kinds(&roots, &edges);
+
+    let components = packages.iter()
+        .filter(|(_, package)| package.link.is_none())
+        .map(|(key, package)| {
+            let name = package.name.clone().unwrap_or_else(|| name_from_path(key).to_string());
+            // The lockfile's own classification wins; unreachable packages without one are extraneous
+            let kind = package.lock_kind.or_else(|| kinds.get(key).copied());
+            let mut component = Component::new(key, &name, package.version.as_deref(), kind.unwrap_or(DependencyKind::Dev));
+            if kind.is_none() {
+                component.notes.push("Not required by the project (extraneous); treated as a dev dependency".to_string());
+            }
+            // Dependencies reached through a workspace link point at the link target
+            component.dependencies = edges.get(key).into_iter().flatten()
+                .map(|(dependency, _)| packages.get(dependency).and_then(|p| p.link.clone()).unwrap_or_else(|| dependency.clone()))
+                .collect();
+            let dir = root.join(key);
+            set_npm_license(&mut component, package.license.as_ref(), package.licenses.as_ref(), &dir);
+            component.source_dir = dir.is_dir().then_some(dir);
+            component
+        })
+        .collect();
+
+    Inventory {
+        source: source.to_string(),
+        roots,
+        components,
+    }
+}
+
+/// Builds an inventory from a package-lock.json (lockfile version 2 or 3)
+/// Entries without a license field fall back to the installed package.json next to the lock file, if any
+pub fn inventory_from_package_lock(lock_path: &Path) -> Result<Inventory, InventoryError> {
+    let lock = read_json(lock_path)?;
+    let Some(entries) = lock.get("packages").and_then(Value::as_object) else {
+        return Err(InventoryError::ParseError(format!(
+            "{}: no \"packages\" section; lockfile version 1 is not supported, audit node_modules instead",
+            lock_path.display()
+        )));
+    };
+    let root = lock_path.parent().unwrap_or(Path::new(""));
+
+    let mut packages = BTreeMap::new();
+    for (key, value) in entries {
+        let mut package = read_package(value, is_project_key(key));
+        if !is_project_key(key) {
+            package.lock_kind = Some(lock_kind(value));
+        }
+        if package.license.is_none() && package.licenses.is_none() && package.link.is_none() {
+            if let Ok(installed) = read_json(&root.join(key).join("package.json")) {
+                package.license = installed.get("license").cloned();
+                package.licenses = installed.get("licenses").cloned();
+            }
+        }
+        if key.is_empty() && package.name.is_none() {
+            package.name = lock.get("name").and_then(Value::as_str).map(String::from);
+        }
+        packages.insert(key.clone(), package);
+    }
+
+    Ok(build_inventory(root, packages, &lock_path.display().to_string()))
+}
+
+/// Builds an inventory by walking `node_modules/*/package.json` below a project directory
+pub fn inventory_from_node_modules(project_dir: &Path) -> Result<Inventory, InventoryError> {
+    let root_manifest = read_json(&project_dir.join("package.json"))?;
+    let mut packages = BTreeMap::new();
+    packages.insert(String::new(), read_package(&root_manifest, true));
+    collect_node_modules(project_dir, "", &mut packages);
+
+    Ok(build_inventory(project_dir, packages, &project_dir.display().to_string()))
+}
+
+/// Reads a package-lock.json, or a project directory through its lock file or else its node_modules
+pub fn inventory_from_npm_project(path: &Path) -> Result<Inventory, InventoryError> {
+    if path.is_file() {
+        return inventory_from_package_lock(path);
+    }
+    let lock_path = path.join("package-lock.json");
+    if lock_path.is_file() {
+        inventory_from_package_lock(&lock_path)
+    } else {
+        inventory_from_node_modules(path)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{temp_dir, write_file};
+
+    fn component<'a>(inventory: &'a Inventory, id: &str) -> &'a Component {
+        inventory.components.iter().find(|c| c.id == id).unwrap()
+    }
+
+    const LOCK: &str = r#"{
+  "name": "app",
+  "lockfileVersion": 3,
+  "packages": {
+    "": {"name": "app", "version": "1.0.0", "license": "MIT", "workspaces": ["packages/ui"],
+         "dependencies": {"left-pad": "^1"}, "devDependencies": {"jest": "^29"}},
+    "packages/ui": {"name": "ui", "version": "0.1.0", "license": "MIT",
+         "dependencies": {"react": "^18"}, "devDependencies": {"storybook": "^7"}},
+    "node_modules/ui": {"resolved": "packages/ui", "link": true},
+    "node_modules/left-pad": {"version": "1.3.0", "license": "WTFPL"},
+    "node_modules/react": {"version": "18.2.0", "license": "MIT"},
+    "node_modules/jest": {"version": "29.0.0", "license": "MIT", "dev": true,
+         "dependencies": {"fsevents": "^2"}},
+    "node_modules/storybook": {"version": "7.0.0", "license": "MIT", "dev": true},
+    "node_modules/fsevents": {"version": "2.3.2", "license": "MIT", "dev": true, "optional": true},
+    "node_modules/esbuild": {"version": "0.19.0", "license": "MIT", "devOptional": true},
+    "node_modules/ghost": {"version": "1.0.0", "license": "GPL-3.0-only", "dev": true}
+  }
+}"#;
+
+    #[test]
+    fn lockfile_dependency_kinds() {
+        let dir = temp_dir("npm-lock");
+        let lock = write_file(&dir, "package-lock.json", LOCK);
+        let inventory = inventory_from_package_lock(&lock).unwrap();
+
+        assert_eq!(inventory.roots, vec!["", "packages/ui"]);
+        assert_eq!(component(&inventory, "").kind, DependencyKind::Root);
+        assert_eq!(component(&inventory, "packages/ui").kind, DependencyKind::Root);
+        assert_eq!(component(&inventory, "node_modules/left-pad").kind, DependencyKind::Normal);
+        assert_eq!(component(&inventory, "node_modules/react").kind, DependencyKind::Normal);
+        assert_eq!(component(&inventory, "node_modules/jest").kind, DependencyKind::Dev);
+        assert_eq!(component(&inventory, "node_modules/fsevents").kind, DependencyKind::Dev);
+        // Dev dependency of a workspace member, and one no package requires
+        assert_eq!(component(&inventory, "node_modules/storybook").kind, DependencyKind::Dev);
+        assert_eq!(component(&inventory, "node_modules/ghost").kind, DependencyKind::Dev);
+        // Also an optional dependency of a production package, so npm installs it without dev dependencies
+        assert_eq!(component(&inventory, "node_modules/esbuild").kind, DependencyKind::Normal);
+        assert!(inventory.components.iter().all(|c| c.id != "node_modules/ui"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn workspace_link_points_at_member() {
+        let dir = temp_dir("npm-link");
+        let lock = write_file(&dir, "package-lock.json", LOCK);
+        let inventory = inventory_from_package_lock(&lock).unwrap();
+        assert_eq!(component(&inventory, "packages/ui").dependencies, vec!["node_modules/react", "node_modules/storybook"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn node_modules_walk_marks_extraneous_packages() {
+        let dir = temp_dir("npm-walk");
+        write_file(&dir, "package.json", r#"{"name": "app", "dependencies": {"a": "1"}, "devDependencies": {"@scope/b": "1"}}"#);
+        write_file(&dir, "node_modules/a/package.json", r#"{"name": "a", "version": "1.0.0", "license": "MIT"}"#);
+        write_file(&dir, "node_modules/@scope/b/package.json", r#"{"name": "@scope/b", "version": "1.0.0", "license": "ISC"}"#);
+        write_file(&dir, "node_modules/stray/package.json", r#"{"name": "stray", "version": "1.0.0", "license": "MIT"}"#);
+        let inventory = inventory_from_node_modules(&dir).unwrap();
+
+        assert_eq!(component(&inventory, "node_modules/a").kind, DependencyKind::Normal);
+        assert_eq!(component(&inventory, "node_modules/@scope/b").kind, DependencyKind::Dev);
+        let stray = component(&inventory, "node_modules/stray");
+        assert_eq!(stray.kind, DependencyKind::Dev);
+        assert!(stray.notes.iter().any(|n| n.contains("extraneous")));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn legacy_license_fields() {
+        let dir = Path::new("pkg");
+        let mut component = Component::new("x", "x", None, DependencyKind::Normal);
+        let licenses = serde_json::json!([{"type": "MIT"}, {"type": "Apache-2.0"}]);
+        set_npm_license(&mut component, None, Some(&licenses), dir);
+        assert_eq!(component.expression.as_deref(), Some("MIT OR Apache-2.0"));
+
+        let mut component = Component::new("x", "x", None, DependencyKind::Normal);
+        set_npm_license(&mut component, Some(&Value::from("SEE LICENSE IN LICENSE.txt")), None, dir);
+        assert_eq!(component.expression, None);
+        assert_eq!(component.license_files, vec![dir.join("LICENSE.txt")]);
+
+        let mut component = Component::new("x", "x", None, DependencyKind::Normal);
+        set_npm_license(&mut component, Some(&Value::from("UNLICENSED")), None, dir);
+        assert_eq!(component.expression, None);
+        assert_eq!(component.declared_license.as_deref(), Some("UNLICENSED"));
+    }
+}
@@ -0,0 +1,326 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use crate::license_database::{LicenseDatabase, LicenseSource, NewLicense};
+use crate::license_expression_parser::LicenseExpressionParser;
+use crate::models::{new_copyleft_strength_order, NewCopyleftStrength, SpdxExpr};
+
+/// Outbound licenses considered when the caller names none, most common first
+pub const DEFAULT_OUTBOUND_CANDIDATES: [&str; 15] = [
+    "MIT",
+    "BSD-2-Clause",
+    "BSD-3-Clause",
+    "Apache-2.0",
+    "MPL-2.0",
+    "LGPL-2.1-only",
+    "LGPL-2.1-or-later",
+    "LGPL-3.0-only",
+    "LGPL-3.0-or-later",
+    "GPL-2.0-only",
+    "GPL-2.0-or-later",
+    "GPL-3.0-only",
+    "GPL-3.0-or-later",
+    "AGPL-3.0-only",
+    "AGPL-3.0-or-later",
+];
+
+/// Exceptions that let code linking to the covered library use any license
+const LINKING_EXCEPTIONS: [&str; 6] = [
+    "Classpath-exception-2.0",
+    "GCC-exception-2.0",
+    "GCC-exception-3.1",
+    "Libtool-exception",
+    "Bison-exception-2.2",
+    "Autoconf-exception-3.0",
+];
+
+/// Copyleft licenses whose own terms allow the covered code to be distributed under another license
+const RELICENSING_GRANTS: &[(&str, &[&str])] = &[
+    ("GPL-2.0-or-later", &["GPL-2.0-only", "GPL-3.0-only", "GPL-3.0-or-later", "AGPL-3.0-only", "AGPL-3.0-or-later"]),
+    ("GPL-3.0-only", &["AGPL-3.0-only"]),
+    ("GPL-3.0-or-later", &["GPL-3.0-only", "AGPL-3.0-only", "AGPL-3.0-or-later"]),
+    ("AGPL-3.0-or-later", &["AGPL-3.0-only"]),
+];
+
+/// Conjunctive alternatives kept per dependency; larger expansions are cut
+const MAX_ALTERNATIVES: usize = 64;
+
+/// A license expression the outbound license must satisfy
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutboundInput {
+    /// Name of the dependency, for naming it when it blocks a license
+    pub name: String,
+    pub expression: String,
+}
+
+/// A dependency that rules out an outbound license
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockingDependency {
+    pub dependency: String,
+    pub expression: String,
+    /// Licenses of the dependency's closest alternative that do not allow the outbound license
+    pub blocking_licenses: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboundOption {
+    /// SPDX id of the outbound license
+    pub license: String,
+    pub category: NewCopyleftStrength,
+    /// Empty for feasible licenses
+    pub blocked_by: Vec<BlockingDependency>,
+}
+
+/// Licenses a project could be released under, given the licenses of its dependencies
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboundReport {
+    pub database_version: Option<String>,
+    pub dependency_count: usize,
+    /// Feasible licenses, most permissive first
+    pub feasible: Vec<OutboundOption>,
+    /// Licenses ruled out, fewest blocking dependencies first
+    pub excluded: Vec<OutboundOption>,
+}
+
+fn unknown_license(id: &str) -> NewLicense {
+    NewLicense {
+        id: id.to_string(),
+        name: id.to_string(),
+        copyleft_strength: NewCopyleftStrength::UnstatedLicense,
+        source: LicenseSource::Unknown,
+        obligations: Vec::new(),
+        is_exception: false,
+        is_deprecated: false,
+        spdx_id: None,
+    }
+}
+
+/// Expands an expression into the license sets that satisfy it (disjunctive normal form)
+///
+/// A linking exception lowers the license it applies to to weak copyleft.
+fn alternatives(database: &LicenseDatabase, expr: &SpdxExpr) -> Vec<Vec<NewLicense>> {
+    match expr {
+        SpdxExpr::License(id) => vec![vec![database.get(id).unwrap_or_else(|| unknown_license(id))]],
+        SpdxExpr::Or(left, right) => {
+            let mut all = alternatives(database, left);
+            all.extend(alternatives(database, right));
+            all.truncate(MAX_ALTERNATIVES);
+            all
+        }
+        SpdxExpr::And(left, right) => {
+            let right = alternatives(database, right);
+            let mut all = Vec::new();
+            for left_set in alternatives(database, left) {
+                for right_set in &right {
+                    if all.len() == MAX_ALTERNATIVES {
+                        return all;
+                    }
+                    all.push(left_set.iter().chain(right_set).cloned().collect());
+                }
+            }
+            all
+        }
+        SpdxExpr::With(license, exception) => {
+            let linking = database.get(exception).map(|e| e.name).unwrap_or_else(|| exception.clone());
+            let mut all = alternatives(database, license);
+            if LINKING_EXCEPTIONS.iter().any(|e| e.eq_ignore_ascii_case(&linking)) {
+                for license in all.iter_mut().flatten() {
+                    if new_copyleft_strength_order(&license.copyleft_strength) > new_copyleft_strength_order(&NewCopyleftStrength::CopyleftLimited) {
+                        license.copyleft_strength = NewCopyleftStrength::CopyleftLimited;
+                    }
+                }
+            }
+            all
+        }
+    }
+}
+
+/// Whether code under `inbound` may be part of a project released under `outbound`
+///
+/// Licenses at least as restrictive as source-available need a compatible outbound license that
+/// is at least as strong, unless they grant relicensing themselves (e.g. "or later" versions).
+/// Other licenses accept any compatible outbound license, or any at least as strong.
+fn permits(parser: &LicenseExpressionParser, database: &LicenseDatabase, inbound: &NewLicense, outbound: &NewLicense) -> bool {
+    if inbound.name == outbound.name {
+        return true;
+    }
+    let granted = RELICENSING_GRANTS.iter()
+        .any(|(from, to)| *from == inbound.name && to.contains(&outbound.name.as_str()));
+    if granted {
+        return true;
+    }
+    let inbound_order = new_copyleft_strength_order(&inbound.copyleft_strength);
+    let at_least_as_strong = new_copyleft_strength_order(&outbound.copyleft_strength) >= inbound_order;
+    let compatible = parser.are_licenses_compatible(database, inbound, outbound);
+    if inbound_order >= new_copyleft_strength_order(&NewCopyleftStrength::SourceAvailable) {
+        compatible && at_least_as_strong
+    } else {
+        compatible || at_least_as_strong
+    }
+}
+
+/// Licenses of a dependency blocking `outbound`, or None if some alternative permits it
+fn blocking_licenses(parser: &LicenseExpressionParser, database: &LicenseDatabase, sets: &[Vec<NewLicense>], outbound: &NewLicense) -> Option<Vec<String>> {
+    let mut closest: Option<Vec<String>> = None;
+    for set in sets {
+        let blocking: Vec<String> = set.iter()
+            .filter(|license| !license.is_exception && !permits(parser, database, license, outbound))
+            .map(|license| license.name.clone())
+            .collect();
+        if blocking.is_empty() {
+            return None;
+        }
+        if closest.as_ref().is_none_or(|c| blocking.len() < c.len()) {
+            closest = Some(blocking);
+        }
+    }
+    closest
+}
+
+/// Intersects the constraints of every dependency expression to find feasible outbound licenses
+///
+/// `candidates` are SPDX ids to consider; when empty, [`DEFAULT_OUTBOUND_CANDIDATES`] found in the
+/// database are used. A dependency whose expression does not parse blocks every candidate.
+pub fn recommend_outbound(parser: &LicenseExpressionParser, inputs: &[OutboundInput], candidates: &[String]) -> Result<OutboundReport, String> {
+    let database = parser.database();
+    let candidates: Vec<NewLicense> = if candidates.is_empty() {
+        DEFAULT_OUTBOUND_CANDIDATES.iter().filter_map(|id| database.get(id)).collect()
+    } else {
+        candidates.iter()
+            .map(|id| database.get(id).ok_or_else(|| format!("Unknown outbound license: {}", id)))
+            .collect::<Result<_, _>>()?
+    };
+
+    let expanded: Vec<(&OutboundInput, Option<Vec<Vec<NewLicense>>>)> = inputs.iter()
+        .map(|input| (input, parser.parse(&input.expression).ok().map(|expr| alternatives(&database, &expr))))
+        .collect();
+
+    let mut feasible = Vec::new();
+    let mut excluded = Vec::new();
+    for (rank, candidate) in candidates.iter().enumerate() {
+        let blocked_by: Vec<BlockingDependency> = expanded.iter()
+            .filter_map(|(input, sets)| {
+                let blocking = match sets {
+                    Some(sets) => blocking_licenses(parser, &database, sets, candidate)?,
+                    None => vec![format!("invalid expression '{}'", input.expression)],
+                };
+                Some(BlockingDependency {
+                    dependency: input.name.clone(),
+                    expression: input.expression.clone(),
+                    blocking_licenses: blocking,
+                })
+            })
+            .collect();
+        let option = OutboundOption {
+            license: candidate.name.clone(),
+            category: candidate.copyleft_strength.clone(),
+            blocked_by,
+        };
+        if option.blocked_by.is_empty() {
+            feasible.push((rank, option));
+        } else {
+            excluded.push((rank, option));
+        }
+    }
+    feasible.sort_by_key(|(rank, option)| (new_copyleft_strength_order(&option.category), *rank));
+    excluded.sort_by_key(|(rank, option)| (option.blocked_by.len(), new_copyleft_strength_order(&option.category), *rank));
+
+    Ok(OutboundReport {
+        database_version: database.version().map(|v| v.label()),
+        dependency_count: inputs.len(),
+        feasible: feasible.into_iter().map(|(_, option)| option).collect(),
+        excluded: excluded.into_iter().map(|(_, option)| option).collect(),
+    })
+}
+
+impl fmt::Display for OutboundReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Outbound Licenses for {} dependencies", self.dependency_count)?;
+        if let Some(ref version) = self.database_version {
+            writeln!(f, "Database Version: {}", version)?;
+        }
+        if self.feasible.is_empty() {
+            writeln!(f, "FEASIBLE: none of the candidate licenses")?;
+        } else {
+            writeln!(f, "FEASIBLE ({}):", self.feasible.len())?;
+            for option in &self.feasible {
+                writeln!(f, "  {} ({})", option.license, option.category)?;
+            }
+        }
+        if !self.excluded.is_empty() {
+            writeln!(f, "EXCLUDED ({}):", self.excluded.len())?;
+            for option in &self.excluded {
+                writeln!(f, "  {} ({}):", option.license, option.category)?;
+                for blocking in &option.blocked_by {
+                    if blocking.dependency == blocking.expression {
+                        writeln!(f, "    blocked by {}", blocking.dependency)?;
+                    } else {
+                        writeln!(f, "    blocked by {} ({}): {}", blocking.dependency, blocking.expression, blocking.blocking_licenses.join(", "))?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::parser;
+
+    fn inputs(expressions: &[&str]) -> Vec<OutboundInput> {
+        expressions.iter().enumerate()
+            .map(|(i, e)| OutboundInput { name: format!("dep{}", i), expression: e.to_string() })
+            .collect()
+    }
+
+    fn feasible(expressions: &[&str]) -> Vec<String> {
+        recommend_outbound(&parser(), &inputs(expressions), &[]).unwrap().feasible.into_iter().map(|o| o.license).collect()
+    }
+
+    #[test]
+    fn permissive_dependencies_allow_every_candidate_most_permissive_first() {
+        let licenses = feasible(&["MIT", "Apache-2.0 OR ISC"]);
+        assert_eq!(licenses.len(), DEFAULT_OUTBOUND_CANDIDATES.len());
+        assert_eq!(licenses[0], "MIT");
+        assert_eq!(licenses.last().map(String::as_str), Some("AGPL-3.0-or-later"));
+    }
+
+    #[test]
+    fn strong_copyleft_narrows_the_choice() {
+        assert_eq!(feasible(&["GPL-3.0-only"]), vec!["GPL-3.0-only", "AGPL-3.0-only"]);
+        assert_eq!(feasible(&["GPL-2.0-only", "Apache-2.0"]), vec!["GPL-2.0-only"]);
+        assert!(feasible(&["GPL-2.0-or-later"]).contains(&"GPL-3.0-or-later".to_string()));
+        assert!(feasible(&["GPL-2.0-only", "GPL-3.0-only"]).is_empty());
+    }
+
+    #[test]
+    fn alternatives_and_linking_exceptions_keep_permissive_options() {
+        assert_eq!(feasible(&["MIT OR GPL-3.0-only"])[0], "MIT");
+        assert_eq!(feasible(&["GPL-2.0-only WITH Classpath-exception-2.0"])[0], "MIT");
+        assert_eq!(feasible(&["LGPL-2.1-only"])[0], "MIT");
+    }
+
+    #[test]
+    fn excluded_licenses_name_their_blocking_dependencies() {
+        let report = recommend_outbound(&parser(), &inputs(&["GPL-3.0-only", "MIT AND ("]), &[]).unwrap();
+        assert!(report.feasible.is_empty());
+        let mit = report.excluded.iter().find(|o| o.license == "MIT").unwrap();
+        assert_eq!(mit.blocked_by.len(), 2);
+        assert_eq!(mit.blocked_by[0].blocking_licenses, vec!["GPL-3.0-only"]);
+        assert_eq!(mit.blocked_by[1].blocking_licenses, vec!["invalid expression 'MIT AND ('"]);
+        // Licenses only blocked by the invalid expression come first
+        assert_eq!(report.excluded[0].blocked_by.len(), 1);
+        assert!(report.to_string().contains("FEASIBLE: none of the candidate licenses"));
+    }
+
+    #[test]
+    fn candidates_can_be_chosen() {
+        let candidates = vec!["mit".to_string(), "GPL-3.0-only".to_string()];
+        let report = recommend_outbound(&parser(), &inputs(&["Apache-2.0"]), &candidates).unwrap();
+        let licenses: Vec<&str> = report.feasible.iter().map(|o| o.license.as_str()).collect();
+        assert_eq!(licenses, vec!["MIT", "GPL-3.0-only"]);
+        let unknown = recommend_outbound(&parser(), &[], &["Not-A-License".to_string()]).unwrap_err();
+        assert_eq!(unknown, "Unknown outbound license: Not-A-License");
+    }
+}
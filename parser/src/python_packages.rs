@@ -0,0 +1,307 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::inventory::{any_of_licenses, check_declared_expression, Component, DependencyKind, Inventory, InventoryError};
+use crate::license_database::LicenseDatabase;
+use crate::license_names::MAX_LICENSE_NAME_LENGTH;
+
+/// Trove classifiers below `License ::` and the SPDX id they stand for
+const LICENSE_CLASSIFIERS: &[(&str, &str)] = &[
+    ("OSI Approved :: MIT License", "MIT"),
+    ("OSI Approved :: MIT No Attribution License (MIT-0)", "MIT-0"),
+    ("OSI Approved :: Apache Software License", "Apache-2.0"),
+    ("OSI Approved :: BSD License", "BSD-3-Clause"),
+    ("OSI Approved :: ISC License (ISCL)", "ISC"),
+    ("OSI Approved :: zlib/libpng License", "Zlib"),
+    ("OSI Approved :: Python Software Foundation License", "PSF-2.0"),
+    ("OSI Approved :: Historical Permission Notice and Disclaimer (HPND)", "HPND"),
+    ("OSI Approved :: Universal Permissive License (UPL)", "UPL-1.0"),
+    ("OSI Approved :: Boost Software License 1.0 (BSL-1.0)", "BSL-1.0"),
+    ("OSI Approved :: The Unlicense (Unlicense)", "Unlicense"),
+    ("OSI Approved :: Artistic License", "Artistic-2.0"),
+    ("OSI Approved :: Mozilla Public License 1.1 (MPL 1.1)", "MPL-1.1"),
+    ("OSI Approved :: Mozilla Public License 2.0 (MPL 2.0)", "MPL-2.0"),
+    ("OSI Approved :: Eclipse Public License 1.0 (EPL-1.0)", "EPL-1.0"),
+    ("OSI Approved :: Eclipse Public License 2.0 (EPL-2.0)", "EPL-2.0"),
+    ("OSI Approved :: Common Development and Distribution License 1.0 (CDDL-1.0)", "CDDL-1.0"),
+    ("OSI Approved :: European Union Public Licence 1.2 (EUPL 1.2)", "EUPL-1.2"),
+    ("OSI Approved :: GNU General Public License (GPL)", "GPL-2.0-or-later"),
+    ("OSI Approved :: GNU General Public License v2 (GPLv2)", "GPL-2.0-only"),
+    ("OSI Approved :: GNU General Public License v2 or later (GPLv2+)", "GPL-2.0-or-later"),
+    ("OSI Approved :: GNU General Public License v3 (GPLv3)", "GPL-3.0-only"),
+    ("OSI Approved :: GNU General Public License v3 or later (GPLv3+)", "GPL-3.0-or-later"),
+    ("OSI Approved :: GNU Library or Lesser General Public License (LGPL)", "LGPL-2.0-or-later"),
+    ("OSI Approved :: GNU Lesser General Public License v2 (LGPLv2)", "LGPL-2.0-only"),
+    ("OSI Approved :: GNU Lesser General Public License v2 or later (LGPLv2+)", "LGPL-2.0-or-later"),
+    ("OSI Approved :: GNU Lesser General Public License v3 (LGPLv3)", "LGPL-3.0-only"),
+    ("OSI Approved :: GNU Lesser General Public License v3 or later (LGPLv3+)", "LGPL-3.0-or-later"),
+    ("OSI Approved :: GNU Affero General Public License v3", "AGPL-3.0-only"),
+    ("OSI Approved :: GNU Affero General Public License v3 or later (AGPLv3+)", "AGPL-3.0-or-later"),
+    ("CC0 1.0 Universal (CC0 1.0) Public Domain Dedication", "CC0-1.0"),
+    ("Public Domain", "LicenseRef-scancode-public-domain"),
+    ("Other/Proprietary License", "LicenseRef-scancode-proprietary-license"),
+];
+
+const CLASSIFIER_PREFIX: &str = "License ::";
+
+/// Core metadata fields of an installed distribution
+#[derive(Debug, Default)]
+struct DistMetadata {
+    name: Option<String>,
+    version: Option<String>,
+    license: Option<String>,
+    license_expression: Option<String>,
+    license_files: Vec<String>,
+    license_classifiers: Vec<String>,
+    requires: Vec<String>,
+}
+
+/// Parses the RFC 822 style header block of a METADATA file; the description body is ignored
+fn parse_metadata(content: &str) -> DistMetadata {
+    let mut fields: Vec<(String, String)> = Vec::new();
+    for line in content.lines() {
+        if line.trim().is_empty() {
+            break;
+        }
+        if line.starts_with([' ', '\t']) {
+            // Continuation of a multi-line field such as License
+            if let Some((_, value)) = fields.last_mut() {
+                value.push('\n');
+                value.push_str(line.trim_start().trim_start_matches('|'));
+            }
+        } else if let Some((key, value)) = line.split_once(':') {
+            fields.push((key.trim().to_ascii_lowercase(), value.trim().to_string()));
+        }
+    }
+
+    let mut metadata = DistMetadata::default();
+    for (key, value) in fields {
+        match key.as_str() {
+            "name" => metadata.name = Some(value),
+            "version" => metadata.version = Some(value),
+            "license" if !value.trim().is_empty() && !value.eq_ignore_ascii_case("UNKNOWN") => metadata.license = Some(value),
+            "license-expression" => metadata.license_expression = Some(value),
+            "license-file" => metadata.license_files.push(value),
+            "classifier" => {
+                if let Some(classifier) = value.strip_prefix(CLASSIFIER_PREFIX) {
+                    metadata.license_classifiers.push(classifier.trim().to_string());
+                }
+            }
+            "requires-dist" => metadata.requires.push(value),
+            _ => {}
+        }
+    }
+    metadata
+}
+
+/// Normalizes a distribution name as in PEP 503, so `Foo_Bar` and `foo-bar` are the same package
+fn normalize_name(name: &str) -> String {
+    let mut normalized = String::new();
+    for c in name.trim().chars() {
+        if matches!(c, '-' | '_' | '.') {
+            if !normalized.ends_with('-') {
+                normalized.push('-');
+            }
+        } else {
+            normalized.push(c.to_ascii_lowercase());
+        }
+    }
+    normalized
+}
+
+/// Name of the distribution a Requires-Dist entry names, or None for optional extras
+fn required_name(requirement: &str) -> Option<String> {
+    let (spec, marker) = requirement.split_once(';').unwrap_or((requirement, ""));
+    if marker.contains("extra") {
+        return None;
+    }
+    let end = spec.find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))).unwrap_or(spec.len());
+    let name = &spec[..end];
+    (!name.is_empty()).then(|| normalize_name(name))
+}
+
+/// Records the license of a distribution on its component
+///
+/// Takes License-Expression when it is a valid SPDX expression, then a free-text License field
+/// when it maps to known ids, then the license classifiers, read as OR when there are several.
+fn set_python_license(component: &mut Component, database: &LicenseDatabase, metadata: &DistMetadata) {
+    if let Some(ref expression) = metadata.license_expression {
+        match check_declared_expression(expression) {
+            Ok(()) => {
+                component.declared_license = Some(expression.clone());
+                component.expression = Some(expression.trim().to_string());
+                return;
+            }
+            Err(error) => component.notes.push(format!("License-Expression '{}' is not a valid SPDX expression: {}", expression, error)),
+        }
+    }
+
+    if let Some(ref license) = metadata.license {
+        if license.lines().count() > 1 || license.len() > MAX_LICENSE_NAME_LENGTH {
+            component.notes.push("License field holds a license text".to_string());
+        } else {
+            component.declared_license = Some(license.clone());
+            match database.normalize_license_field(license) {
+                Some(normalized) => {
+                    component.notes.extend(normalized.notes);
+                    component.expression = Some(normalized.expression);
+                    return;
+                }
+                None => component.notes.push(format!("License field '{}' not recognized", license)),
+            }
+        }
+    }
+
+    if metadata.license_classifiers.is_empty() {
+        return;
+    }
+    let mut ids: Vec<&str> = Vec::new();
+    for classifier in &metadata.license_classifiers {
+        match LICENSE_CLASSIFIERS.iter().find(|(name, _)| name == classifier) {
+            Some((_, id)) if !ids.contains(id) => ids.push(id),
+            Some(_) => {}
+            None => component.notes.push(format!("Classifier 'License :: {}' has no SPDX id", classifier)),
+        }
+    }
+    if ids.is_empty() {
+        return;
+    }
+    if component.declared_license.is_none() {
+        component.declared_license = Some(metadata.license_classifiers.join("; "));
+    }
+    if ids.len() > 1 {
+        component.notes.push("Several license classifiers read as OR".to_string());
+    }
+    component.notes.push("License read from trove classifiers".to_string());
+    component.expression = any_of_licenses(&ids);
+}
+
+/// License files of a distribution: `licenses/` below .dist-info (PEP 639) or the .dist-info itself
+fn find_license_files(dist_info: &Path, metadata: &DistMetadata) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for file in &metadata.license_files {
+        for candidate in [dist_info.join("licenses").join(file), dist_info.join(file)] {
+            if candidate.is_file() {
+                files.push(candidate);
+                break;
+            }
+        }
+    }
+    if files.is_empty() {
+        let conventional = fs::read_dir(dist_info).into_iter().flatten().flatten()
+            .map(|e| e.path())
+            .filter(|p| p.is_file() && p.file_name().and_then(|n| n.to_str()).is_some_and(|n| {
+                let upper = n.to_ascii_uppercase();
+                upper.starts_with("LICENSE") || upper.starts_with("LICENCE") || upper.starts_with("COPYING")
+            }));
+        files.extend(conventional);
+        files.sort();
+    }
+    files
+}
+
+/// Builds an inventory from the `*.dist-info/METADATA` files of a site-packages directory
+///
+/// An environment has no project of its own, so every distribution is a normal dependency.
+/// Free-text License fields are mapped to SPDX ids through the database.
+pub fn inventory_from_site_packages(database: &LicenseDatabase, site_packages: &Path) -> Result<Inventory, InventoryError> {
+    let entries = fs::read_dir(site_packages)
+        .map_err(|e| InventoryError::FileReadError(format!("{}: {}", site_packages.display(), e)))?;
+    let mut dist_infos: Vec<PathBuf> = entries.flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_dir() && p.extension().is_some_and(|ext| ext == "dist-info"))
+        .collect();
+    dist_infos.sort();
+
+    let mut distributions: Vec<(PathBuf, DistMetadata)> = Vec::new();
+    for dist_info in dist_infos {
+        let path = dist_info.join("METADATA");
+        match fs::read_to_string(&path) {
+            Ok(content) => distributions.push((dist_info, parse_metadata(&content))),
+            Err(e) => log::warn!("Skipping {}: {}", path.display(), e),
+        }
+    }
+
+    let ids: Vec<String> = distributions.iter()
+        .map(|(dist_info, metadata)| {
+            let name = metadata.name.clone().unwrap_or_else(|| dist_info.file_stem().unwrap_or_default().to_string_lossy().into_owned());
+            normalize_name(&name)
+        })
+        .collect();
+
+    let components = distributions.iter().zip(&ids)
+        .map(|((dist_info, metadata), id)| {
+            let name = metadata.name.clone().unwrap_or_else(|| id.clone());
+            let mut component = Component::new(id, &name, metadata.version.as_deref(), DependencyKind::Normal);
+            for requirement in &metadata.requires {
+                let Some(dependency) = required_name(requirement) else {
+                    continue;
+                };
+                if !ids.contains(&dependency) {
+                    log::debug!("Dependency {} of {} is not installed", dependency, id);
+                } else if !component.dependencies.contains(&dependency) {
+                    component.dependencies.push(dependency);
+                }
+            }
+            set_python_license(&mut component, database, metadata);
+            component.license_files = find_license_files(dist_info, metadata);
+            component.source_dir = Some(dist_info.to_path_buf());
+            component
+        })
+        .collect();
+
+    Ok(Inventory {
+        source: site_packages.display().to_string(),
+        roots: Vec::new(),
+        components,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{parser, temp_dir, write_file};
+
+    fn install(site_packages: &Path, dist: &str, metadata: &str) {
+        write_file(site_packages, &format!("{}.dist-info/METADATA", dist), metadata);
+    }
+
+    #[test]
+    fn parses_metadata_headers() {
+        let metadata = parse_metadata("Metadata-Version: 2.1\nName: Foo\nLicense: BSD\n  line two\nClassifier: License :: OSI Approved :: MIT License\nRequires-Dist: bar (>=1)\n\nLicense: ignored body");
+        assert_eq!(metadata.name.as_deref(), Some("Foo"));
+        assert_eq!(metadata.license.as_deref(), Some("BSD\nline two"));
+        assert_eq!(metadata.license_classifiers, vec!["OSI Approved :: MIT License"]);
+        assert_eq!(metadata.requires, vec!["bar (>=1)"]);
+    }
+
+    #[test]
+    fn requirement_names() {
+        assert_eq!(required_name("Foo_Bar (>=1.0)").as_deref(), Some("foo-bar"));
+        assert_eq!(required_name("pytest; extra == 'test'"), None);
+        assert_eq!(required_name("colorama; sys_platform == 'win32'").as_deref(), Some("colorama"));
+    }
+
+    #[test]
+    fn license_sources_in_order_of_precedence() {
+        let dir = temp_dir("site-packages");
+        install(&dir, "a-1.0", "Name: a\nVersion: 1.0\nLicense-Expression: MIT\nLicense: GPL\nRequires-Dist: B\n");
+        install(&dir, "b-2.0", "Name: b\nVersion: 2.0\nLicense: Apache 2.0\n");
+        install(&dir, "c-3.0", "Name: c\nVersion: 3.0\nLicense: UNKNOWN\nClassifier: License :: OSI Approved :: MIT License\nClassifier: License :: OSI Approved :: Apache Software License\n");
+        install(&dir, "d-4.0", "Name: d\nVersion: 4.0\nLicense: LGPL\n");
+        install(&dir, "e-5.0", "Name: e\nVersion: 5.0\nLicense-Expression: MIT Apache-2.0\nClassifier: License :: OSI Approved :: MIT License\n");
+        write_file(&dir, "d-4.0.dist-info/LICENSE.txt", "GNU LESSER GENERAL PUBLIC LICENSE");
+        let inventory = inventory_from_site_packages(&parser().database(), &dir).unwrap();
+        let expression = |id: &str| inventory.components.iter().find(|c| c.id == id).unwrap().expression.clone();
+
+        assert_eq!(expression("a").as_deref(), Some("MIT"));
+        assert_eq!(expression("b").as_deref(), Some("Apache-2.0"));
+        assert_eq!(expression("c").as_deref(), Some("MIT OR Apache-2.0"));
+        assert_eq!(expression("d").as_deref(), Some("LGPL-2.0-or-later"));
+        assert_eq!(expression("e").as_deref(), Some("MIT"));
+        let e = inventory.components.iter().find(|c| c.id == "e").unwrap();
+        assert_eq!(e.notes[0], "License-Expression 'MIT Apache-2.0' is not a valid SPDX expression: Unexpected 'Apache-2.0' after expression");
+        assert_eq!(inventory.components[0].dependencies, vec!["b"]);
+        assert_eq!(inventory.components[3].license_files, vec![dir.join("d-4.0.dist-info/LICENSE.txt")]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,599 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use crate::debian_copyright::dep5_paragraphs;
+use crate::license_expression_parser::LicenseExpressionParser;
+use crate::models::SpdxExpr;
+use crate::source_scanner::{self, extract_tag_value};
+
+const LICENSE_TAG: &str = "SPDX-License-Identifier:";
+const COPYRIGHT_TAG: &str = "SPDX-FileCopyrightText:";
+const LICENSES_DIR: &str = "LICENSES";
+const REUSE_TOML: &str = "REUSE.toml";
+const DEP5_PATH: &str = ".reuse/dep5";
+
+/// License files REUSE exempts by name, such as `LICENSE`, `LICENCE.md` or `COPYING-GPL`
+static LICENSE_FILE_NAME: OnceLock<Regex> = OnceLock::new();
+
+/// Where the licensing information of a file came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ReuseInfoSource {
+    /// Tags inside the file itself
+    Inline,
+    /// A `<file>.license` sidecar
+    Sidecar,
+    ReuseToml,
+    Dep5,
+}
+
+/// Licensing information resolved for one file
+#[derive(Debug, Clone, Serialize)]
+pub struct ReuseFileInfo {
+    pub path: PathBuf,
+    pub copyright_notices: Vec<String>,
+    pub license_expressions: Vec<String>,
+    pub sources: Vec<ReuseInfoSource>,
+}
+
+/// An expression that is not valid SPDX
+#[derive(Debug, Clone, Serialize)]
+pub struct InvalidExpression {
+    pub path: PathBuf,
+    pub expression: String,
+    pub error: String,
+}
+
+/// A license id that is neither known to the database nor a LicenseRef
+#[derive(Debug, Clone, Serialize)]
+pub struct BadLicense {
+    pub id: String,
+    pub files: Vec<PathBuf>,
+    pub suggestions: Vec<String>,
+}
+
+/// Result of checking a directory against the REUSE specification
+#[derive(Debug, Clone, Serialize)]
+pub struct ReuseLintReport {
+    pub root: PathBuf,
+    pub files: Vec<ReuseFileInfo>,
+    pub files_without_copyright: Vec<PathBuf>,
+    pub files_without_license: Vec<PathBuf>,
+    pub invalid_expressions: Vec<InvalidExpression>,
+    pub bad_licenses: Vec<BadLicense>,
+    pub deprecated_licenses: Vec<String>,
+    /// Licenses used in the project without a text in LICENSES/
+    pub missing_license_texts: Vec<String>,
+    /// Texts in LICENSES/ that no file uses
+    pub unused_license_texts: Vec<String>,
+    /// Problems with the REUSE.toml or DEP-5 configuration itself
+    pub configuration_errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+    #[default]
+    None,
+}
+
+impl OneOrMany {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            OneOrMany::One(value) => vec![value],
+            OneOrMany::Many(values) => values,
+            OneOrMany::None => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Precedence {
+    #[default]
+    Closest,
+    Aggregate,
+    Override,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReuseTomlFile {
+    version: u32,
+    #[serde(default)]
+    annotations: Vec<RawAnnotation>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawAnnotation {
+    path: OneOrMany,
+    #[serde(default)]
+    precedence: Precedence,
+    #[serde(rename = "SPDX-FileCopyrightText", default)]
+    copyright: OneOrMany,
+    #[serde(rename = "SPDX-License-Identifier", default)]
+    license: OneOrMany,
+}
+
+/// A REUSE.toml annotation or DEP-5 stanza, with its globs compiled
+#[derive(Debug)]
+struct Annotation {
+    patterns: Vec<Regex>,
+    precedence: Precedence,
+    copyright: Vec<String>,
+    license: Vec<String>,
+    source: ReuseInfoSource,
+}
+
+impl Annotation {
+    fn matches(&self, path: &str) -> bool {
+        self.patterns.iter().any(|p| p.is_match(path))
+    }
+}
+
+/// Translates a glob into an anchored regex; `*` crosses directories only if `star_crosses_dirs`
+/// (DEP-5 semantics), while `**` always does (REUSE.toml semantics)
+fn glob_to_regex(glob: &str, star_crosses_dirs: bool) -> Option<Regex> {
+    let mut pattern = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    pattern.push_str(&regex::escape(&escaped.to_string()));
+                }
+            }
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                pattern.push_str(".*");
+            }
+            '*' if star_crosses_dirs => pattern.push_str(".*"),
+            '*' => pattern.push_str("[^/]*"),
+            '?' if star_crosses_dirs => pattern.push('.'),
+            _ => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    Regex::new(&pattern).ok()
+}
+
+fn load_reuse_toml(path: &Path, errors: &mut Vec<String>) -> Vec<Annotation> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    let file: ReuseTomlFile = match toml::from_str(&content) {
+        Ok(file) => file,
+        Err(e) => {
+            errors.push(format!("{}: {}", REUSE_TOML, e));
+            return Vec::new();
+        }
+    };
+    if file.version != 1 {
+        errors.push(format!("{}: unsupported version {}", REUSE_TOML, file.version));
+    }
+
+    file.annotations.into_iter()
+        .map(|raw| Annotation {
+            patterns: raw.path.into_vec().iter()
+                .filter_map(|glob| {
+                    let regex = glob_to_regex(glob, false);
+                    if regex.is_none() {
+                        errors.push(format!("{}: invalid path glob '{}'", REUSE_TOML, glob));
+                    }
+                    regex
+                })
+                .collect(),
+            precedence: raw.precedence,
+            copyright: raw.copyright.into_vec(),
+            license: raw.license.into_vec(),
+            source: ReuseInfoSource::ReuseToml,
+        })
+        .collect()
+}
+
+/// Reads the Files/Copyright/License stanzas of a DEP-5 file
+fn load_dep5(path: &Path) -> Vec<Annotation> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+
+    let mut annotations = Vec::new();
+    for fields in dep5_paragraphs(&content) {
+        let Some(files) = fields.get("files") else {
+            continue;
+        };
+        annotations.push(Annotation {
+            patterns: files.join(" ")
+                .split_whitespace()
+                .filter_map(|glob| glob_to_regex(glob, true))
+                .collect(),
+            precedence: Precedence::Aggregate,
+            copyright: fields.get("copyright").cloned().unwrap_or_default()
+                .into_iter()
+                .filter(|line| !line.is_empty() && line != ".")
+                .collect(),
+            // Only the first line of a License field is the expression; the rest is license text
+            license: fields.get("license").and_then(|lines| lines.first().cloned())
+                .filter(|line| !line.is_empty())
+                .into_iter()
+                .collect(),
+            source: ReuseInfoSource::Dep5,
+        });
+    }
+    annotations
+}
+
+/// Returns the copyright notice on a line, if the line is one
+fn copyright_notice(line: &str) -> Option<String> {
+    if let Some(value) = extract_tag_value(line, COPYRIGHT_TAG) {
+        return Some(value);
+    }
+    let statement = line.trim_start_matches(|c: char| !c.is_alphanumeric() && c != '©' && c != '(');
+    let is_notice = statement.starts_with('©')
+        || statement.strip_prefix("Copyright")
+            .is_some_and(|rest| rest.starts_with(char::is_whitespace) || rest.starts_with("(c)") || rest.starts_with("(C)"));
+    if is_notice {
+        Some(statement.trim_end().trim_end_matches("*/").trim_end().to_string())
+    } else {
+        None
+    }
+}
+
+fn inline_info(content: &str) -> (Vec<String>, Vec<String>) {
+    let mut copyright = Vec::new();
+    let mut license = Vec::new();
+    for line in content.lines() {
+        if let Some(value) = extract_tag_value(line, LICENSE_TAG) {
+            license.push(value);
+        } else if let Some(notice) = copyright_notice(line) {
+            copyright.push(notice);
+        }
+    }
+    (copyright, license)
+}
+
+/// Collects license and exception ids referenced by an expression
+fn collect_ids(expr: &SpdxExpr, ids: &mut BTreeSet<String>) {
+    match expr {
+        SpdxExpr::License(id) => {
+            ids.insert(id.clone());
+        }
+        SpdxExpr::And(left, right) | SpdxExpr::Or(left, right) => {
+            collect_ids(left, ids);
+            collect_ids(right, ids);
+        }
+        SpdxExpr::With(license, exception) => {
+            collect_ids(license, ids);
+            ids.insert(exception.clone());
+        }
+    }
+}
+
+/// Returns true for files REUSE itself never requires information for
+///
+/// Files ignored by version control are not detected; pass them with `excluded` instead.
+fn is_ignored(relative: &Path, path: &Path) -> bool {
+    let first = relative.components().next().and_then(|c| c.as_os_str().to_str());
+    let license_file = LICENSE_FILE_NAME.get_or_init(|| Regex::new(r"^(LICEN[CS]E|COPYING)([-.].*)?$").expect("valid regex"));
+    first == Some(LICENSES_DIR)
+        || relative.file_name().and_then(|n| n.to_str()).is_some_and(|n| license_file.is_match(n))
+        || first == Some(".reuse")
+        || relative == Path::new(REUSE_TOML)
+        || relative.extension().is_some_and(|e| e == "license")
+        || path.is_symlink()
+        || fs::metadata(path).map(|m| m.len() == 0).unwrap_or(false)
+}
+
+/// Checks a project directory against the REUSE 3.x specification
+/// `excluded` names (files or directories) are skipped anywhere in the tree; `.git` is always skipped
+pub fn lint_reuse(parser: &LicenseExpressionParser, root: &Path, excluded: &[String]) -> ReuseLintReport {
+    let database = parser.database();
+    let mut configuration_errors = Vec::new();
+    let toml_path = root.join(REUSE_TOML);
+    let dep5_path = root.join(DEP5_PATH);
+    if toml_path.exists() && dep5_path.exists() {
+        configuration_errors.push(format!("Both {} and {} exist; only one may be used", REUSE_TOML, DEP5_PATH));
+    }
+    let mut annotations = load_reuse_toml(&toml_path, &mut configuration_errors);
+    annotations.extend(load_dep5(&dep5_path));
+
+    let mut paths = Vec::new();
+    source_scanner::collect_files(root, excluded, &mut paths);
+
+    let mut files = Vec::new();
+    for path in paths {
+        let relative = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+        if is_ignored(&relative, &path) {
+            continue;
+        }
+        let relative_str = relative.to_string_lossy().replace('\\', "/");
+
+        // The last matching annotation wins
+        let annotation = annotations.iter().rev().find(|a| a.matches(&relative_str));
+
+        let sidecar = PathBuf::from(format!("{}.license", path.display()));
+        let (mut copyright, mut license, mut sources) = (Vec::new(), Vec::new(), Vec::new());
+        if annotation.is_none_or(|a| a.precedence != Precedence::Override) {
+            let (file_source, content) = if sidecar.is_file() {
+                (ReuseInfoSource::Sidecar, source_scanner::read_text_file(&sidecar))
+            } else {
+                (ReuseInfoSource::Inline, source_scanner::read_text_file(&path))
+            };
+            if let Some(content) = content {
+                (copyright, license) = inline_info(&content);
+                if !copyright.is_empty() || !license.is_empty() {
+                    sources.push(file_source);
+                }
+            }
+        }
+
+        if let Some(annotation) = annotation {
+            let aggregate = annotation.precedence != Precedence::Closest;
+            let mut used = false;
+            if aggregate || copyright.is_empty() {
+                copyright.extend(annotation.copyright.iter().cloned());
+                used |= !annotation.copyright.is_empty();
+            }
+            if aggregate || license.is_empty() {
+                license.extend(annotation.license.iter().cloned());
+                used |= !annotation.license.is_empty();
+            }
+            if used {
+                sources.push(annotation.source);
+            }
+        }
+
+        files.push(ReuseFileInfo {
+            path: relative,
+            copyright_notices: copyright,
+            license_expressions: license,
+            sources,
+        });
+    }
+
+    let mut invalid_expressions = Vec::new();
+    let mut used_ids: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    for file in &files {
+        for expression in &file.license_expressions {
+            match parser.parse_strict(expression) {
+                Ok(expr) => {
+                    let mut ids = BTreeSet::new();
+                    collect_ids(&expr, &mut ids);
+                    for id in ids {
+                        used_ids.entry(id).or_default().push(file.path.clone());
+                    }
+                }
+                Err(error) => invalid_expressions.push(InvalidExpression {
+                    path: file.path.clone(),
+                    expression: expression.clone(),
+                    error,
+                }),
+            }
+        }
+    }
+
+    let mut bad_licenses = Vec::new();
+    let mut deprecated_licenses = Vec::new();
+    for (id, id_files) in &used_ids {
+        if id.starts_with("LicenseRef-") {
+            continue;
+        }
+        match database.get(id) {
+            Some(license) if license.name == *id => {
+                if license.is_deprecated {
+                    deprecated_licenses.push(id.clone());
+                }
+            }
+            _ => bad_licenses.push(BadLicense {
+                id: id.clone(),
+                files: id_files.clone(),
+                suggestions: database.suggest_license_ids(id, 3),
+            }),
+        }
+    }
+
+    let license_texts: BTreeSet<String> = fs::read_dir(root.join(LICENSES_DIR))
+        .map(|entries| {
+            entries.flatten()
+                .filter(|e| e.path().is_file())
+                .filter_map(|e| e.path().file_stem().and_then(|s| s.to_str()).map(String::from))
+                .collect()
+        })
+        .unwrap_or_default();
+    let missing_license_texts = used_ids.keys()
+        .filter(|id| !license_texts.contains(*id))
+        .cloned()
+        .collect();
+    let unused_license_texts = license_texts.iter()
+        .filter(|id| !used_ids.contains_key(*id))
+        .cloned()
+        .collect();
+
+    ReuseLintReport {
+        root: root.to_path_buf(),
+        files_without_copyright: files.iter().filter(|f| f.copyright_notices.is_empty()).map(|f| f.path.clone()).collect(),
+        files_without_license: files.iter().filter(|f| f.license_expressions.is_empty()).map(|f| f.path.clone()).collect(),
+        files,
+        invalid_expressions,
+        bad_licenses,
+        deprecated_licenses,
+        missing_license_texts,
+        unused_license_texts,
+        configuration_errors,
+    }
+}
+
+impl ReuseLintReport {
+    pub fn is_compliant(&self) -> bool {
+        self.files_without_copyright.is_empty()
+            && self.files_without_license.is_empty()
+            && self.invalid_expressions.is_empty()
+            && self.bad_licenses.is_empty()
+            && self.deprecated_licenses.is_empty()
+            && self.missing_license_texts.is_empty()
+            && self.unused_license_texts.is_empty()
+            && self.configuration_errors.is_empty()
+    }
+}
+
+fn write_paths(f: &mut fmt::Formatter<'_>, title: &str, paths: &[PathBuf]) -> fmt::Result {
+    if !paths.is_empty() {
+        writeln!(f, "{} ({}):", title, paths.len())?;
+        for path in paths {
+            writeln!(f, "  {}", path.display())?;
+        }
+    }
+    Ok(())
+}
+
+fn write_ids(f: &mut fmt::Formatter<'_>, title: &str, ids: &[String]) -> fmt::Result {
+    if !ids.is_empty() {
+        writeln!(f, "{} ({}):", title, ids.len())?;
+        for id in ids {
+            writeln!(f, "  {}", id)?;
+        }
+    }
+    Ok(())
+}
+
+impl fmt::Display for ReuseLintReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "REUSE Lint for: {}", self.root.display())?;
+
+        write_ids(f, "CONFIGURATION ERRORS", &self.configuration_errors)?;
+
+        if !self.bad_licenses.is_empty() {
+            writeln!(f, "BAD LICENSES ({}):", self.bad_licenses.len())?;
+            for bad in &self.bad_licenses {
+                write!(f, "  {} (used in {} files)", bad.id, bad.files.len())?;
+                if !bad.suggestions.is_empty() {
+                    write!(f, " - did you mean: {}?", bad.suggestions.join(", "))?;
+                }
+                writeln!(f)?;
+            }
+        }
+
+        write_ids(f, "DEPRECATED LICENSES", &self.deprecated_licenses)?;
+        write_ids(f, "MISSING LICENSE TEXTS", &self.missing_license_texts)?;
+        write_ids(f, "UNUSED LICENSE TEXTS", &self.unused_license_texts)?;
+
+        if !self.invalid_expressions.is_empty() {
+            writeln!(f, "INVALID EXPRESSIONS ({}):", self.invalid_expressions.len())?;
+            for invalid in &self.invalid_expressions {
+                writeln!(f, "  {}: '{}' ({})", invalid.path.display(), invalid.expression, invalid.error)?;
+            }
+        }
+
+        write_paths(f, "FILES WITHOUT COPYRIGHT", &self.files_without_copyright)?;
+        write_paths(f, "FILES WITHOUT LICENSE", &self.files_without_license)?;
+
+        let covered = self.files.iter()
+            .filter(|f| !f.copyright_notices.is_empty() && !f.license_expressions.is_empty())
+            .count();
+        writeln!(f, "Summary: {} / {} files with copyright and license information", covered, self.files.len())?;
+        if self.is_compliant() {
+            writeln!(f, "Result: compliant with the REUSE Specification")
+        } else {
+            writeln!(f, "Result: NOT compliant with the REUSE Specification")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{parser, temp_dir, write_file};
+
+    fn reuse_project(name: &str) -> PathBuf {
+        let dir = temp_dir(name);
+        write_file(&dir, "LICENSES/MIT.txt", "MIT License\n");
+        dir
+    }
+
+    #[test]
+    fn compliant_project() {
+        let dir = reuse_project("reuse-compliant");
+        write_file(&dir, "src/lib.rs", "// SPDX-FileCopyrightText: 2024 Jane Doe\n// SPDX-License-Identifier: MIT\n");
+        let report = lint_reuse(&parser(), &dir, &[]);
+        assert!(report.is_compliant(), "{}", report);
+        assert_eq!(report.files[0].sources, vec![ReuseInfoSource::Inline]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn license_files_are_exempt() {
+        let dir = reuse_project("reuse-license-files");
+        write_file(&dir, "LICENSE", "MIT License\n");
+        write_file(&dir, "COPYING.LESSER", "GNU LESSER GENERAL PUBLIC LICENSE\n");
+        write_file(&dir, "vendor/LICENCE-MIT.md", "MIT License\n");
+        write_file(&dir, "LICENSED_FILES.txt", "not a license\n");
+        let report = lint_reuse(&parser(), &dir, &[]);
+        assert_eq!(report.files_without_license, vec![PathBuf::from("LICENSED_FILES.txt")]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn leftover_tokens_make_an_expression_invalid() {
+        let dir = reuse_project("reuse-leftover");
+        write_file(&dir, "src/lib.rs", "// SPDX-FileCopyrightText: 2024 Jane Doe\n// SPDX-License-Identifier: MIT GPL-3.0-only\n");
+        let report = lint_reuse(&parser(), &dir, &[]);
+        assert!(!report.is_compliant());
+        assert_eq!(report.invalid_expressions.len(), 1);
+        assert_eq!(report.invalid_expressions[0].expression, "MIT GPL-3.0-only");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_information_and_license_texts() {
+        let dir = reuse_project("reuse-missing");
+        write_file(&dir, "a.py", "# SPDX-License-Identifier: Apache-2.0\n");
+        write_file(&dir, "b.py", "# Copyright 2024 Jane Doe\n");
+        let report = lint_reuse(&parser(), &dir, &[]);
+        assert_eq!(report.files_without_copyright, vec![PathBuf::from("a.py")]);
+        assert_eq!(report.files_without_license, vec![PathBuf::from("b.py")]);
+        assert_eq!(report.missing_license_texts, vec!["Apache-2.0"]);
+        assert_eq!(report.unused_license_texts, vec!["MIT"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn sidecar_and_reuse_toml_provide_information() {
+        let dir = reuse_project("reuse-annotations");
+        fs::write(dir.join("logo.png"), [0x89, b'P', b'N', b'G', 0]).unwrap();
+        write_file(&dir, "logo.png.license", "SPDX-FileCopyrightText: 2024 Jane Doe\nSPDX-License-Identifier: MIT\n");
+        write_file(&dir, "docs/guide.md", "# Guide\n");
+        write_file(&dir, "REUSE.toml", r#"version = 1
+
+[[annotations]]
+path = "docs/**"
+SPDX-FileCopyrightText = "2024 Jane Doe"
+SPDX-License-Identifier = "MIT"
+"#);
+        let report = lint_reuse(&parser(), &dir, &[]);
+        assert!(report.is_compliant(), "{}", report);
+        let guide = report.files.iter().find(|f| f.path == Path::new("docs/guide.md")).unwrap();
+        assert_eq!(guide.sources, vec![ReuseInfoSource::ReuseToml]);
+        let logo = report.files.iter().find(|f| f.path == Path::new("logo.png")).unwrap();
+        assert_eq!(logo.sources, vec![ReuseInfoSource::Sidecar]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn glob_semantics() {
+        assert!(glob_to_regex("src/*.rs", false).unwrap().is_match("src/lib.rs"));
+        assert!(!glob_to_regex("src/*.rs", false).unwrap().is_match("src/a/lib.rs"));
+        assert!(glob_to_regex("src/**", false).unwrap().is_match("src/a/lib.rs"));
+        assert!(glob_to_regex("src/*", true).unwrap().is_match("src/a/lib.rs"));
+    }
+
+    #[test]
+    fn copyright_notice_forms() {
+        assert_eq!(copyright_notice("// Copyright 2024 Jane Doe").as_deref(), Some("Copyright 2024 Jane Doe"));
+        assert_eq!(copyright_notice("/* © 2024 Jane Doe */").as_deref(), Some("© 2024 Jane Doe"));
+        assert_eq!(copyright_notice("// Copyrighted material"), None);
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use serde::Deserialize;
+use crate::inventory::{Component, DependencyKind, Inventory, InventoryError};
+
+/// Id of the component collecting files that belong to no detected package
+pub const UNPACKAGED_FILES_ID: &str = "unpackaged-files";
+
+/// How scanned files are grouped into components
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScanCodeGrouping {
+    /// By the packages ScanCode detected (`for_packages`)
+    #[default]
+    Package,
+    /// By directory, cut to a number of path components
+    Directory,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScanCodeOptions {
+    /// Detections whose best match scores below this (0-100) are discarded
+    pub min_score: f64,
+    pub group_by: ScanCodeGrouping,
+    /// Path components kept for directory grouping, counting the scanned root
+    pub directory_depth: usize,
+}
+
+impl Default for ScanCodeOptions {
+    fn default() -> Self {
+        ScanCodeOptions {
+            min_score: 0.0,
+            group_by: ScanCodeGrouping::Package,
+            directory_depth: 2,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ScanResults {
+    #[serde(default)]
+    packages: Vec<ScanPackage>,
+    #[serde(default)]
+    files: Vec<ScanFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScanPackage {
+    package_uid: Option<String>,
+    purl: Option<String>,
+    namespace: Option<String>,
+    name: Option<String>,
+    version: Option<String>,
+    declared_license_expression_spdx: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScanFile {
+    path: String,
+    #[serde(rename = "type", default)]
+    file_type: String,
+    detected_license_expression_spdx: Option<String>,
+    #[serde(default)]
+    license_detections: Vec<ScanDetection>,
+    #[serde(default)]
+    for_packages: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScanDetection {
+    license_expression_spdx: Option<String>,
+    #[serde(default)]
+    matches: Vec<ScanMatch>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScanMatch {
+    score: Option<f64>,
+    license_expression_spdx: Option<String>,
+}
+
+/// Findings collected for one component
+#[derive(Debug, Default)]
+struct ScanGroup {
+    expressions: Vec<String>,
+    files: usize,
+    files_with_license: usize,
+    discarded: usize,
+}
+
+impl ScanDetection {
+    /// Best match score; ScanCode scores matches, not whole detections
+    fn score(&self) -> Option<f64> {
+        self.matches.iter().filter_map(|m| m.score).reduce(f64::max)
+    }
+
+    fn expression(&self) -> Option<String> {
+        self.license_expression_spdx.clone().filter(|e| !e.is_empty()).or_else(|| {
+            let parts: Vec<String> = self.matches.iter().filter_map(|m| m.license_expression_spdx.clone()).collect();
+            join_and(parts)
+        })
+    }
+}
+
+/// Combines distinct expressions with AND, parenthesizing compound ones
+fn join_and(mut parts: Vec<String>) -> Option<String> {
+    parts.retain(|p| !p.trim().is_empty());
+    parts.sort();
+    parts.dedup();
+    match parts.len() {
+        0 => None,
+        1 => parts.pop(),
+        _ => Some(parts.iter()
+            .map(|p| if p.contains(char::is_whitespace) { format!("({})", p) } else { p.clone() })
+            .collect::<Vec<_>>()
+            .join(" AND ")),
+    }
+}
+
+/// Directory of a scanned path, cut to `depth` components
+fn directory_key(path: &str, depth: usize) -> String {
+    let components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+    let parent = &components[..components.len().saturating_sub(1)];
+    if parent.is_empty() {
+        return ".".to_string();
+    }
+    parent[..parent.len().min(depth.max(1))].join("/")
+}
+
+/// `namespace/name` of a package, or its purl
+fn package_name(package: &ScanPackage) -> String {
+    match (&package.namespace, &package.name) {
+        (Some(namespace), Some(name)) if !namespace.is_empty() => format!("{}/{}", namespace, name),
+        (_, Some(name)) => name.clone(),
+        _ => package.purl.clone().unwrap_or_default(),
+    }
+}
+
+/// Adds a file's detections to a group, discarding those scoring below `min_score`
+fn add_file(group: &mut ScanGroup, file: &ScanFile, min_score: f64) {
+    group.files += 1;
+    let mut expressions = Vec::new();
+    if file.license_detections.is_empty() {
+        // Trimmed results may only carry the file-level expression
+        expressions.extend(file.detected_license_expression_spdx.clone().filter(|e| !e.is_empty()));
+    }
+    for detection in &file.license_detections {
+        if detection.score().is_some_and(|score| score < min_score) {
+            group.discarded += 1;
+            continue;
+        }
+        expressions.extend(detection.expression());
+    }
+    if !expressions.is_empty() {
+        group.files_with_license += 1;
+    }
+    group.expressions.extend(expressions);
+}
+
+fn group_component(id: &str, name: &str, version: Option<&str>, declared: Option<&str>, group: ScanGroup, options: &ScanCodeOptions) -> Component {
+    let mut component = Component::new(id, name, version, DependencyKind::Normal);
+    component.declared_license = declared.map(String::from).filter(|d| !d.is_empty());
+    if group.files > 0 {
+        component.notes.push(format!("{} of {} scanned files with license detections", group.files_with_license, group.files));
+    }
+    if group.discarded > 0 {
+        component.notes.push(format!("{} detection(s) scoring below {} discarded", group.discarded, options.min_score));
+    }
+    component.expression = join_and(group.expressions);
+    if component.expression.is_none() {
+        if let Some(ref declared) = component.declared_license {
+            component.notes.push("No license detected in files; using the declared license".to_string());
+            component.expression = Some(declared.clone());
+        }
+    }
+    component
+}
+
+/// Builds an inventory from ScanCode toolkit JSON output (`scancode --json`, 32.x format)
+///
+/// Files are grouped by detected package or by directory. Each group's expression is the AND of
+/// the distinct `license_expression_spdx` of the detections kept after the score threshold.
+pub fn inventory_from_scancode(path: &Path, options: &ScanCodeOptions) -> Result<Inventory, InventoryError> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| InventoryError::FileReadError(format!("{}: {}", path.display(), e)))?;
+    let results: ScanResults = serde_json::from_str(&content)
+        .map_err(|e| InventoryError::ParseError(format!("{}: {}", path.display(), e)))?;
+    // `type` is only present in scans run with --info
+    let files = results.files.iter().filter(|f| f.file_type != "directory");
+
+    let mut components = Vec::new();
+    match options.group_by {
+        ScanCodeGrouping::Package => {
+            let mut groups: BTreeMap<&str, ScanGroup> = BTreeMap::new();
+            let mut unpackaged = ScanGroup::default();
+            for file in files {
+                if file.for_packages.is_empty() {
+                    add_file(&mut unpackaged, file, options.min_score);
+                }
+                for package_uid in &file.for_packages {
+                    add_file(groups.entry(package_uid).or_default(), file, options.min_score);
+                }
+            }
+            for package in &results.packages {
+                let Some(uid) = package.package_uid.as_deref().or(package.purl.as_deref()) else {
+                    continue;
+                };
+                let group = groups.remove(uid).unwrap_or_default();
+                components.push(group_component(uid, &package_name(package), package.version.as_deref(),
+                    package.declared_license_expression_spdx.as_deref(), group, options));
+            }
+            // Files claimed by packages missing from the `packages` section
+            for (uid, group) in groups {
+                components.push(group_component(uid, uid, None, None, group, options));
+            }
+            if unpackaged.files > 0 {
+                components.push(group_component(UNPACKAGED_FILES_ID, "Files outside any package", None, None, unpackaged, options));
+            }
+        }
+        ScanCodeGrouping::Directory => {
+            let mut groups: BTreeMap<String, ScanGroup> = BTreeMap::new();
+            for file in files {
+                add_file(groups.entry(directory_key(&file.path, options.directory_depth)).or_default(), file, options.min_score);
+            }
+            for (directory, group) in groups {
+                components.push(group_component(&directory, &directory, None, None, group, options));
+            }
+        }
+    }
+
+    Ok(Inventory {
+        source: path.display().to_string(),
+        roots: Vec::new(),
+        components,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{temp_dir, write_file};
+
+    const SCAN: &str = r#"{
+        "packages": [
+            {"package_uid": "pkg:npm/left-pad@1.0?uuid=1", "purl": "pkg:npm/left-pad@1.0", "name": "left-pad", "version": "1.0", "declared_license_expression_spdx": "WTFPL"},
+            {"package_uid": "pkg:npm/%40acme/util@2.0?uuid=2", "namespace": "@acme", "name": "util", "version": "2.0", "declared_license_expression_spdx": "ISC"}
+        ],
+        "files": [
+            {"path": "project/node_modules", "type": "directory"},
+            {"path": "project/node_modules/left-pad/index.js", "type": "file", "for_packages": ["pkg:npm/left-pad@1.0?uuid=1"],
+             "license_detections": [
+                {"license_expression_spdx": "MIT", "matches": [{"score": 100.0}]},
+                {"license_expression_spdx": "GPL-2.0-only", "matches": [{"score": 40.0}, {"score": 55.5}]}
+             ]},
+            {"path": "project/node_modules/left-pad/LICENSE", "type": "file", "for_packages": ["pkg:npm/left-pad@1.0?uuid=1"],
+             "license_detections": [{"matches": [{"score": 99.0, "license_expression_spdx": "Apache-2.0 OR MIT"}]}]},
+            {"path": "project/node_modules/@acme/util/index.js", "type": "file", "for_packages": ["pkg:npm/%40acme/util@2.0?uuid=2"]},
+            {"path": "project/src/main.c", "type": "file", "detected_license_expression_spdx": "BSD-3-Clause"},
+            {"path": "project/README", "type": "file"}
+        ]
+    }"#;
+
+    fn scan(options: &ScanCodeOptions) -> Inventory {
+        let dir = temp_dir("scancode");
+        let path = write_file(&dir, "scan.json", SCAN);
+        let inventory = inventory_from_scancode(&path, options).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        inventory
+    }
+
+    #[test]
+    fn groups_detections_by_package() {
+        let inventory = scan(&ScanCodeOptions::default());
+        let left_pad = inventory.component("pkg:npm/left-pad@1.0?uuid=1").unwrap();
+        assert_eq!(left_pad.name, "left-pad");
+        assert_eq!(left_pad.declared_license.as_deref(), Some("WTFPL"));
+        assert_eq!(left_pad.expression.as_deref(), Some("(Apache-2.0 OR MIT) AND GPL-2.0-only AND MIT"));
+        assert_eq!(left_pad.notes, vec!["2 of 2 scanned files with license detections"]);
+
+        let util = inventory.component("pkg:npm/%40acme/util@2.0?uuid=2").unwrap();
+        assert_eq!(util.name, "@acme/util");
+        assert_eq!(util.expression.as_deref(), Some("ISC"));
+        assert!(util.notes.contains(&"No license detected in files; using the declared license".to_string()));
+
+        let unpackaged = inventory.component(UNPACKAGED_FILES_ID).unwrap();
+        assert_eq!(unpackaged.expression.as_deref(), Some("BSD-3-Clause"));
+        assert_eq!(unpackaged.notes, vec!["1 of 2 scanned files with license detections"]);
+    }
+
+    #[test]
+    fn discards_low_scoring_detections() {
+        let inventory = scan(&ScanCodeOptions { min_score: 60.0, ..ScanCodeOptions::default() });
+        let left_pad = inventory.component("pkg:npm/left-pad@1.0?uuid=1").unwrap();
+        assert_eq!(left_pad.expression.as_deref(), Some("(Apache-2.0 OR MIT) AND MIT"));
+        assert!(left_pad.notes.contains(&"1 detection(s) scoring below 60 discarded".to_string()));
+    }
+
+    #[test]
+    fn groups_detections_by_directory() {
+        let options = ScanCodeOptions { group_by: ScanCodeGrouping::Directory, directory_depth: 3, ..ScanCodeOptions::default() };
+        let inventory = scan(&options);
+        let ids: Vec<&str> = inventory.components.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, vec!["project", "project/node_modules/@acme", "project/node_modules/left-pad", "project/src"]);
+        assert_eq!(directory_key("README", 2), ".");
+        assert_eq!(directory_key("/a/b/c/d.txt", 0), "a");
+    }
+
+    #[test]
+    fn joins_distinct_expressions() {
+        let parts = |p: &[&str]| p.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(join_and(parts(&["MIT", " ", "MIT"])).as_deref(), Some("MIT"));
+        assert_eq!(join_and(parts(&["MIT OR ISC", "Zlib"])).as_deref(), Some("(MIT OR ISC) AND Zlib"));
+        assert_eq!(join_and(Vec::new()), None);
+    }
+}
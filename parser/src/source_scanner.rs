@@ -0,0 +1,422 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use crate::license_expression_parser::LicenseExpressionParser;
+use crate::models::{LicenseAnalysis, SpdxExpr};
+
+const LICENSE_TAG: &str = "SPDX-License-Identifier:";
+const COPYRIGHT_TAG: &str = "SPDX-FileCopyrightText:";
+
+/// Files larger than this are not scanned for tags
+const MAX_SCANNED_FILE_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Comment terminators that may follow a tag value on the same line
+const COMMENT_CLOSERS: [&str; 7] = ["*/", "-->", "--%>", "#}", "*)", "\"\"\"", "'''"];
+
+/// Comment markers that may precede a tag at the start of a line, longest first
+const COMMENT_OPENERS: [&str; 15] = ["<!--", "<%--", "///", "//!", "//", "/*", "(*", "{#", "--", "\"\"\"", "'''", "#", "*", ";", "%"];
+
+/// SPDX tags found in one file
+#[derive(Debug, Clone, Serialize)]
+pub struct FileScanResult {
+    pub path: PathBuf,
+    pub license_identifiers: Vec<String>,
+    pub copyright_texts: Vec<String>,
+    /// All valid identifiers in the file combined with AND
+    pub expression: Option<String>,
+    pub analysis: Option<LicenseAnalysis>,
+    pub parse_errors: Vec<String>,
+}
+
+/// Aggregate of all files in a directory and its subdirectories
+#[derive(Debug, Clone, Serialize)]
+pub struct DirectoryScanResult {
+    pub path: PathBuf,
+    pub file_count: usize,
+    pub files_without_license: usize,
+    pub expression: Option<String>,
+    pub analysis: Option<LicenseAnalysis>,
+}
+
+/// Result of scanning a source tree for SPDX headers
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceScanReport {
+    pub root: PathBuf,
+    pub files: Vec<FileScanResult>,
+    pub directories: Vec<DirectoryScanResult>,
+    /// Text files without an SPDX-License-Identifier tag
+    pub files_without_license: Vec<PathBuf>,
+    pub skipped_binary_files: usize,
+    /// Files over the scan size limit
+    pub skipped_large_files: usize,
+    /// Files that could not be read, with the I/O error
+    pub unreadable_files: Vec<UnreadableFile>,
+    /// AND of the distinct file expressions across the whole tree
+    pub aggregate_expression: Option<String>,
+    pub aggregate_analysis: Option<LicenseAnalysis>,
+}
+
+/// A file that could not be opened or read
+#[derive(Debug, Clone, Serialize)]
+pub struct UnreadableFile {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+/// Why a file was not read as text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum SkipReason {
+    /// The file could not be opened or read
+    Unreadable(String),
+    /// The file is larger than the scan size limit
+    TooLarge,
+    /// The file contains NUL bytes near its start
+    Binary,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::Unreadable(error) => write!(f, "{}", error),
+            SkipReason::TooLarge => write!(f, "larger than {} bytes", MAX_SCANNED_FILE_SIZE),
+            SkipReason::Binary => write!(f, "binary file"),
+        }
+    }
+}
+
+/// Extracts the value following `tag` on a line, without any trailing comment terminator
+///
+/// The tag must start the line, optionally after comment markers, so tags quoted in code are ignored.
+pub fn extract_tag_value(line: &str, tag: &str) -> Option<String> {
+    let mut rest = line.trim_start();
+    while let Some(marker) = COMMENT_OPENERS.iter().find(|m| rest.starts_with(**m)) {
+        rest = rest[marker.len()..].trim_start();
+    }
+    let mut value = rest.strip_prefix(tag)?.trim();
+    for closer in COMMENT_CLOSERS {
+        if let Some(end) = value.find(closer) {
+            value = value[..end].trim_end();
+        }
+    }
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+/// Reads a file as text, or tells why it was skipped
+pub(crate) fn read_source_file(path: &Path) -> Result<String, SkipReason> {
+    let unreadable = |e: std::io::Error| SkipReason::Unreadable(e.to_string());
+    let mut file = fs::File::open(path).map_err(unreadable)?;
+    if file.metadata().map_err(unreadable)?.len() > MAX_SCANNED_FILE_SIZE {
+        return Err(SkipReason::TooLarge);
+    }
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).map_err(unreadable)?;
+    if bytes.iter().take(8192).any(|b| *b == 0) {
+        return Err(SkipReason::Binary);
+    }
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Reads a file as text; returns None for binary or oversized files and logs unreadable ones
+pub(crate) fn read_text_file(path: &Path) -> Option<String> {
+    match read_source_file(path) {
+        Ok(text) => Some(text),
+        Err(reason @ SkipReason::Unreadable(_)) => {
+            log::warn!("Cannot read {}: {}", path.display(), reason);
+            None
+        }
+        Err(_) => None,
+    }
+}
+
+/// Recursively lists files under `dir`, skipping excluded names, in a stable order
+pub(crate) fn collect_files(dir: &Path, excluded: &[String], files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        log::warn!("Cannot read directory {}", dir.display());
+        return;
+    };
+    let mut paths: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+    paths.sort();
+
+    for path in paths {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        if name == ".git" || excluded.iter().any(|e| e == name) {
+            continue;
+        }
+        if path.is_dir() {
+            collect_files(&path, excluded, files);
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
+}
+
+/// Combines distinct expressions with AND and analyzes the result
+pub(crate) fn aggregate<'a, I>(parser: &LicenseExpressionParser, expressions: I) -> (Option<String>, Option<LicenseAnalysis>)
+where
+    I: IntoIterator<Item = &'a SpdxExpr>,
+{
+    let mut distinct: Vec<&SpdxExpr> = Vec::new();
+    for expr in expressions {
+        if !distinct.contains(&expr) {
+            distinct.push(expr);
+        }
+    }
+    distinct.sort_by_key(|expr| expr.to_string());
+
+    match SpdxExpr::and_all(distinct.into_iter().cloned().collect()) {
+        Some(combined) => {
+            let expression = combined.to_string();
+            let analysis = parser.analyze(&expression);
+            (Some(expression), Some(analysis))
+        }
+        None => (None, None),
+    }
+}
+
+/// Scans a source tree offline for SPDX-License-Identifier and SPDX-FileCopyrightText tags
+/// `excluded` names (files or directories) are skipped anywhere in the tree; `.git` is always skipped
+pub fn scan_source_tree(parser: &LicenseExpressionParser, root: &Path, excluded: &[String]) -> SourceScanReport {
+    let mut paths = Vec::new();
+    collect_files(root, excluded, &mut paths);
+
+    let mut files = Vec::new();
+    let mut file_exprs: Vec<(PathBuf, Option<SpdxExpr>)> = Vec::new();
+    let mut skipped_binary_files = 0;
+    let mut skipped_large_files = 0;
+    let mut unreadable_files = Vec::new();
+
+    for path in paths {
+        let relative = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+        let content = match read_source_file(&path) {
+            Ok(content) => content,
+            Err(SkipReason::Binary) => {
+                skipped_binary_files += 1;
+                continue;
+            }
+            Err(SkipReason::TooLarge) => {
+                skipped_large_files += 1;
+                continue;
+            }
+            Err(SkipReason::Unreadable(error)) => {
+                unreadable_files.push(UnreadableFile { path: relative, error });
+                continue;
+            }
+        };
+
+        let mut result = FileScanResult {
+            path: relative.clone(),
+            license_identifiers: Vec::new(),
+            copyright_texts: Vec::new(),
+            expression: None,
+            analysis: None,
+            parse_errors: Vec::new(),
+        };
+        let mut parsed = Vec::new();
+        for line in content.lines() {
+            if let Some(value) = extract_tag_value(line, LICENSE_TAG) {
+                match parser.parse(&value) {
+                    Ok(expr) => parsed.push(expr),
+                    Err(e) => result.parse_errors.push(format!("{}: {}", value, e)),
+                }
+                result.license_identifiers.push(value);
+            }
+            if let Some(value) = extract_tag_value(line, COPYRIGHT_TAG) {
+                result.copyright_texts.push(value);
+            }
+        }
+
+        let file_expr = SpdxExpr::and_all(parsed);
+        if let Some(ref expr) = file_expr {
+            result.expression = Some(expr.to_string());
+            result.analysis = Some(parser.analyze(&expr.to_string()));
+        }
+        file_exprs.push((relative, file_expr));
+        files.push(result);
+    }
+
+    // Every ancestor directory aggregates the files below it
+    let mut by_directory: BTreeMap<PathBuf, Vec<usize>> = BTreeMap::new();
+    for (index, (path, _)) in file_exprs.iter().enumerate() {
+        let mut dir = path.parent();
+        while let Some(d) = dir {
+            by_directory.entry(d.to_path_buf()).or_default().push(index);
+            dir = d.parent();
+        }
+    }
+
+    let directories: Vec<DirectoryScanResult> = by_directory.into_iter()
+        .map(|(path, indexes)| {
+            let (expression, analysis) = aggregate(parser, indexes.iter().filter_map(|i| file_exprs[*i].1.as_ref()));
+            DirectoryScanResult {
+                path,
+                file_count: indexes.len(),
+                files_without_license: indexes.iter().filter(|i| files[**i].license_identifiers.is_empty()).count(),
+                expression,
+                analysis,
+            }
+        })
+        .collect();
+
+    let files_without_license = files.iter()
+        .filter(|f| f.license_identifiers.is_empty())
+        .map(|f| f.path.clone())
+        .collect();
+
+    let (aggregate_expression, aggregate_analysis) = aggregate(parser, file_exprs.iter().filter_map(|(_, e)| e.as_ref()));
+
+    SourceScanReport {
+        root: root.to_path_buf(),
+        files,
+        directories,
+        files_without_license,
+        skipped_binary_files,
+        skipped_large_files,
+        unreadable_files,
+        aggregate_expression,
+        aggregate_analysis,
+    }
+}
+
+fn display_dir(path: &Path) -> String {
+    if path.as_os_str().is_empty() {
+        ".".to_string()
+    } else {
+        format!("{}/", path.display())
+    }
+}
+
+impl fmt::Display for SourceScanReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Source Scan for: {}", self.root.display())?;
+        writeln!(f, "Files Scanned: {} ({} binary, {} oversized, {} unreadable files skipped)",
+            self.files.len(), self.skipped_binary_files, self.skipped_large_files, self.unreadable_files.len())?;
+
+        writeln!(f, "Files:")?;
+        for file in &self.files {
+            match (&file.expression, &file.analysis) {
+                (Some(expression), Some(analysis)) => {
+                    writeln!(f, "  {}: {} [{}]", file.path.display(), expression, analysis.risk_level)?
+                }
+                _ if !file.parse_errors.is_empty() => writeln!(f, "  {}: INVALID", file.path.display())?,
+                _ => writeln!(f, "  {}: NO LICENSE HEADER", file.path.display())?,
+            }
+            for copyright in &file.copyright_texts {
+                writeln!(f, "    Copyright: {}", copyright)?;
+            }
+            for error in &file.parse_errors {
+                writeln!(f, "    Parse Error: {}", error)?;
+            }
+        }
+
+        writeln!(f, "Directories:")?;
+        for dir in &self.directories {
+            let risk = dir.analysis.as_ref().map(|a| a.risk_level.to_string()).unwrap_or_else(|| "Unknown".to_string());
+            writeln!(f, "  {}: {} [{}] ({} files, {} without license)",
+                display_dir(&dir.path), dir.expression.as_deref().unwrap_or("NONE"), risk, dir.file_count, dir.files_without_license)?;
+        }
+
+        if !self.unreadable_files.is_empty() {
+            writeln!(f, "UNREADABLE FILES ({}):", self.unreadable_files.len())?;
+            for file in &self.unreadable_files {
+                writeln!(f, "  {}: {}", file.path.display(), file.error)?;
+            }
+        }
+
+        if !self.files_without_license.is_empty() {
+            writeln!(f, "FILES WITHOUT LICENSE HEADERS ({}):", self.files_without_license.len())?;
+            for path in &self.files_without_license {
+                writeln!(f, "  {}", path.display())?;
+            }
+        }
+
+        writeln!(f)?;
+        match self.aggregate_analysis {
+            Some(ref analysis) => write!(f, "{}", analysis),
+            None => writeln!(f, "No SPDX license identifiers found"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{parser, temp_dir, write_file};
+
+    #[test]
+    fn tag_value_stops_at_comment_closer() {
+        assert_eq!(extract_tag_value("/* SPDX-License-Identifier: MIT */", LICENSE_TAG).as_deref(), Some("MIT"));
+        assert_eq!(extract_tag_value("<!-- SPDX-License-Identifier: Apache-2.0 -->", LICENSE_TAG).as_deref(), Some("Apache-2.0"));
+        assert_eq!(extract_tag_value("# SPDX-License-Identifier:", LICENSE_TAG), None);
+        assert_eq!(extract_tag_value("no tag here", LICENSE_TAG), None);
+        assert_eq!(extract_tag_value("  //! SPDX-License-Identifier: MIT", LICENSE_TAG).as_deref(), Some("MIT"));
+        assert_eq!(extract_tag_value(" * SPDX-FileCopyrightText: 2024 Jane Doe", COPYRIGHT_TAG).as_deref(), Some("2024 Jane Doe"));
+        assert_eq!(extract_tag_value("-- SPDX-License-Identifier: MIT", LICENSE_TAG).as_deref(), Some("MIT"));
+    }
+
+    #[test]
+    fn tag_value_ignores_tags_inside_code() {
+        assert_eq!(extract_tag_value(r#"const LICENSE_TAG: &str = "SPDX-License-Identifier:";"#, LICENSE_TAG), None);
+        assert_eq!(extract_tag_value(r#"    let header = "// SPDX-License-Identifier: MIT";"#, LICENSE_TAG), None);
+        assert_eq!(extract_tag_value(r#"let tag = "SPDX-FileCopyrightText: 2024 Jane Doe";"#, COPYRIGHT_TAG), None);
+    }
+
+    #[test]
+    fn read_source_file_reports_why_a_file_was_skipped() {
+        let dir = temp_dir("read-text");
+        let text = write_file(&dir, "a.txt", "hello");
+        let binary = dir.join("b.bin");
+        fs::write(&binary, [0x7f, b'E', b'L', b'F', 0, 1]).unwrap();
+        let large = dir.join("large.txt");
+        fs::File::create(&large).unwrap().set_len(MAX_SCANNED_FILE_SIZE + 1).unwrap();
+
+        assert_eq!(read_source_file(&text).as_deref(), Ok("hello"));
+        assert_eq!(read_source_file(&binary), Err(SkipReason::Binary));
+        assert_eq!(read_source_file(&large), Err(SkipReason::TooLarge));
+        assert!(matches!(read_source_file(&dir.join("missing.txt")), Err(SkipReason::Unreadable(_))));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn scan_aggregates_files_and_directories() {
+        let dir = temp_dir("scan");
+        write_file(&dir, "src/main.rs", "// SPDX-License-Identifier: MIT\n// SPDX-FileCopyrightText: 2024 Jane Doe\n");
+        write_file(&dir, "src/lib.rs", "// SPDX-License-Identifier: Apache-2.0\nconst TAG: &str = \"SPDX-License-Identifier: GPL-3.0-only\";\n");
+        write_file(&dir, "docs/readme.md", "no header\n");
+        write_file(&dir, "bad.c", "// SPDX-License-Identifier: MIT AND\n");
+        fs::write(dir.join("logo.png"), [0x89, b'P', b'N', b'G', 0]).unwrap();
+
+        let report = scan_source_tree(&parser(), &dir, &[]);
+        assert_eq!(report.files.len(), 4);
+        assert_eq!(report.skipped_binary_files, 1);
+        assert_eq!(report.skipped_large_files, 0);
+        assert!(report.unreadable_files.is_empty());
+        assert_eq!(report.files_without_license, vec![PathBuf::from("docs/readme.md")]);
+        assert_eq!(report.aggregate_expression.as_deref(), Some("Apache-2.0 AND MIT"));
+
+        let main = report.files.iter().find(|f| f.path == Path::new("src/main.rs")).unwrap();
+        assert_eq!(main.copyright_texts, vec!["2024 Jane Doe"]);
+        let bad = report.files.iter().find(|f| f.path == Path::new("bad.c")).unwrap();
+        assert_eq!(bad.parse_errors.len(), 1);
+
+        let src = report.directories.iter().find(|d| d.path == Path::new("src")).unwrap();
+        assert_eq!((src.file_count, src.files_without_license), (2, 0));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn excluded_names_are_skipped() {
+        let dir = temp_dir("exclude");
+        write_file(&dir, "vendor/dep.rs", "// SPDX-License-Identifier: GPL-3.0-only\n");
+        write_file(&dir, "lib.rs", "// SPDX-License-Identifier: MIT\n");
+        let report = scan_source_tree(&parser(), &dir, &["vendor".to_string()]);
+        assert_eq!(report.aggregate_expression.as_deref(), Some("MIT"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
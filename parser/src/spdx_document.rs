@@ -0,0 +1,651 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use crate::inventory::{resolve_kinds, Component, DependencyKind, Inventory, InventoryError};
+use crate::license_audit::{audit_inventory, AuditReport};
+use crate::license_database::LicenseDatabase;
+use crate::license_expression_parser::LicenseExpressionParser;
+use crate::license_names::NameMatchMethod;
+use crate::license_overlay::{OverlayFile, OverlayLicense};
+use crate::license_policy::LicensePolicy;
+use crate::license_text_matcher::LicenseTextCorpus;
+use crate::models::LicenseAnalysis;
+
+/// Values SPDX uses for "no license information" in license fields
+const NO_ASSERTION: &str = "NOASSERTION";
+const NONE: &str = "NONE";
+
+/// Phrases of a license text or name that suggest its ScanCode category, checked in order
+const CATEGORY_HINTS: &[(&str, &str)] = &[
+    ("lesser general public license", "Copyleft Limited"),
+    ("library general public license", "Copyleft Limited"),
+    ("mozilla public license", "Copyleft Limited"),
+    ("eclipse public license", "Copyleft Limited"),
+    ("general public license", "Copyleft"),
+    ("copyleft", "Copyleft"),
+    ("public domain", "Public Domain"),
+    ("non-commercial", "Free Restricted"),
+    ("noncommercial", "Free Restricted"),
+    ("source available", "Source-available"),
+    ("permission is hereby granted", "Permissive"),
+    ("redistribution and use in source and binary forms", "Permissive"),
+    ("permission to use, copy, modify", "Permissive"),
+];
+
+/// Category of a document-defined license text that matches none of the hints
+const DEFAULT_CUSTOM_CATEGORY: &str = "Proprietary Free";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpdxPackage {
+    #[serde(rename = "SPDXID")]
+    pub spdx_id: String,
+    pub name: String,
+    #[serde(default)]
+    pub version_info: Option<String>,
+    #[serde(default)]
+    pub license_concluded: Option<String>,
+    #[serde(default)]
+    pub license_declared: Option<String>,
+    #[serde(default)]
+    pub license_info_from_files: Vec<String>,
+    #[serde(default)]
+    pub has_files: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpdxFile {
+    #[serde(rename = "SPDXID")]
+    pub spdx_id: String,
+    pub file_name: String,
+    #[serde(default)]
+    pub license_concluded: Option<String>,
+    #[serde(default)]
+    pub license_info_in_files: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpdxRelationship {
+    pub spdx_element_id: String,
+    pub relationship_type: String,
+    pub related_spdx_element: String,
+}
+
+/// A license the document defines itself, referenced as `LicenseRef-...`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractedLicense {
+    pub license_id: String,
+    #[serde(default)]
+    pub extracted_text: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// The parts of an SPDX 2.3 document that carry licensing information
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpdxDocument {
+    #[serde(default)]
+    pub spdx_version: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub document_describes: Vec<String>,
+    #[serde(default)]
+    pub packages: Vec<SpdxPackage>,
+    #[serde(default)]
+    pub files: Vec<SpdxFile>,
+    #[serde(default)]
+    pub relationships: Vec<SpdxRelationship>,
+    #[serde(default)]
+    pub has_extracted_licensing_infos: Vec<ExtractedLicense>,
+}
+
+/// A license field and its analysis; `expression` is None for NOASSERTION and NONE
+#[derive(Debug, Clone, Serialize)]
+pub struct SpdxLicenseField {
+    pub value: String,
+    /// The value with resolved LicenseRefs replaced by SPDX ids
+    pub expression: Option<String>,
+    pub analysis: Option<LicenseAnalysis>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SpdxFileLicenses {
+    pub spdx_id: String,
+    pub file_name: String,
+    pub concluded: Option<SpdxLicenseField>,
+    /// AND of the licenses found in the file
+    pub info_in_file: Option<SpdxLicenseField>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SpdxPackageLicenses {
+    pub spdx_id: String,
+    pub name: String,
+    pub version: Option<String>,
+    pub concluded: Option<SpdxLicenseField>,
+    pub declared: Option<SpdxLicenseField>,
+    /// AND of licenseInfoFromFiles
+    pub from_files: Option<SpdxLicenseField>,
+    pub files: Vec<SpdxFileLicenses>,
+    /// Set when the concluded and declared licenses differ in copyleft strength
+    pub copyleft_mismatch: Option<String>,
+}
+
+/// Licensing of an SPDX document: per-package fields plus an audit of the effective licenses
+#[derive(Debug, Clone, Serialize)]
+pub struct SpdxSbomReport {
+    pub document_name: String,
+    pub spdx_version: String,
+    pub packages: Vec<SpdxPackageLicenses>,
+    /// LicenseRefs from hasExtractedLicensingInfos and the SPDX id they were resolved to
+    pub resolved_license_refs: BTreeMap<String, String>,
+    /// LicenseRefs that match no known license, analyzed as custom licenses of the inferred category
+    pub custom_license_refs: BTreeMap<String, String>,
+    pub unresolved_license_refs: Vec<String>,
+    pub audit: AuditReport,
+}
+
+/// Reads an SPDX 2.x document, in JSON or tag-value form
+pub fn read_spdx_document(path: &Path) -> Result<SpdxDocument, InventoryError> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| InventoryError::FileReadError(format!("{}: {}", path.display(), e)))?;
+    let result = if content.trim_start().starts_with('{') {
+        parse_spdx_json(&content)
+    } else {
+        parse_spdx_tag_value(&content)
+    };
+    result.map_err(|e| InventoryError::ParseError(format!("{}: {}", path.display(), e)))
+}
+
+pub fn parse_spdx_json(content: &str) -> Result<SpdxDocument, String> {
+    let document: SpdxDocument = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    check_version(&document)?;
+    Ok(document)
+}
+
+fn check_version(document: &SpdxDocument) -> Result<(), String> {
+    if document.spdx_version.starts_with("SPDX-2.") {
+        Ok(())
+    } else {
+        Err(format!("unsupported spdxVersion '{}'", document.spdx_version))
+    }
+}
+
+/// The element a tag-value line applies to; tags after PackageName describe that package, and so on
+enum TagSection {
+    Document,
+    Package,
+    File,
+    ExtractedLicense,
+}
+
+/// Parses the tag-value format; files listed after a package belong to it
+pub fn parse_spdx_tag_value(content: &str) -> Result<SpdxDocument, String> {
+    let mut document = SpdxDocument::default();
+    let mut section = TagSection::Document;
+    let mut lines = content.lines();
+    while let Some(line) = lines.next() {
+        let Some((tag, value)) = line.split_once(':') else {
+            continue;
+        };
+        let mut value = value.trim().to_string();
+        // Multi-line values are wrapped in <text>...</text>
+        if let Some(rest) = value.strip_prefix("<text>") {
+            let mut text = rest.to_string();
+            while !text.contains("</text>") {
+                let Some(next) = lines.next() else {
+                    break;
+                };
+                text.push('\n');
+                text.push_str(next);
+            }
+            value = text.split("</text>").next().unwrap_or_default().to_string();
+        }
+
+        match tag.trim() {
+            "SPDXVersion" => document.spdx_version = value,
+            "DocumentName" => document.name = value,
+            "PackageName" => {
+                section = TagSection::Package;
+                document.packages.push(SpdxPackage { name: value, ..SpdxPackage::default() });
+            }
+            "FileName" => {
+                section = TagSection::File;
+                document.files.push(SpdxFile { file_name: value, ..SpdxFile::default() });
+            }
+            "LicenseID" => {
+                section = TagSection::ExtractedLicense;
+                document.has_extracted_licensing_infos.push(ExtractedLicense { license_id: value, ..ExtractedLicense::default() });
+            }
+            "SPDXID" => match section {
+                TagSection::Package => set_last(&mut document.packages, |p| p.spdx_id = value),
+                TagSection::File => {
+                    set_last(&mut document.files, |f| f.spdx_id = value.clone());
+                    if let Some(package) = document.packages.last_mut() {
+                        package.has_files.push(value);
+                    }
+                }
+                _ => {}
+            },
+            "PackageVersion" => set_last(&mut document.packages, |p| p.version_info = Some(value)),
+            "PackageLicenseConcluded" => set_last(&mut document.packages, |p| p.license_concluded = Some(value)),
+            "PackageLicenseDeclared" => set_last(&mut document.packages, |p| p.license_declared = Some(value)),
+            "PackageLicenseInfoFromFiles" => set_last(&mut document.packages, |p| p.license_info_from_files.push(value)),
+            "LicenseConcluded" => set_last(&mut document.files, |f| f.license_concluded = Some(value)),
+            "LicenseInfoInFile" => set_last(&mut document.files, |f| f.license_info_in_files.push(value)),
+            "ExtractedText" => set_last(&mut document.has_extracted_licensing_infos, |l| l.extracted_text = Some(value)),
+            "LicenseName" => set_last(&mut document.has_extracted_licensing_infos, |l| l.name = Some(value)),
+            "Relationship" => {
+                let parts: Vec<&str> = value.split_whitespace().collect();
+                if let [element, relationship_type, related] = parts[..] {
+                    document.relationships.push(SpdxRelationship {
+                        spdx_element_id: element.to_string(),
+                        relationship_type: relationship_type.to_string(),
+                        related_spdx_element: related.to_string(),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    check_version(&document)?;
+    Ok(document)
+}
+
+fn set_last<T>(items: &mut [T], update: impl FnOnce(&mut T)) {
+    if let Some(item) = items.last_mut() {
+        update(item);
+    }
+}
+
+/// Category suggested by the first hint found in `text`, ignoring case and line breaks
+fn infer_category(text: &str) -> Option<&'static str> {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    CATEGORY_HINTS.iter().find(|(hint, _)| text.contains(hint)).map(|(_, category)| *category)
+}
+
+/// LicenseRefs of a document, split by how they can be analyzed
+#[derive(Debug, Default)]
+struct DocumentLicenseRefs {
+    /// Refs matching a known license, with its SPDX id
+    resolved: BTreeMap<String, String>,
+    /// Refs defining a license of their own, with the category inferred from their text or name
+    custom: BTreeMap<String, String>,
+    /// Refs without text whose name matches nothing
+    unresolved: Vec<String>,
+}
+
+/// Resolves the document's own LicenseRefs to SPDX ids, by license text if a corpus is given, else by name
+/// Refs matching no known license are custom licenses; only refs with neither text nor a telling name stay unresolved.
+fn resolve_license_refs(document: &SpdxDocument, database: &LicenseDatabase, corpus: Option<&LicenseTextCorpus>) -> DocumentLicenseRefs {
+    let mut refs = DocumentLicenseRefs::default();
+    for extracted in &document.has_extracted_licensing_infos {
+        let by_text = corpus.zip(extracted.extracted_text.as_deref())
+            .and_then(|(corpus, text)| corpus.identify(text, 1).expression());
+        let by_name = || extracted.name.as_deref()
+            .and_then(|name| database.map_license_name(name))
+            .filter(|matched| matched.method != NameMatchMethod::Suggested)
+            .map(|matched| matched.id);
+        if let Some(id) = by_text.or_else(by_name) {
+            refs.resolved.insert(extracted.license_id.clone(), id);
+            continue;
+        }
+        let text = extracted.extracted_text.as_deref().filter(|t| !t.trim().is_empty() && t.trim() != NO_ASSERTION);
+        let hint = text.into_iter().chain(extracted.name.as_deref()).find_map(infer_category);
+        let category = match (hint, text) {
+            (Some(category), _) => category,
+            (None, Some(_)) => DEFAULT_CUSTOM_CATEGORY,
+            (None, None) => {
+                refs.unresolved.push(extracted.license_id.clone());
+                continue;
+            }
+        };
+        refs.custom.insert(extracted.license_id.clone(), category.to_string());
+    }
+    refs
+}
+
+/// A parser whose database also knows the document's custom licenses
+fn document_parser(parser: &LicenseExpressionParser, document: &SpdxDocument, custom: &BTreeMap<String, String>) -> LicenseExpressionParser {
+    if custom.is_empty() {
+        return parser.clone();
+    }
+    let overlay = OverlayFile {
+        licenses: custom.iter()
+            .map(|(id, category)| OverlayLicense {
+                id: id.clone(),
+                category: Some(category.clone()),
+                aliases: Vec::new(),
+                obligations: Vec::new(),
+                compatible_with: Vec::new(),
+                incompatible_with: Vec::new(),
+            })
+            .collect(),
+    };
+    let origin = format!("SPDX document {}", document.name);
+    match parser.database().with_overlay(&overlay, &origin) {
+        Ok(database) => LicenseExpressionParser::from_shared(Arc::new(database)),
+        Err(e) => {
+            log::warn!("Cannot register the licenses defined by {}: {}", origin, e);
+            parser.clone()
+        }
+    }
+}
+
+/// Analyzes a license field after replacing resolved LicenseRefs; NOASSERTION and NONE have no expression
+fn license_field(parser: &LicenseExpressionParser, value: &str, refs: &BTreeMap<String, String>, license_ref: &Regex) -> SpdxLicenseField {
+    let value = value.trim();
+    if value.is_empty() || value == NO_ASSERTION || value == NONE {
+        return SpdxLicenseField { value: value.to_string(), expression: None, analysis: None };
+    }
+    let expression = license_ref.replace_all(value, |captures: &regex::Captures| {
+        match refs.get(&captures[0]) {
+            Some(id) if id.contains(char::is_whitespace) => format!("({})", id),
+            Some(id) => id.clone(),
+            None => captures[0].to_string(),
+        }
+    }).into_owned();
+    let expression = parser.parse(&expression).map(|expr| expr.to_string()).unwrap_or(expression);
+    SpdxLicenseField {
+        value: value.to_string(),
+        analysis: Some(parser.analyze(&expression)),
+        expression: Some(expression),
+    }
+}
+
+/// Combines several license values with AND, leaving out NOASSERTION and NONE
+fn combined_field(parser: &LicenseExpressionParser, values: &[String], refs: &BTreeMap<String, String>, license_ref: &Regex) -> Option<SpdxLicenseField> {
+    let mut distinct: Vec<&str> = Vec::new();
+    for value in values.iter().map(|v| v.trim()).filter(|v| *v != NO_ASSERTION && *v != NONE && !v.is_empty()) {
+        if !distinct.contains(&value) {
+            distinct.push(value);
+        }
+    }
+    if distinct.is_empty() {
+        return None;
+    }
+    let combined = distinct.iter()
+        .map(|v| if v.contains(char::is_whitespace) { format!("({})", v) } else { v.to_string() })
+        .collect::<Vec<_>>()
+        .join(" AND ");
+    Some(license_field(parser, &combined, refs, license_ref))
+}
+
+fn dependency_edge(relationship_type: &str) -> Option<(bool, DependencyKind)> {
+    // (true if the related element depends on the element, kind)
+    match relationship_type {
+        "DEPENDS_ON" | "CONTAINS" => Some((false, DependencyKind::Normal)),
+        "DEPENDENCY_OF" | "RUNTIME_DEPENDENCY_OF" | "OPTIONAL_DEPENDENCY_OF" | "CONTAINED_BY" => Some((true, DependencyKind::Normal)),
+        "BUILD_DEPENDENCY_OF" => Some((true, DependencyKind::Build)),
+        "DEV_DEPENDENCY_OF" | "TEST_DEPENDENCY_OF" => Some((true, DependencyKind::Dev)),
+        _ => None,
+    }
+}
+
+/// Builds an inventory of the document's packages, licensed by their concluded license,
+/// else their declared license, else the licenses found in their files
+///
+/// Described packages are the supplier's product and are audited like any dependency.
+fn spdx_inventory(document: &SpdxDocument, packages: &[SpdxPackageLicenses], source: &str) -> Inventory {
+    let package_ids: Vec<&str> = document.packages.iter().map(|p| p.spdx_id.as_str()).collect();
+    let mut roots: Vec<String> = document.document_describes.clone();
+    let mut edges: HashMap<String, Vec<(String, DependencyKind)>> = HashMap::new();
+    for relationship in &document.relationships {
+        let (element, related) = (&relationship.spdx_element_id, &relationship.related_spdx_element);
+        if relationship.relationship_type == "DESCRIBES" && element == "SPDXRef-DOCUMENT" {
+            if !roots.contains(related) {
+                roots.push(related.clone());
+            }
+            continue;
+        }
+        let Some((reversed, kind)) = dependency_edge(&relationship.relationship_type) else {
+            continue;
+        };
+        let (from, to) = if reversed { (related, element) } else { (element, related) };
+        if package_ids.contains(&from.as_str()) && package_ids.contains(&to.as_str()) {
+            edges.entry(from.clone()).or_default().push((to.clone(), kind));
+        }
+    }
+    let kinds = resolve_kinds(&roots, &edges);
+
+    let components = packages.iter()
+        .map(|package| {
+            let kind = match kinds.get(&package.spdx_id).copied() {
+                Some(DependencyKind::Root) | None => DependencyKind::Normal,
+                Some(kind) => kind,
+            };
+            let mut component = Component::new(&package.spdx_id, &package.name, package.version.as_deref(), kind);
+            component.dependencies = edges.get(&package.spdx_id).into_iter().flatten().map(|(id, _)| id.clone()).collect();
+            component.declared_license = package.declared.as_ref().filter(|d| d.expression.is_some()).map(|d| d.value.clone());
+
+            let sources = [("concluded", &package.concluded), ("declared", &package.declared), ("from files", &package.from_files)];
+            if let Some((label, field)) = sources.iter().find_map(|(label, field)| field.as_ref().filter(|f| f.expression.is_some()).map(|f| (label, f))) {
+                component.expression = field.expression.clone();
+                if *label != "concluded" {
+                    component.notes.push(format!("No concluded license; using the license {}", label));
+                }
+            }
+            component
+        })
+        .collect();
+
+    Inventory {
+        source: source.to_string(),
+        roots,
+        components,
+    }
+}
+
+/// Analyzes every license field of an SPDX document and audits the packages against a policy
+/// `corpus` identifies the texts of the document's own LicenseRefs; without it they are resolved by name.
+pub fn analyze_spdx_document(
+    parser: &LicenseExpressionParser,
+    document: &SpdxDocument,
+    policy: &LicensePolicy,
+    corpus: Option<&LicenseTextCorpus>,
+    source: &str,
+) -> SpdxSbomReport {
+    let DocumentLicenseRefs { resolved: refs, custom, unresolved: unresolved_license_refs } = resolve_license_refs(document, &parser.database(), corpus);
+    let parser = &document_parser(parser, document, &custom);
+    let license_ref = Regex::new(r"(DocumentRef-[A-Za-z0-9.\-]+:)?LicenseRef-[A-Za-z0-9.\-]+").expect("valid regex");
+    let field = |value: &Option<String>| value.as_deref().map(|v| license_field(parser, v, &refs, &license_ref));
+
+    let packages: Vec<SpdxPackageLicenses> = document.packages.iter()
+        .map(|package| {
+            let mut file_ids = package.has_files.clone();
+            file_ids.extend(document.relationships.iter()
+                .filter(|r| r.relationship_type == "CONTAINS" && r.spdx_element_id == package.spdx_id)
+                .map(|r| r.related_spdx_element.clone()));
+            let files = document.files.iter()
+                .filter(|file| file_ids.contains(&file.spdx_id))
+                .map(|file| SpdxFileLicenses {
+                    spdx_id: file.spdx_id.clone(),
+                    file_name: file.file_name.clone(),
+                    concluded: field(&file.license_concluded),
+                    info_in_file: combined_field(parser, &file.license_info_in_files, &refs, &license_ref),
+                })
+                .collect();
+
+            let concluded = field(&package.license_concluded);
+            let declared = field(&package.license_declared);
+            let strength = |f: &Option<SpdxLicenseField>| f.as_ref().and_then(|f| f.analysis.as_ref()).map(|a| a.strongest_copyleft.clone());
+            let copyleft_mismatch = match (strength(&concluded), strength(&declared)) {
+                (Some(concluded_strength), Some(declared_strength)) if concluded_strength != declared_strength => Some(format!(
+                    "Concluded {} ({}) and declared {} ({}) differ in copyleft strength",
+                    package.license_concluded.as_deref().unwrap_or_default(), concluded_strength,
+                    package.license_declared.as_deref().unwrap_or_default(), declared_strength,
+                )),
+                _ => None,
+            };
+
+            SpdxPackageLicenses {
+                spdx_id: package.spdx_id.clone(),
+                name: package.name.clone(),
+                version: package.version_info.clone(),
+                concluded,
+                declared,
+                from_files: combined_field(parser, &package.license_info_from_files, &refs, &license_ref),
+                files,
+                copyleft_mismatch,
+            }
+        })
+        .collect();
+
+    let inventory = spdx_inventory(document, &packages, source);
+    SpdxSbomReport {
+        document_name: document.name.clone(),
+        spdx_version: document.spdx_version.clone(),
+        packages,
+        resolved_license_refs: refs,
+        custom_license_refs: custom,
+        unresolved_license_refs,
+        audit: audit_inventory(parser, &inventory, policy),
+    }
+}
+
+impl fmt::Display for SpdxSbomReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "SPDX Document: {} ({})", self.document_name, self.spdx_version)?;
+        if !self.resolved_license_refs.is_empty() {
+            writeln!(f, "Resolved License Refs ({}):", self.resolved_license_refs.len())?;
+            for (license_ref, id) in &self.resolved_license_refs {
+                writeln!(f, "  {} -> {}", license_ref, id)?;
+            }
+        }
+        if !self.custom_license_refs.is_empty() {
+            writeln!(f, "Document-defined Licenses ({}):", self.custom_license_refs.len())?;
+            for (license_ref, category) in &self.custom_license_refs {
+                writeln!(f, "  {} [{}, inferred]", license_ref, category)?;
+            }
+        }
+        if !self.unresolved_license_refs.is_empty() {
+            writeln!(f, "UNRESOLVED LICENSE REFS ({}):", self.unresolved_license_refs.len())?;
+            for license_ref in &self.unresolved_license_refs {
+                writeln!(f, "  {}", license_ref)?;
+            }
+        }
+
+        let mismatches: Vec<&SpdxPackageLicenses> = self.packages.iter().filter(|p| p.copyleft_mismatch.is_some()).collect();
+        if !mismatches.is_empty() {
+            writeln!(f, "CONCLUDED/DECLARED MISMATCHES ({}):", mismatches.len())?;
+            for package in mismatches {
+                writeln!(f, "  {}: {}", package.name, package.copyleft_mismatch.as_deref().unwrap_or_default())?;
+            }
+        }
+        write!(f, "{}", self.audit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::NewCopyleftStrength;
+    use crate::test_support::parser;
+
+    const DOCUMENT: &str = r#"{
+  "spdxVersion": "SPDX-2.3",
+  "name": "app-sbom",
+  "documentDescribes": ["SPDXRef-app"],
+  "packages": [
+    {"SPDXID": "SPDXRef-app", "name": "app", "versionInfo": "1.0", "licenseConcluded": "MIT", "licenseDeclared": "MIT"},
+    {"SPDXID": "SPDXRef-eula", "name": "eula-lib", "licenseConcluded": "LicenseRef-Acme-EULA"},
+    {"SPDXID": "SPDXRef-named", "name": "named-lib", "licenseConcluded": "LicenseRef-Apache"},
+    {"SPDXID": "SPDXRef-blank", "name": "blank-lib", "licenseConcluded": "LicenseRef-Blank"},
+    {"SPDXID": "SPDXRef-gpl", "name": "gpl-lib", "licenseConcluded": "NOASSERTION", "licenseDeclared": "GPL-3.0-only"},
+    {"SPDXID": "SPDXRef-test", "name": "test-lib", "licenseConcluded": "Apache-2.0", "licenseDeclared": "GPL-2.0-only"}
+  ],
+  "relationships": [
+    {"spdxElementId": "SPDXRef-app", "relationshipType": "DEPENDS_ON", "relatedSpdxElement": "SPDXRef-eula"},
+    {"spdxElementId": "SPDXRef-test", "relationshipType": "TEST_DEPENDENCY_OF", "relatedSpdxElement": "SPDXRef-app"}
+  ],
+  "hasExtractedLicensingInfos": [
+    {"licenseId": "LicenseRef-Acme-EULA", "name": "Acme EULA", "extractedText": "Copyright Acme Inc. All rights reserved. Internal use only."},
+    {"licenseId": "LicenseRef-Apache", "name": "Apache License 2.0", "extractedText": "NOASSERTION"},
+    {"licenseId": "LicenseRef-Blank", "name": "Something"}
+  ]
+}"#;
+
+    fn analyze(document: &str) -> SpdxSbomReport {
+        let document = parse_spdx_json(document).unwrap();
+        analyze_spdx_document(&parser(), &document, &LicensePolicy::default(), None, "sbom.spdx.json")
+    }
+
+    fn package<'a>(report: &'a SpdxSbomReport, name: &str) -> &'a SpdxPackageLicenses {
+        report.packages.iter().find(|p| p.name == name).unwrap()
+    }
+
+    #[test]
+    fn document_license_refs() {
+        let report = analyze(DOCUMENT);
+        assert_eq!(report.resolved_license_refs.get("LicenseRef-Apache").map(String::as_str), Some("Apache-2.0"));
+        assert_eq!(report.custom_license_refs.get("LicenseRef-Acme-EULA").map(String::as_str), Some("Proprietary Free"));
+        assert_eq!(report.unresolved_license_refs, vec!["LicenseRef-Blank"]);
+
+        let named = package(&report, "named-lib").concluded.as_ref().unwrap();
+        assert_eq!(named.expression.as_deref(), Some("Apache-2.0"));
+        let eula = package(&report, "eula-lib").concluded.as_ref().unwrap();
+        let analysis = eula.analysis.as_ref().unwrap();
+        assert_eq!(analysis.strongest_copyleft, NewCopyleftStrength::ProprietaryFree);
+        assert_eq!(analysis.possible_licenses[0].source, crate::license_database::LicenseSource::Overlay("SPDX document app-sbom".to_string()));
+    }
+
+    #[test]
+    fn custom_license_category_from_text() {
+        assert_eq!(infer_category("This library is free software; you can redistribute it under the GNU Lesser\nGeneral Public License"), Some("Copyleft Limited"));
+        assert_eq!(infer_category("Permission is hereby granted, free of charge"), Some("Permissive"));
+        assert_eq!(infer_category("For non-commercial use only"), Some("Free Restricted"));
+        assert_eq!(infer_category("Acme EULA"), None);
+    }
+
+    #[test]
+    fn packages_and_relationships() {
+        let report = analyze(DOCUMENT);
+        let component = |name: &str| report.audit.components.iter().find(|c| c.component.name == name).unwrap();
+        assert_eq!(component("app").component.kind, DependencyKind::Normal);
+        assert_eq!(component("eula-lib").component.kind, DependencyKind::Normal);
+        assert_eq!(component("test-lib").component.kind, DependencyKind::Dev);
+
+        let gpl = &component("gpl-lib").component;
+        assert_eq!(gpl.expression.as_deref(), Some("GPL-3.0-only"));
+        assert!(gpl.notes.iter().any(|n| n.contains("using the license declared")));
+
+        assert!(package(&report, "test-lib").copyleft_mismatch.is_some());
+        assert!(package(&report, "app").copyleft_mismatch.is_none());
+    }
+
+    #[test]
+    fn tag_value_document() {
+        let document = parse_spdx_tag_value("SPDXVersion: SPDX-2.3
+DocumentName: tv
+PackageName: lib
+SPDXID: SPDXRef-lib
+PackageLicenseConcluded: LicenseRef-Custom
+FileName: ./src/lib.c
+SPDXID: SPDXRef-file
+LicenseInfoInFile: MIT
+LicenseID: LicenseRef-Custom
+ExtractedText: <text>Permission is hereby granted
+to do anything.</text>
+LicenseName: Custom
+").unwrap();
+        assert_eq!(document.packages[0].has_files, vec!["SPDXRef-file"]);
+        assert_eq!(document.has_extracted_licensing_infos[0].extracted_text.as_deref(), Some("Permission is hereby granted\nto do anything."));
+        let report = analyze_spdx_document(&parser(), &document, &LicensePolicy::default(), None, "tv");
+        assert_eq!(report.custom_license_refs.get("LicenseRef-Custom").map(String::as_str), Some("Permissive"));
+        assert_eq!(report.packages[0].files[0].info_in_file.as_ref().unwrap().expression.as_deref(), Some("MIT"));
+    }
+
+    #[test]
+    fn rejects_other_spdx_versions() {
+        assert!(parse_spdx_json(r#"{"spdxVersion": "SPDX-3.0"}"#).is_err());
+    }
+}
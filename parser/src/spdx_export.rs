@@ -0,0 +1,442 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::Serialize;
+use crate::inventory::DependencyKind;
+use crate::license_audit::{AuditReport, ComponentAudit};
+use crate::license_database::LicenseDatabase;
+use crate::license_expression_parser::LicenseExpressionParser;
+use crate::license_text_matcher::strip_front_matter;
+use crate::models::SpdxExpr;
+
+const SPDX_VERSION: &str = "SPDX-2.3";
+const DATA_LICENSE: &str = "CC0-1.0";
+const DOCUMENT_ID: &str = "SPDXRef-DOCUMENT";
+const NO_ASSERTION: &str = "NOASSERTION";
+const LICENSE_REF_PREFIX: &str = "LicenseRef-";
+
+/// How the exporter fills in licenseConcluded
+#[derive(Debug, Clone, Default)]
+pub struct SpdxExportOptions {
+    /// Document name; defaults to the audited source
+    pub document_name: Option<String>,
+    /// Conclude the recommended alternative of OR expressions instead of the whole expression
+    pub conclude_recommended: bool,
+    /// Directory of ScanCode `<license_key>.LICENSE` texts for hasExtractedLicensingInfos
+    pub license_texts_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpdxCreationInfo {
+    pub created: String,
+    pub creators: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpdxPackageOutput {
+    #[serde(rename = "SPDXID")]
+    pub spdx_id: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_info: Option<String>,
+    pub download_location: String,
+    pub files_analyzed: bool,
+    pub license_concluded: String,
+    pub license_declared: String,
+    pub copyright_text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpdxRelationshipOutput {
+    pub spdx_element_id: String,
+    pub relationship_type: String,
+    pub related_spdx_element: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractedLicenseOutput {
+    pub license_id: String,
+    pub extracted_text: String,
+    pub name: String,
+}
+
+/// An SPDX 2.3 JSON document
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpdxDocumentOutput {
+    pub spdx_version: String,
+    pub data_license: String,
+    #[serde(rename = "SPDXID")]
+    pub spdx_id: String,
+    pub name: String,
+    pub document_namespace: String,
+    pub creation_info: SpdxCreationInfo,
+    pub packages: Vec<SpdxPackageOutput>,
+    pub relationships: Vec<SpdxRelationshipOutput>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub has_extracted_licensing_infos: Vec<ExtractedLicenseOutput>,
+}
+
+/// Replaces characters SPDX does not allow in ids (`[A-Za-z0-9.-]`)
+fn sanitize_id(value: &str) -> String {
+    value.chars().map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '-' }).collect()
+}
+
+/// Current UTC time as `YYYY-MM-DDThh:mm:ssZ`
+pub(crate) fn utc_timestamp() -> String {
+    let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+    let (days, time) = (seconds / 86_400, seconds % 86_400);
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, time / 3_600, time % 3_600 / 60, time % 60)
+}
+
+/// SPDX id for a single license or exception id, recording it when it is a LicenseRef
+fn canonical_id(id: &str, database: &LicenseDatabase, refs: &mut BTreeMap<String, Option<String>>) -> String {
+    match database.get(id) {
+        Some(license) => {
+            // ScanCode keys without an SPDX id, and aliases that are not on the SPDX list, are not valid SPDX ids
+            let spdx_id = license.spdx_id.clone()
+                .unwrap_or_else(|| format!("{}scancode-{}", LICENSE_REF_PREFIX, sanitize_id(&license.id)));
+            if spdx_id.starts_with(LICENSE_REF_PREFIX) {
+                refs.insert(spdx_id.clone(), Some(license.id.clone()));
+            }
+            spdx_id
+        }
+        None if id.starts_with(LICENSE_REF_PREFIX) => {
+            refs.entry(id.to_string()).or_insert(None);
+            id.to_string()
+        }
+        None => {
+            let reference = format!("{}{}", LICENSE_REF_PREFIX, sanitize_id(id));
+            refs.entry(reference.clone()).or_insert(None);
+            reference
+        }
+    }
+}
+
+/// Rewrites an expression so every id is on the SPDX list or a LicenseRef, recording the LicenseRefs
+///
+/// Known licenses are written with their SPDX id; ScanCode-only licenses keep their `LicenseRef-scancode-*`
+/// id, or get one when ScanCode has no SPDX id for them. Ids unknown to the database become `LicenseRef-<id>`. An exception that is not on the SPDX list cannot follow WITH and is ANDed instead.
+fn to_spdx_expression(expr: &SpdxExpr, database: &LicenseDatabase, refs: &mut BTreeMap<String, Option<String>>) -> SpdxExpr {
+    match expr {
+        SpdxExpr::License(id) => SpdxExpr::License(canonical_id(id, database, refs)),
+        SpdxExpr::And(left, right) => SpdxExpr::And(
+            Box::new(to_spdx_expression(left, database, refs)),
+            Box::new(to_spdx_expression(right, database, refs)),
+        ),
+        SpdxExpr::Or(left, right) => SpdxExpr::Or(
+            Box::new(to_spdx_expression(left, database, refs)),
+            Box::new(to_spdx_expression(right, database, refs)),
+        ),
+        SpdxExpr::With(license, exception) => {
+            let license = to_spdx_expression(license, database, refs);
+            let exception = canonical_id(exception, database, refs);
+            if exception.starts_with(LICENSE_REF_PREFIX) {
+                SpdxExpr::And(Box::new(license), Box::new(SpdxExpr::License(exception)))
+            } else {
+                SpdxExpr::With(Box::new(license), exception)
+            }
+        }
+    }
+}
+
+/// Top-level alternatives of an OR expression
+fn alternatives(expr: &SpdxExpr) -> Vec<&SpdxExpr> {
+    match expr {
+        SpdxExpr::Or(left, right) => {
+            let mut all = alternatives(left);
+            all.extend(alternatives(right));
+            all
+        }
+        other => vec![other],
+    }
+}
+
+/// The expression to conclude for a component: the normalized expression, or its recommended alternative
+pub(crate) fn concluded_expression(audit: &ComponentAudit, conclude_recommended: bool) -> Option<SpdxExpr> {
+    let analysis = audit.analysis.as_ref()?;
+    let parsed = analysis.parsed_expression.as_ref()?;
+    if conclude_recommended {
+        if let Some(ref choice) = analysis.recommended_choice {
+            let chosen = alternatives(parsed).into_iter()
+                .find(|alternative| alternative.license_ids().iter().any(|id| *id == choice.name || id.eq_ignore_ascii_case(&choice.id)));
+            if let Some(chosen) = chosen {
+                return Some(chosen.clone());
+            }
+        }
+    }
+    Some(parsed.clone())
+}
+
+/// Text of a LicenseRef for hasExtractedLicensingInfos, from the ScanCode texts where available
+fn extracted_text(license_key: Option<&str>, options: &SpdxExportOptions) -> String {
+    let text = license_key.zip(options.license_texts_dir.as_ref())
+        .and_then(|(key, dir)| fs::read_to_string(dir.join(format!("{}.LICENSE", key))).ok())
+        .map(|text| strip_front_matter(&text).trim().to_string())
+        .filter(|text| !text.is_empty());
+    match (text, license_key) {
+        (Some(text), _) => text,
+        (None, Some(key)) => format!("The license text is not included; see https://scancode-licensedb.aboutcode.org/{}.html", key),
+        (None, None) => "The license text was not available when this document was generated.".to_string(),
+    }
+}
+
+/// Builds an SPDX 2.3 document from an audit report
+///
+/// licenseDeclared is the component's own license field, mapped to SPDX ids when it holds license
+/// names, and
+/// licenseConcluded is the normalized (or recommended) expression the audit analyzed.
+pub fn export_spdx(parser: &LicenseExpressionParser, report: &AuditReport, options: &SpdxExportOptions) -> SpdxDocumentOutput {
+    let database = parser.database();
+    let mut refs: BTreeMap<String, Option<String>> = BTreeMap::new();
+
+    let mut spdx_ids: HashMap<&str, String> = HashMap::new();
+    for audit in &report.components {
+        let component = &audit.component;
+        let base = format!("SPDXRef-Package-{}", sanitize_id(&component.display_name()));
+        let mut spdx_id = base.clone();
+        let mut suffix = 1;
+        while spdx_ids.values().any(|existing| *existing == spdx_id) {
+            suffix += 1;
+            spdx_id = format!("{}-{}", base, suffix);
+        }
+        spdx_ids.insert(component.id.as_str(), spdx_id);
+    }
+
+    let mut packages = Vec::new();
+    for audit in &report.components {
+        let component = &audit.component;
+        let declared = component.declared_license.as_deref()
+            .and_then(|declared| {
+                parser.parse(declared).ok()
+                    .filter(|expr| expr.license_ids().iter().all(|id| id.starts_with(LICENSE_REF_PREFIX) || database.get(id).is_some()))
+                    .or_else(|| database.normalize_license_field(declared).and_then(|n| parser.parse(&n.expression).ok()))
+            })
+            .or_else(|| component.expression.as_deref().and_then(|e| parser.parse(e).ok()))
+            .map(|expr| to_spdx_expression(&expr, &database, &mut refs).to_string());
+        let concluded = concluded_expression(audit, options.conclude_recommended)
+            .map(|expr| to_spdx_expression(&expr, &database, &mut refs).to_string());
+
+        packages.push(SpdxPackageOutput {
+            spdx_id: spdx_ids[component.id.as_str()].clone(),
+            name: component.name.clone(),
+            version_info: component.version.clone(),
+            download_location: NO_ASSERTION.to_string(),
+            files_analyzed: false,
+            license_concluded: concluded.unwrap_or_else(|| NO_ASSERTION.to_string()),
+            license_declared: declared.unwrap_or_else(|| NO_ASSERTION.to_string()),
+            copyright_text: NO_ASSERTION.to_string(),
+            comment: (!component.notes.is_empty()).then(|| component.notes.join("\n")),
+        });
+    }
+
+    let mut relationships = Vec::new();
+    let depended_on: Vec<&str> = report.components.iter()
+        .flat_map(|a| a.component.dependencies.iter().map(String::as_str))
+        .collect();
+    let has_roots = report.components.iter().any(|a| a.component.kind == DependencyKind::Root);
+    for audit in &report.components {
+        let component = &audit.component;
+        let described = if has_roots {
+            component.kind == DependencyKind::Root
+        } else {
+            !depended_on.contains(&component.id.as_str())
+        };
+        if described {
+            relationships.push(SpdxRelationshipOutput {
+                spdx_element_id: DOCUMENT_ID.to_string(),
+                relationship_type: "DESCRIBES".to_string(),
+                related_spdx_element: spdx_ids[component.id.as_str()].clone(),
+            });
+        }
+    }
+    for audit in &report.components {
+        let from = &spdx_ids[audit.component.id.as_str()];
+        for dependency in &audit.component.dependencies {
+            let Some(target) = report.components.iter().find(|a| a.component.id == *dependency) else {
+                continue;
+            };
+            let to = &spdx_ids[dependency.as_str()];
+            let (element, relationship_type, related) = match target.component.kind {
+                DependencyKind::Dev => (to, "DEV_DEPENDENCY_OF", from),
+                DependencyKind::Build => (to, "BUILD_DEPENDENCY_OF", from),
+                _ => (from, "DEPENDS_ON", to),
+            };
+            relationships.push(SpdxRelationshipOutput {
+                spdx_element_id: element.clone(),
+                relationship_type: relationship_type.to_string(),
+                related_spdx_element: related.clone(),
+            });
+        }
+    }
+
+    let has_extracted_licensing_infos = refs.iter()
+        .map(|(license_ref, key)| ExtractedLicenseOutput {
+            license_id: license_ref.clone(),
+            extracted_text: extracted_text(key.as_deref(), options),
+            name: key.clone().unwrap_or_else(|| license_ref.trim_start_matches(LICENSE_REF_PREFIX).to_string()),
+        })
+        .collect();
+
+    let name = options.document_name.clone().unwrap_or_else(|| report.source.clone());
+    let mut hasher = DefaultHasher::new();
+    (&report.source, SystemTime::now()).hash(&mut hasher);
+    SpdxDocumentOutput {
+        spdx_version: SPDX_VERSION.to_string(),
+        data_license: DATA_LICENSE.to_string(),
+        spdx_id: DOCUMENT_ID.to_string(),
+        document_namespace: format!("https://spdx.org/spdxdocs/{}-{:016x}", sanitize_id(&name), hasher.finish()),
+        name,
+        creation_info: SpdxCreationInfo {
+            created: utc_timestamp(),
+            creators: vec![format!("Tool: license-expression-copyleft-{}", env!("CARGO_PKG_VERSION"))],
+        },
+        packages,
+        relationships,
+        has_extracted_licensing_infos,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inventory::{Component, Inventory};
+    use crate::license_audit::audit_inventory;
+    use crate::license_policy::LicensePolicy;
+    use crate::spdx_document::parse_spdx_json;
+    use crate::test_support::{parser, temp_dir, write_file};
+
+    fn component(id: &str, kind: DependencyKind, declared: Option<&str>, expression: Option<&str>) -> Component {
+        let mut component = Component::new(id, id, Some("1.0"), kind);
+        component.declared_license = declared.map(String::from);
+        component.expression = expression.map(String::from);
+        component
+    }
+
+    fn report(parser: &LicenseExpressionParser) -> AuditReport {
+        let mut app = component("app", DependencyKind::Root, None, Some("MIT"));
+        app.dependencies = vec!["dual".to_string(), "custom".to_string(), "tester".to_string()];
+        let inventory = Inventory {
+            source: "demo/Cargo.lock".to_string(),
+            roots: vec!["app".to_string()],
+            components: vec![
+                app,
+                component("dual", DependencyKind::Normal, Some("The MIT License"), Some("GPL-3.0-only OR MIT")),
+                component("custom", DependencyKind::Normal, None, Some("Acme_EULA")),
+                component("tester", DependencyKind::Dev, None, None),
+            ],
+        };
+        audit_inventory(parser, &inventory, &LicensePolicy::default())
+    }
+
+    fn package<'a>(document: &'a SpdxDocumentOutput, name: &str) -> &'a SpdxPackageOutput {
+        document.packages.iter().find(|p| p.name == name).unwrap()
+    }
+
+    #[test]
+    fn exports_declared_and_concluded_licenses() {
+        let parser = parser();
+        let document = export_spdx(&parser, &report(&parser), &SpdxExportOptions::default());
+        let dual = package(&document, "dual");
+        assert_eq!(dual.license_declared, "MIT");
+        assert_eq!(dual.license_concluded, "GPL-3.0-only OR MIT");
+        assert_eq!(package(&document, "tester").license_concluded, NO_ASSERTION);
+
+        let recommended = SpdxExportOptions { conclude_recommended: true, ..SpdxExportOptions::default() };
+        let document = export_spdx(&parser, &report(&parser), &recommended);
+        assert_eq!(package(&document, "dual").license_concluded, "MIT");
+    }
+
+    #[test]
+    fn unknown_ids_become_extracted_license_refs() {
+        let parser = parser();
+        let document = export_spdx(&parser, &report(&parser), &SpdxExportOptions::default());
+        assert_eq!(package(&document, "custom").license_concluded, "LicenseRef-Acme-EULA");
+        let extracted = &document.has_extracted_licensing_infos;
+        assert_eq!(extracted.len(), 1);
+        assert_eq!(extracted[0].license_id, "LicenseRef-Acme-EULA");
+        assert_eq!(extracted[0].name, "Acme-EULA");
+    }
+
+    #[test]
+    fn scancode_keys_without_spdx_ids_become_license_refs() {
+        let parser = parser();
+        let inventory = Inventory {
+            source: "requirements.txt".to_string(),
+            roots: Vec::new(),
+            components: vec![
+                component("legacy", DependencyKind::Normal, None, Some("proprietary OR MIT")),
+                component("alias", DependencyKind::Normal, None, Some("GPL")),
+            ],
+        };
+        let report = audit_inventory(&parser, &inventory, &LicensePolicy::default());
+        let document = export_spdx(&parser, &report, &SpdxExportOptions::default());
+        assert_eq!(package(&document, "legacy").license_concluded, "LicenseRef-scancode-proprietary OR MIT");
+        assert_eq!(package(&document, "alias").license_concluded, "GPL-1.0-or-later");
+        let extracted: Vec<&str> = document.has_extracted_licensing_infos.iter().map(|e| e.license_id.as_str()).collect();
+        assert_eq!(extracted, vec!["LicenseRef-scancode-proprietary"]);
+    }
+
+    #[test]
+    fn relationships_follow_dependency_kinds() {
+        let parser = parser();
+        let document = export_spdx(&parser, &report(&parser), &SpdxExportOptions::default());
+        let relationship = |kind: &str| document.relationships.iter()
+            .filter(|r| r.relationship_type == kind)
+            .map(|r| (r.spdx_element_id.as_str(), r.related_spdx_element.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(relationship("DESCRIBES"), vec![(DOCUMENT_ID, "SPDXRef-Package-app-1.0")]);
+        assert_eq!(relationship("DEV_DEPENDENCY_OF"), vec![("SPDXRef-Package-tester-1.0", "SPDXRef-Package-app-1.0")]);
+        assert_eq!(relationship("DEPENDS_ON").len(), 2);
+    }
+
+    #[test]
+    fn exported_documents_read_back_as_spdx() {
+        let parser = parser();
+        let options = SpdxExportOptions { document_name: Some("demo sbom".to_string()), ..SpdxExportOptions::default() };
+        let document = export_spdx(&parser, &report(&parser), &options);
+        assert!(document.document_namespace.starts_with("https://spdx.org/spdxdocs/demo-sbom-"));
+
+        let read = parse_spdx_json(&serde_json::to_string(&document).unwrap()).unwrap();
+        assert_eq!(read.packages.len(), 4);
+        assert_eq!(read.has_extracted_licensing_infos.len(), 1);
+    }
+
+    #[test]
+    fn extracted_texts_come_from_scancode_texts() {
+        let dir = temp_dir("spdx-texts");
+        write_file(&dir, "acme.LICENSE", "---\nkey: acme\n---\nAcme license text\n");
+        let options = SpdxExportOptions { license_texts_dir: Some(dir.clone()), ..SpdxExportOptions::default() };
+        assert_eq!(extracted_text(Some("acme"), &options), "Acme license text");
+        assert!(extracted_text(Some("other"), &options).ends_with("scancode-licensedb.aboutcode.org/other.html"));
+        assert!(extracted_text(None, &options).contains("not available"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn sanitizes_ids_and_formats_timestamps() {
+        assert_eq!(sanitize_id("@scope/pkg@1.0.0+build"), "-scope-pkg-1.0.0-build");
+        let timestamp = utc_timestamp();
+        assert_eq!(timestamp.len(), 20);
+        assert!(timestamp.ends_with('Z') && timestamp.as_bytes()[10] == b'T');
+    }
+}
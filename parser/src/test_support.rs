@@ -0,0 +1,35 @@
+//! Helpers shared by unit tests
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::license_expression_parser::LicenseExpressionParser;
+
+/// The license index checked in at the repository root
+pub(crate) fn index_path() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../index.json")
+}
+
+/// A parser backed by the repository's license index
+pub(crate) fn parser() -> LicenseExpressionParser {
+    LicenseExpressionParser::with_database::<&Path>(&index_path(), &[]).unwrap()
+}
+
+/// A fresh, empty directory under the system temp dir, unique per call
+pub(crate) fn temp_dir(name: &str) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let dir = std::env::temp_dir().join(format!(
+        "license-expression-copyleft-{}-{}-{}", std::process::id(), COUNTER.fetch_add(1, Ordering::Relaxed), name
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Writes `content` to `dir/relative`, creating parent directories
+pub(crate) fn write_file(dir: &Path, relative: &str, content: &str) -> PathBuf {
+    let path = dir.join(relative);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, content).unwrap();
+    path
+}
@@ -1,21 +1,793 @@
-use clap::Parser;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Arc;
+use clap::error::ErrorKind;
+use clap::{Args as ClapArgs, CommandFactory, Parser, Subcommand, ValueEnum};
+use regex::Regex;
+use parser::audit_diff;
+use parser::cargo_lock::{self, DEFAULT_VENDOR_DIR};
+use parser::cargo_metadata;
+use parser::compiled_license_database;
+use parser::cyclonedx_bom;
+use parser::debian_copyright;
+use parser::inventory::{DependencyKind, Inventory};
+use parser::license_audit;
+use parser::license_database::{self, LicenseDatabase, LicenseStore, DEFAULT_INDEX_PATH};
+use parser::license_database_diff;
 use parser::license_expression_parser::LicenseExpressionParser;
+use parser::license_graph::{self, LicenseGraph, Linkage};
+use parser::license_policy::{self, LicensePolicy};
+use parser::license_query::{IdPattern, LicenseQuery, LicenseSortKey};
+use parser::license_text_matcher::{LicenseTextCorpus, DEFAULT_LICENSE_TEXTS_DIR};
+use parser::license_waivers;
+use parser::maven_poms;
+use parser::models::{NewCopyleftStrength, RiskLevel};
+use parser::notice_file::{self, NoticeFormat, NoticeOptions};
+use parser::npm_packages;
+use parser::outbound_license::{self, OutboundInput};
+use parser::python_packages;
+use parser::reuse_lint;
+use parser::scancode_results::{self, ScanCodeGrouping, ScanCodeOptions};
+use parser::source_scanner;
+use parser::spdx_document;
+use parser::spdx_export::{self, SpdxExportOptions};
 
 #[derive(Parser)]
 #[command(name = "license-expression-copyleft")]
 #[command(about = "Analyze license expressions for copyleft strength and compatibility")]
+#[command(subcommand_negates_reqs = true)]
+#[command(override_usage = "license-expression-copyleft [OPTIONS] <LICENSE_EXPRESSION>\n       license-expression-copyleft [OPTIONS] <COMMAND>")]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// The license expression to analyze
-    #[arg(value_name = "LICENSE_EXPRESSION")]
-    license_expression: String,
+    #[arg(value_name = "LICENSE_EXPRESSION", required = true)]
+    license_expression: Option<String>,
+
+    /// License index to load
+    #[arg(long = "database", value_name = "FILE", global = true, default_value = DEFAULT_INDEX_PATH)]
+    database: PathBuf,
+
+    /// License overlay file layered on top of the bundled database (repeatable, later files win)
+    #[arg(long = "overlay", value_name = "FILE", global = true)]
+    overlays: Vec<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Inspect and compare license database releases
+    Db {
+        #[command(subcommand)]
+        command: DbCommand,
+    },
+    /// List licenses in the database matching the given filters
+    List {
+        /// Only licenses in this category, e.g. "Copyleft Limited" or copyleft-limited (repeatable)
+        #[arg(long = "category", value_name = "CATEGORY", value_parser = parse_list_category)]
+        categories: Vec<NewCopyleftStrength>,
+        /// Only exceptions (true) or only non-exceptions (false)
+        #[arg(long, value_name = "BOOL")]
+        exception: Option<bool>,
+        /// Only deprecated (true) or only non-deprecated (false) licenses
+        #[arg(long, value_name = "BOOL")]
+        deprecated: Option<bool>,
+        /// Case-insensitive glob over ids and aliases, e.g. "*GPL*"
+        #[arg(long, value_name = "PATTERN", conflicts_with = "regex")]
+        glob: Option<String>,
+        /// Regular expression over ids and aliases
+        #[arg(long, value_name = "REGEX")]
+        regex: Option<Regex>,
+        #[arg(long, value_enum, default_value_t = SortKey::Id)]
+        sort: SortKey,
+        /// Sort in descending order
+        #[arg(long)]
+        desc: bool,
+        /// Number of matching licenses to skip
+        #[arg(long, default_value_t = 0)]
+        offset: usize,
+        /// Maximum number of licenses to show
+        #[arg(long)]
+        limit: Option<usize>,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Identify the license(s) of a license file by comparing it with known license texts
+    Identify {
+        /// License file to identify
+        file: PathBuf,
+        /// Directory of ScanCode <license_key>.LICENSE texts
+        #[arg(long = "license-texts", value_name = "DIR", default_value = DEFAULT_LICENSE_TEXTS_DIR)]
+        license_texts: PathBuf,
+        /// Number of candidates to show
+        #[arg(long, default_value_t = 5)]
+        top: usize,
+    },
+    /// Scan a source tree for SPDX-License-Identifier headers and analyze the result
+    Scan {
+        /// Root directory of the source tree
+        dir: PathBuf,
+        /// File or directory name to skip anywhere in the tree (repeatable); .git is always skipped
+        #[arg(long = "exclude", value_name = "NAME")]
+        excluded: Vec<String>,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Audit the licenses of a project's dependencies; exits with status 1 if the policy fails
+    Audit {
+        #[command(subcommand)]
+        command: AuditCommand,
+    },
+    /// Check a project against the REUSE Specification; exits with status 1 if it is not compliant
+    ReuseLint {
+        /// Root directory of the project
+        dir: PathBuf,
+        /// File or directory name to skip anywhere in the tree (repeatable); .git is always skipped
+        #[arg(long = "exclude", value_name = "NAME")]
+        excluded: Vec<String>,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Find the licenses a project could be released under, given its dependencies' expressions
+    Outbound {
+        /// Dependency license expressions
+        expressions: Vec<String>,
+        /// File with one dependency per line, as "EXPRESSION" or "NAME: EXPRESSION" (# starts a comment)
+        #[arg(long = "from-file", value_name = "FILE")]
+        from_file: Option<PathBuf>,
+        /// Outbound license to consider (repeatable) [default: common open source licenses]
+        #[arg(long = "candidate", value_name = "LICENSE")]
+        candidates: Vec<String>,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Compare two audits and report added and removed components and license changes
+    Diff {
+        /// Earlier SBOM, lock file or JSON audit report (`audit ... --format json`)
+        old: PathBuf,
+        /// Later SBOM, lock file or JSON audit report
+        new: PathBuf,
+        /// Exit with status 1 if a license change or the overall risk increases risk
+        #[arg(long = "fail-on-increase")]
+        fail_on_increase: bool,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Propagate license obligations through a dependency graph file with linkage types
+    Graph {
+        /// JSON graph of nodes (with license expressions) and edges (with linkage types)
+        graph: PathBuf,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Write a CycloneDX BOM back with the copyleft analysis of each component as properties
+    EnrichCyclonedx {
+        /// CycloneDX BOM (.cdx.json or .cdx.xml)
+        bom: PathBuf,
+        /// Where to write the enriched BOM [default: stdout]
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+        #[command(flatten)]
+        policy: PolicyOptions,
+    },
+    /// Analyze Debian machine-readable (DEP-5) copyright files of an unpacked root filesystem
+    DebianCopyright {
+        /// Root filesystem (usr/share/doc/*/copyright is read) or a single copyright file
+        path: PathBuf,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+}
+
+#[derive(Subcommand)]
+enum AuditCommand {
+    /// Audit a Rust workspace from `cargo metadata --format-version 1` output
+    Cargo {
+        /// File holding the cargo metadata JSON, or - for stdin
+        #[arg(default_value = "-")]
+        metadata: PathBuf,
+        #[command(flatten)]
+        options: AuditOptions,
+    },
+    /// Audit a Rust workspace from Cargo.lock and `cargo vendor` sources, without running cargo
+    CargoLock {
+        #[arg(default_value = "Cargo.lock")]
+        lockfile: PathBuf,
+        /// Directory of vendored crates [default: vendor next to the lock file]
+        #[arg(long, value_name = "DIR")]
+        vendor: Option<PathBuf>,
+        #[command(flatten)]
+        options: AuditOptions,
+    },
+    /// Audit a supplier SBOM in CycloneDX 1.4-1.6 JSON or XML form
+    Cyclonedx {
+        /// CycloneDX BOM (.cdx.json or .cdx.xml)
+        bom: PathBuf,
+        #[command(flatten)]
+        options: AuditOptions,
+    },
+    /// Audit the Maven artifacts whose POMs are below a directory, such as ~/.m2/repository
+    Maven {
+        /// Local repository or directory of POMs (pom.xml files are the project)
+        dir: PathBuf,
+        #[command(flatten)]
+        options: AuditOptions,
+    },
+    /// Audit an npm project from package-lock.json (v2/v3) or its node_modules directory
+    Npm {
+        /// package-lock.json, or a project directory (its lock file is preferred over node_modules)
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Walk node_modules even if a lock file exists
+        #[arg(long)]
+        node_modules: bool,
+        #[command(flatten)]
+        options: AuditOptions,
+    },
+    /// Audit the results of a ScanCode toolkit scan (`scancode --json`)
+    Scancode {
+        /// ScanCode JSON results
+        results: PathBuf,
+        /// Discard license detections whose best match scores below this (0-100)
+        #[arg(long = "min-score", value_name = "SCORE", default_value_t = 0.0)]
+        min_score: f64,
+        #[arg(long = "group-by", value_enum, default_value_t = GroupBy::Package)]
+        group_by: GroupBy,
+        /// Path components kept when grouping by directory, counting the scanned root
+        #[arg(long, default_value_t = 2)]
+        depth: usize,
+        #[command(flatten)]
+        options: AuditOptions,
+    },
+    /// Audit a supplier SBOM in SPDX 2.x JSON or tag-value form
+    Spdx {
+        /// SPDX document (.spdx.json or .spdx)
+        document: PathBuf,
+        #[command(flatten)]
+        options: AuditOptions,
+    },
+    /// Audit the distributions installed in a Python site-packages directory
+    Python {
+        /// site-packages directory holding *.dist-info entries
+        site_packages: PathBuf,
+        #[command(flatten)]
+        options: AuditOptions,
+    },
+}
+
+/// Policy selection shared by the commands that evaluate a policy
+#[derive(ClapArgs)]
+struct PolicyOptions {
+    /// JSON policy file (see README: License Policies)
+    #[arg(long, value_name = "FILE")]
+    policy: Option<PathBuf>,
+    /// Fail when a component reaches this risk level (overrides the policy file)
+    #[arg(long = "fail-on", value_name = "RISK")]
+    fail_on: Option<RiskLevel>,
+    /// JSON baseline of accepted findings (see README: Waivers)
+    #[arg(long, value_name = "FILE")]
+    waivers: Option<PathBuf>,
+}
+
+/// Options shared by every audit input
+#[derive(ClapArgs)]
+struct AuditOptions {
+    #[command(flatten)]
+    policy: PolicyOptions,
+    /// Directory of ScanCode <license_key>.LICENSE texts used to identify license files
+    #[arg(long = "license-texts", value_name = "DIR")]
+    license_texts: Option<PathBuf>,
+    /// Also write the audited components as an SPDX 2.3 JSON document
+    #[arg(long = "spdx-output", value_name = "FILE")]
+    spdx_output: Option<PathBuf>,
+    /// Conclude the recommended alternative of OR expressions in the SPDX output
+    #[arg(long = "conclude-choice", requires = "spdx_output")]
+    conclude_choice: bool,
+    /// Also write a third-party NOTICE file attributing the shipped components
+    #[arg(long = "notice-output", value_name = "FILE")]
+    notice_output: Option<PathBuf>,
+    /// Format of the NOTICE file [default: from its extension, .md or .html, else text]
+    #[arg(long = "notice-format", value_enum, requires = "notice_output")]
+    notice_format: Option<NoticeFileFormat>,
+    /// Also propagate license obligations through the dependency graph to the project
+    #[arg(long)]
+    propagation: bool,
+    /// Also list the licenses the project could be released under, given its shipped dependencies
+    #[arg(long)]
+    outbound: bool,
+    /// Linkage assumed for shipped dependencies during propagation
+    #[arg(long = "shipped-linkage", value_enum, default_value_t = ShippedLinkage::Static, requires = "propagation")]
+    shipped_linkage: ShippedLinkage,
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    #[value(alias = "table")]
+    Text,
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SortKey {
+    Id,
+    Category,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ShippedLinkage {
+    Static,
+    Dynamic,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum GroupBy {
+    Package,
+    Directory,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum NoticeFileFormat {
+    Text,
+    Markdown,
+    Html,
+}
+
+#[derive(Subcommand)]
+enum DbCommand {
+    /// Show version metadata of the license database
+    Info,
+    /// Compile the JSON license index into the memory-mappable binary format
+    Compile {
+        /// Output path of the compiled database
+        output: PathBuf,
+    },
+    /// List added, removed and re-categorized licenses between two releases
+    Diff {
+        /// Previous release of the license index
+        old: PathBuf,
+        /// New release of the license index
+        new: PathBuf,
+        /// File of previously analyzed expressions, one per line, to re-assess against both releases
+        #[arg(long = "expressions", value_name = "FILE")]
+        expressions: Option<PathBuf>,
+    },
+}
+
+/// Parses a `list --category` value, rejecting CLA which no license in the database carries
+fn parse_list_category(value: &str) -> Result<NewCopyleftStrength, String> {
+    match value.parse()? {
+        NewCopyleftStrength::CLA => Err("no license has the CLA category: ScanCode CLA licenses are loaded as Proprietary Free".to_string()),
+        category => Ok(category),
+    }
 }
 
 fn main() {
     env_logger::init();
 
     let args = Args::parse();
+    // Global options may appear before or after a subcommand, so a stray expression is only caught here
+    if let (Some(_), Some(expression)) = (&args.command, &args.license_expression) {
+        Args::command()
+            .error(ErrorKind::ArgumentConflict, format!("unexpected license expression '{}' before a subcommand", expression))
+            .exit();
+    }
+
+    match args.command {
+        Some(Command::Db { command: DbCommand::Info }) => {
+            let (store, version) = LicenseStore::open(&args.database).unwrap_or_else(|e| exit_with_error(e));
+            let format = match store {
+                LicenseStore::Index(_) => "json index",
+                LicenseStore::Compiled(_) => "compiled",
+            };
+            println!("{} ({}): {}", args.database.display(), format, version);
+        }
+        Some(Command::Db { command: DbCommand::Compile { output } }) => {
+            let version = compiled_license_database::compile_license_index_file(&args.database, &output)
+                .unwrap_or_else(|e| exit_with_error(e));
+            println!("Compiled {} into {}: {}", args.database.display(), output.display(), version);
+        }
+        Some(Command::Db { command: DbCommand::Diff { old, new, expressions } }) => {
+            run_db_diff(&old, &new, expressions.as_deref(), &args.overlays);
+        }
+        Some(Command::List { categories, exception, deprecated, glob, regex, sort, desc, offset, limit, format }) => {
+            let database = LicenseDatabase::open(&args.database, &args.overlays).unwrap_or_else(|e| exit_with_error(e));
+            let query = LicenseQuery {
+                copyleft_strengths: categories,
+                is_exception: exception,
+                is_deprecated: deprecated,
+                id_pattern: glob.map(IdPattern::Glob).or(regex.map(IdPattern::Regex)),
+                sort_by: match sort {
+                    SortKey::Id => LicenseSortKey::Id,
+                    SortKey::Category => LicenseSortKey::CopyleftStrength,
+                },
+                descending: desc,
+                offset,
+                limit,
+            };
+            let result = database.query(&query);
+            match format {
+                OutputFormat::Text => print!("{}", result),
+                OutputFormat::Json => print_json(&result),
+            }
+        }
+        Some(Command::Identify { file, license_texts, top }) => {
+            let database = Arc::new(LicenseDatabase::open(&args.database, &args.overlays).unwrap_or_else(|e| exit_with_error(e)));
+            let corpus = LicenseTextCorpus::load(&license_texts, &database).unwrap_or_else(|e| exit_with_error(e));
+            let text = fs::read_to_string(&file)
+                .unwrap_or_else(|e| exit_with_error(format!("{}: {}", file.display(), e)));
+
+            let identification = corpus.identify(&text, top);
+            println!("{}", identification);
+            if let Some(expression) = identification.expression() {
+                let parser = LicenseExpressionParser::from_shared(database);
+                println!("{}", parser.analyze(&expression));
+            }
+        }
+        Some(Command::Scan { dir, excluded, format }) => {
+            let parser = LicenseExpressionParser::with_database(&args.database, &args.overlays)
+                .unwrap_or_else(|e| exit_with_error(e));
+            let report = source_scanner::scan_source_tree(&parser, &dir, &excluded);
+            match format {
+                OutputFormat::Text => print!("{}", report),
+                OutputFormat::Json => print_json(&report),
+            }
+        }
+        Some(Command::Audit { command: AuditCommand::Cargo { metadata, options } }) => {
+            let content = read_input(&metadata);
+            let inventory = cargo_metadata::inventory_from_cargo_metadata(&content, &metadata.display().to_string())
+                .unwrap_or_else(|e| exit_with_error(e));
+            run_audit(&load_parser(&args.database, &args.overlays), inventory, &options);
+        }
+        Some(Command::Audit { command: AuditCommand::CargoLock { lockfile, vendor, options } }) => {
+            let vendor = vendor.unwrap_or_else(|| lockfile.parent().unwrap_or(Path::new("")).join(DEFAULT_VENDOR_DIR));
+            let inventory = cargo_lock::inventory_from_cargo_lock(&lockfile, &vendor).unwrap_or_else(|e| exit_with_error(e));
+            run_audit(&load_parser(&args.database, &args.overlays), inventory, &options);
+        }
+        Some(Command::Audit { command: AuditCommand::Cyclonedx { bom, options } }) => {
+            let parser = load_parser(&args.database, &args.overlays);
+            let inventory = cyclonedx_bom::inventory_from_cyclonedx(&parser.database(), &bom).unwrap_or_else(|e| exit_with_error(e));
+            run_audit(&parser, inventory, &options);
+        }
+        Some(Command::Audit { command: AuditCommand::Maven { dir, options } }) => {
+            let parser = load_parser(&args.database, &args.overlays);
+            let inventory = maven_poms::inventory_from_maven_poms(&parser.database(), &dir).unwrap_or_else(|e| exit_with_error(e));
+            run_audit(&parser, inventory, &options);
+        }
+        Some(Command::Audit { command: AuditCommand::Npm { path, node_modules, options } }) => {
+            let inventory = if node_modules {
+                npm_packages::inventory_from_node_modules(&path)
+            } else {
+                npm_packages::inventory_from_npm_project(&path)
+            };
+            run_audit(&load_parser(&args.database, &args.overlays), inventory.unwrap_or_else(|e| exit_with_error(e)), &options);
+        }
+        Some(Command::Audit { command: AuditCommand::Python { site_packages, options } }) => {
+            let parser = load_parser(&args.database, &args.overlays);
+            let inventory = python_packages::inventory_from_site_packages(&parser.database(), &site_packages)
+                .unwrap_or_else(|e| exit_with_error(e));
+            run_audit(&parser, inventory, &options);
+        }
+        Some(Command::Audit { command: AuditCommand::Scancode { results, min_score, group_by, depth, options } }) => {
+            let parser = load_parser(&args.database, &args.overlays);
+            let scan_options = ScanCodeOptions {
+                min_score,
+                group_by: match group_by {
+                    GroupBy::Package => ScanCodeGrouping::Package,
+                    GroupBy::Directory => ScanCodeGrouping::Directory,
+                },
+                directory_depth: depth,
+            };
+            let inventory = scancode_results::inventory_from_scancode(&results, &scan_options)
+                .unwrap_or_else(|e| exit_with_error(e));
+            run_audit(&parser, inventory, &options);
+        }
+        Some(Command::Audit { command: AuditCommand::Spdx { document, options } }) => {
+            let parser = load_parser(&args.database, &args.overlays);
+            let spdx = spdx_document::read_spdx_document(&document).unwrap_or_else(|e| exit_with_error(e));
+            let corpus = load_corpus(&parser, &options);
+            let report = spdx_document::analyze_spdx_document(&parser, &spdx, &load_policy(&options.policy), corpus.as_ref(), &document.display().to_string());
+            write_spdx_output(&parser, &report.audit, &options);
+            write_notice_output(&parser, &report.audit, &options);
+            match options.format {
+                OutputFormat::Text => print!("{}", report),
+                OutputFormat::Json => print_json(&report),
+            }
+            if report.audit.verdict == license_audit::AuditVerdict::Fail {
+                process::exit(1);
+            }
+        }
+        Some(Command::ReuseLint { dir, excluded, format }) => {
+            let parser = LicenseExpressionParser::with_database(&args.database, &args.overlays)
+                .unwrap_or_else(|e| exit_with_error(e));
+            let report = reuse_lint::lint_reuse(&parser, &dir, &excluded);
+            match format {
+                OutputFormat::Text => print!("{}", report),
+                OutputFormat::Json => print_json(&report),
+            }
+            if !report.is_compliant() {
+                process::exit(1);
+            }
+        }
+        Some(Command::Outbound { expressions, from_file, candidates, format }) => {
+            let parser = load_parser(&args.database, &args.overlays);
+            let mut inputs: Vec<OutboundInput> = expressions.into_iter()
+                .map(|expression| OutboundInput { name: expression.clone(), expression })
+                .collect();
+            if let Some(path) = from_file {
+                let content = read_input(&path);
+                for line in content.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+                    let (name, expression) = line.split_once(": ").unwrap_or((line, line));
+                    inputs.push(OutboundInput { name: name.trim().to_string(), expression: expression.trim().to_string() });
+                }
+            }
+            if inputs.is_empty() {
+                exit_with_error("no dependency expressions given");
+            }
+            let report = outbound_license::recommend_outbound(&parser, &inputs, &candidates).unwrap_or_else(|e| exit_with_error(e));
+            match format {
+                OutputFormat::Text => print!("{}", report),
+                OutputFormat::Json => print_json(&report),
+            }
+        }
+        Some(Command::Diff { old, new, fail_on_increase, format }) => {
+            let parser = load_parser(&args.database, &args.overlays);
+            let old = audit_diff::load_audit(&parser, &old).unwrap_or_else(|e| exit_with_error(e));
+            let new = audit_diff::load_audit(&parser, &new).unwrap_or_else(|e| exit_with_error(e));
+            let diff = audit_diff::diff_audits(&old, &new);
+            match format {
+                OutputFormat::Text => print!("{}", diff),
+                OutputFormat::Json => print_json(&diff),
+            }
+            if fail_on_increase && diff.has_risk_increase() {
+                process::exit(1);
+            }
+        }
+        Some(Command::Graph { graph, format }) => {
+            let parser = load_parser(&args.database, &args.overlays);
+            let graph = LicenseGraph::load_file(&graph).unwrap_or_else(|e| exit_with_error(e));
+            let report = license_graph::analyze_license_graph(&parser, &graph);
+            match format {
+                OutputFormat::Text => print!("{}", report),
+                OutputFormat::Json => print_json(&report),
+            }
+        }
+        Some(Command::EnrichCyclonedx { bom, output, policy }) => {
+            let parser = load_parser(&args.database, &args.overlays);
+            let enrichment = cyclonedx_bom::enrich_cyclonedx(&parser, &bom, &load_policy(&policy))
+                .unwrap_or_else(|e| exit_with_error(e));
+            match output {
+                Some(path) => {
+                    fs::write(&path, &enrichment.content).unwrap_or_else(|e| exit_with_error(format!("{}: {}", path.display(), e)));
+                    println!("Annotated {} of {} components in {} (policy: {})",
+                        enrichment.annotated, enrichment.report.components.len(), path.display(), enrichment.report.verdict);
+                }
+                None => print!("{}", enrichment.content),
+            }
+        }
+        Some(Command::DebianCopyright { path, format }) => {
+            let parser = load_parser(&args.database, &args.overlays);
+            let report = debian_copyright::scan_debian_copyright(&parser, &path);
+            match format {
+                OutputFormat::Text => print!("{}", report),
+                OutputFormat::Json => print_json(&report),
+            }
+        }
+        None => {
+            let parser = LicenseExpressionParser::with_database(&args.database, &args.overlays)
+                .unwrap_or_else(|e| exit_with_error(e));
+            let expression = args.license_expression.unwrap_or_default();
+            let result = parser.analyze(&expression);
+            println!("{}", result);
+        }
+    }
+}
+
+fn run_db_diff(old: &Path, new: &Path, expressions: Option<&Path>, overlays: &[PathBuf]) {
+    let old_index = license_database::load_license_index(old).unwrap_or_else(|e| exit_with_error(e));
+    let new_index = license_database::load_license_index(new).unwrap_or_else(|e| exit_with_error(e));
+    let mut diff = license_database_diff::diff_license_indexes(&old_index, &new_index);
+
+    if let Some(path) = expressions {
+        let content = fs::read_to_string(path)
+            .unwrap_or_else(|e| exit_with_error(format!("{}: {}", path.display(), e)));
+        let expressions: Vec<String> = content.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(String::from)
+            .collect();
+
+        let old_parser = LicenseExpressionParser::with_database(old, overlays).unwrap_or_else(|e| exit_with_error(e));
+        let new_parser = LicenseExpressionParser::with_database(new, overlays).unwrap_or_else(|e| exit_with_error(e));
+        diff.add_expression_impact(&old_parser, &new_parser, &expressions);
+    }
+
+    print!("{}", diff);
+}
+
+/// Loads the policy file, if any, with `--fail-on` taking precedence over its fail_on, and the waiver baseline
+fn load_policy(options: &PolicyOptions) -> LicensePolicy {
+    let mut policy = match options.policy {
+        Some(ref path) => license_policy::load_policy_file(path).unwrap_or_else(|e| exit_with_error(e)),
+        None => LicensePolicy::default(),
+    };
+    if options.fail_on.is_some() {
+        policy.fail_on = options.fail_on.clone();
+    }
+    if let Some(ref path) = options.waivers {
+        policy.waivers = license_waivers::load_waiver_file(path).unwrap_or_else(|e| exit_with_error(e));
+    }
+    policy
+}
+
+fn load_corpus(parser: &LicenseExpressionParser, options: &AuditOptions) -> Option<LicenseTextCorpus> {
+    options.license_texts.as_ref()
+        .map(|dir| LicenseTextCorpus::load(dir, &parser.database()).unwrap_or_else(|e| exit_with_error(e)))
+}
+
+/// Writes the `--spdx-output` document for an audit report, if requested
+fn write_spdx_output(parser: &LicenseExpressionParser, report: &license_audit::AuditReport, options: &AuditOptions) {
+    let Some(ref path) = options.spdx_output else {
+        return;
+    };
+    let export_options = SpdxExportOptions {
+        document_name: None,
+        conclude_recommended: options.conclude_choice,
+        license_texts_dir: options.license_texts.clone(),
+    };
+    let document = spdx_export::export_spdx(parser, report, &export_options);
+    let json = serde_json::to_string_pretty(&document).unwrap_or_else(|e| exit_with_error(e));
+    fs::write(path, json + "\n").unwrap_or_else(|e| exit_with_error(format!("{}: {}", path.display(), e)));
+}
+
+/// Writes the `--notice-output` file for an audit report, if requested
+fn write_notice_output(parser: &LicenseExpressionParser, report: &license_audit::AuditReport, options: &AuditOptions) {
+    let Some(ref path) = options.notice_output else {
+        return;
+    };
+    let format = match options.notice_format {
+        Some(NoticeFileFormat::Text) => NoticeFormat::Text,
+        Some(NoticeFileFormat::Markdown) => NoticeFormat::Markdown,
+        Some(NoticeFileFormat::Html) => NoticeFormat::Html,
+        None => NoticeFormat::from_path(path),
+    };
+    let notice_options = NoticeOptions {
+        title: "Third-Party Notices".to_string(),
+        license_texts_dir: options.license_texts.clone().unwrap_or_else(|| PathBuf::from(DEFAULT_LICENSE_TEXTS_DIR)),
+    };
+    let notice = notice_file::generate_notice(parser, report, &notice_options);
+    fs::write(path, notice.render(format)).unwrap_or_else(|e| exit_with_error(format!("{}: {}", path.display(), e)));
+}
+
+fn run_audit(parser: &LicenseExpressionParser, mut inventory: Inventory, options: &AuditOptions) {
+    let policy = load_policy(&options.policy);
+    if let Some(corpus) = load_corpus(parser, options) {
+        license_audit::identify_license_files(&mut inventory, &corpus);
+    }
+
+    let report = license_audit::audit_inventory(parser, &inventory, &policy);
+    write_spdx_output(parser, &report, options);
+    write_notice_output(parser, &report, options);
+    let propagation = options.propagation.then(|| {
+        let linkage = match options.shipped_linkage {
+            ShippedLinkage::Static => Linkage::Static,
+            ShippedLinkage::Dynamic => Linkage::Dynamic,
+        };
+        license_graph::analyze_license_graph(parser, &LicenseGraph::from_inventory(&inventory, linkage))
+    });
+    let outbound = options.outbound.then(|| {
+        let inputs: Vec<OutboundInput> = report.components.iter()
+            .filter(|audit| audit.component.kind == DependencyKind::Normal)
+            .filter_map(|audit| audit.component.expression.as_ref().map(|expression| OutboundInput {
+                name: audit.component.display_name(),
+                expression: expression.clone(),
+            }))
+            .collect();
+        outbound_license::recommend_outbound(parser, &inputs, &[]).unwrap_or_else(|e| exit_with_error(e))
+    });
+    match options.format {
+        OutputFormat::Text => {
+            print!("{}", report);
+            if let Some(ref propagation) = propagation {
+                print!("\n{}", propagation);
+            }
+            if let Some(ref outbound) = outbound {
+                print!("\n{}", outbound);
+            }
+        }
+        OutputFormat::Json if propagation.is_none() && outbound.is_none() => print_json(&report),
+        OutputFormat::Json => {
+            let mut combined = serde_json::json!({ "audit": report });
+            if let Some(propagation) = propagation {
+                combined["propagation"] = serde_json::json!(propagation);
+            }
+            if let Some(outbound) = outbound {
+                combined["outbound"] = serde_json::json!(outbound);
+            }
+            print_json(&combined);
+        }
+    }
+    if report.verdict == license_audit::AuditVerdict::Fail {
+        process::exit(1);
+    }
+}
+
+fn load_parser(database: &Path, overlays: &[PathBuf]) -> LicenseExpressionParser {
+    LicenseExpressionParser::with_database(database, overlays).unwrap_or_else(|e| exit_with_error(e))
+}
+
+/// Reads a whole input file, or stdin for `-`
+fn read_input(path: &Path) -> String {
+    let mut content = String::new();
+    let result = if path == Path::new("-") {
+        io::stdin().read_to_string(&mut content).map(|_| ())
+    } else {
+        fs::read_to_string(path).map(|c| content = c)
+    };
+    result.unwrap_or_else(|e| exit_with_error(format!("{}: {}", path.display(), e)));
+    content
+}
+
+fn print_json<T: serde::Serialize>(value: &T) {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => println!("{}", json),
+        Err(e) => exit_with_error(e),
+    }
+}
+
+fn exit_with_error<E: std::fmt::Display>(error: E) -> ! {
+    eprintln!("Error: {}", error);
+    process::exit(1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, clap::Error> {
+        Args::try_parse_from(std::iter::once("license-expression-copyleft").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn cli_is_well_formed() {
+        Args::command().debug_assert();
+    }
+
+    #[test]
+    fn global_options_before_subcommand() {
+        let args = parse(&["--database", "other.json", "db", "info"]).unwrap();
+        assert_eq!(args.database, PathBuf::from("other.json"));
+        assert!(matches!(args.command, Some(Command::Db { command: DbCommand::Info })));
+        assert!(args.license_expression.is_none());
+
+        let args = parse(&["--overlay", "f.json", "list", "--limit", "1"]).unwrap();
+        assert_eq!(args.overlays, vec![PathBuf::from("f.json")]);
+        assert!(matches!(args.command, Some(Command::List { limit: Some(1), .. })));
+        assert!(args.license_expression.is_none());
+    }
+
+    #[test]
+    fn global_options_after_subcommand() {
+        let args = parse(&["list", "--overlay", "f.json", "--database", "other.json"]).unwrap();
+        assert_eq!(args.database, PathBuf::from("other.json"));
+        assert_eq!(args.overlays, vec![PathBuf::from("f.json")]);
+    }
+
+    #[test]
+    fn bare_expression_with_global_options() {
+        let args = parse(&["--overlay", "f.json", "MIT OR Apache-2.0"]).unwrap();
+        assert_eq!(args.license_expression.as_deref(), Some("MIT OR Apache-2.0"));
+        assert!(args.command.is_none());
+        assert!(parse(&[]).is_err());
+    }
 
-    let parser = LicenseExpressionParser::new();
-    let result = parser.analyze(&args.license_expression);
-    println!("{}", result);
+    #[test]
+    fn list_rejects_the_cla_category() {
+        let error = parse(&["list", "--category", "CLA"]).err().unwrap();
+        assert!(error.to_string().contains("loaded as Proprietary Free"), "{}", error);
+        assert!(parse(&["list", "--category", "copyleft-limited", "--category", "Proprietary Free"]).is_ok());
+    }
 }